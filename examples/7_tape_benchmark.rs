@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+use f64ad_core::ComplexField;
+use f64ad_core::f64ad::{f64ad, GlobalComputationGraph, GlobalComputationGraphs};
+
+// Records a large graph and times derivative sweeps over it.  Run with `--release`.
+//
+// Everything reported is measured when the example runs.  To compare tape layouts, run this example
+// on both versions of the crate on the same machine.
+fn benchmark(name: &str, computation_graph: GlobalComputationGraph) {
+    computation_graph.reset();
+
+    let inputs: Vec<f64ad> = (0..1000).map(|i| computation_graph.spawn_variable(1.0 + i as f64 * 0.001)).collect();
+
+    let start = Instant::now();
+    let mut result = f64ad::f64(0.0);
+    for _ in 0..500 {
+        for x in &inputs {
+            result += x.sin() * *x + x.sqrt() * 0.5;
+        }
+    }
+    let record_time = start.elapsed();
+
+    let start = Instant::now();
+    let derivatives = result.backwards_mode_grad(false);
+    let backwards_time = start.elapsed();
+
+    let start = Instant::now();
+    let _ = inputs[0].forward_mode_grad(false);
+    let forward_time = start.elapsed();
+
+    let num_nodes = computation_graph.num_nodes();
+    let size_in_bytes = computation_graph.size_in_bytes();
+    println!("{}: {} nodes, {} bytes ({:.1} bytes per node)", name, num_nodes, size_in_bytes, size_in_bytes as f64 / num_nodes as f64);
+    println!("    record {:?}, backwards {:?}, forward {:?}", record_time, backwards_time, forward_time);
+    println!("    per node: record {:.1}ns, backwards {:.1}ns, forward {:.1}ns", nanos_per_node(record_time, num_nodes), nanos_per_node(backwards_time, num_nodes), nanos_per_node(forward_time, num_nodes));
+    println!("    d_result_d_input_0: {:?}", derivatives.wrt(&inputs[0]));
+}
+
+fn nanos_per_node(duration: Duration, num_nodes: usize) -> f64 {
+    duration.as_nanos() as f64 / num_nodes as f64
+}
+
+fn main() {
+    println!("size of f64ad: {} bytes", std::mem::size_of::<f64ad>());
+    benchmark("ComputationGraphF", GlobalComputationGraphs::get(None, None));
+    benchmark("ComputationGraph1", GlobalComputationGraphs::get_first_order(None, None));
+}
@@ -0,0 +1,234 @@
+// Batched evaluation of locked graphs.  The tape of a locked graph is evaluated on many input points
+// at once, a chunk of points at a time, with the values (and adjoints) of each node for all points
+// of a chunk stored next to each other, so the loops over points can be auto-vectorized.  Chunks
+// can be split across threads with rayon.
+
+use rayon::prelude::*;
+use crate::f64ad::{compute_value_f64, f64ad, ComputationGraph, DerivativePolicy, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::tape::{local_partials_from_values, Operand};
+
+/// Number of points evaluated together.
+const CHUNK_SIZE: usize = 64;
+
+/// Values (and, if requested, gradients) of the outputs of a locked graph at many input points.
+#[derive(Clone, Debug)]
+pub struct BatchOutput {
+    num_points: usize,
+    num_inputs: usize,
+    num_outputs: usize,
+    /// `values[point * num_outputs + output]`
+    values: Vec<f64>,
+    /// `gradients[(point * num_outputs + output) * num_inputs + input]`
+    gradients: Option<Vec<f64>>
+}
+impl BatchOutput {
+    #[inline(always)]
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
+    #[inline(always)]
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+    #[inline(always)]
+    pub fn num_outputs(&self) -> usize {
+        self.num_outputs
+    }
+    #[inline(always)]
+    pub fn value(&self, point: usize, output: usize) -> f64 {
+        self.values[point * self.num_outputs + output]
+    }
+    /// Gradient of `output` with respect to the inputs at `point`.
+    #[inline(always)]
+    pub fn gradient(&self, point: usize, output: usize) -> &[f64] {
+        let gradients = self.gradients.as_ref().expect("gradients were not computed in this batch evaluation.");
+        let start = (point * self.num_outputs + output) * self.num_inputs;
+        &gradients[start..start + self.num_inputs]
+    }
+    #[inline(always)]
+    pub fn values(&self) -> &Vec<f64> {
+        &self.values
+    }
+    #[inline(always)]
+    pub fn gradients(&self) -> Option<&Vec<f64>> {
+        self.gradients.as_ref()
+    }
+}
+
+/// The nodes of a locked graph, detached from the graph so it can be shared across threads.
+struct BatchProgram {
+    nodes: Vec<(NodeTypeClass, NodeOperandsMode, [Option<Operand>; 2])>,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    derivative_policy: DerivativePolicy
+}
+
+/// `inputs` holds one row of `num_inputs` values per point, with the inputs in the order they were
+/// spawned.
+pub(crate) fn evaluate_batch(computation_graph: &ComputationGraph, outputs: Vec<usize>, inputs: &[f64], gradients: bool, parallel: bool) -> BatchOutput {
+    let program = computation_graph.with_tape(|tape| {
+        let nodes: Vec<(NodeTypeClass, NodeOperandsMode, [Option<Operand>; 2])> = (0..tape.len()).map(|node_idx| {
+            let node_type_class = tape.node_type_class(node_idx);
+            if let NodeTypeClass::Manual { .. } | NodeTypeClass::Composite { .. } = node_type_class { panic!("cannot evaluate {:?} nodes on a locked graph.", node_type_class); }
+            (node_type_class, tape.node_operands_mode(node_idx), tape.operands(node_idx))
+        }).collect();
+        let inputs = (0..tape.len()).filter(|x| nodes[*x].0 == NodeTypeClass::InputVariable).collect();
+        BatchProgram { nodes, inputs, outputs, derivative_policy: computation_graph.derivative_policy() }
+    });
+
+    let num_inputs = program.inputs.len();
+    let num_outputs = program.outputs.len();
+    assert!(num_inputs > 0, "locked graph has no inputs.");
+    assert_eq!(inputs.len() % num_inputs, 0, "number of input values ({}) is not a multiple of the number of inputs ({}).", inputs.len(), num_inputs);
+    let num_points = inputs.len() / num_inputs;
+
+    let num_chunks = num_points.div_ceil(CHUNK_SIZE);
+    let run_chunk = |chunk_idx: usize| {
+        let end = ((chunk_idx + 1) * CHUNK_SIZE).min(num_points);
+        program.evaluate_chunk(&inputs[chunk_idx * CHUNK_SIZE * num_inputs..end * num_inputs], gradients)
+    };
+    let chunks: Vec<(Vec<f64>, Vec<f64>)> = if parallel { (0..num_chunks).into_par_iter().map(run_chunk).collect() } else { (0..num_chunks).map(run_chunk).collect() };
+
+    let mut values = Vec::with_capacity(num_points * num_outputs);
+    let mut gradient_values = Vec::with_capacity(if gradients { num_points * num_outputs * num_inputs } else { 0 });
+    for (chunk_values, chunk_gradients) in chunks {
+        values.extend(chunk_values);
+        gradient_values.extend(chunk_gradients);
+    }
+
+    BatchOutput {
+        num_points,
+        num_inputs,
+        num_outputs,
+        values,
+        gradients: if gradients { Some(gradient_values) } else { None }
+    }
+}
+
+impl BatchProgram {
+    /// Returns the values and, if `gradients` is true, the gradients of the outputs at the points
+    /// of the chunk, laid out as in `BatchOutput`.
+    fn evaluate_chunk(&self, inputs: &[f64], gradients: bool) -> (Vec<f64>, Vec<f64>) {
+        let num_inputs = self.inputs.len();
+        let num_outputs = self.outputs.len();
+        let w = inputs.len() / num_inputs;
+        let num_nodes = self.nodes.len();
+
+        // `values[node_idx * w + lane]` is the value of a node at point `lane` of the chunk.
+        let mut values = vec![0.0; num_nodes * w];
+        let mut lhs_constants = vec![0.0; w];
+        let mut rhs_constants = vec![0.0; w];
+        let mut input_idx = 0;
+        for node_idx in 0..num_nodes {
+            let (node_type_class, _, operands) = self.nodes[node_idx];
+            let (before, rest) = values.split_at_mut(node_idx * w);
+            let out = &mut rest[..w];
+            if node_type_class == NodeTypeClass::InputVariable {
+                for (lane, o) in out.iter_mut().enumerate() { *o = inputs[lane * num_inputs + input_idx]; }
+                input_idx += 1;
+                continue;
+            }
+            let a = lanes(before, operands[0], w, &mut lhs_constants);
+            let b = operands[1].map(|x| lanes(before, Some(x), w, &mut rhs_constants));
+            compute_values(node_type_class, a, b, out);
+        }
+
+        let mut values_out = vec![0.0; w * num_outputs];
+        for (lane, point_values) in values_out.chunks_mut(num_outputs).enumerate() {
+            for (output_idx, output) in self.outputs.iter().enumerate() { point_values[output_idx] = values[output * w + lane]; }
+        }
+        if !gradients { return (values_out, vec![]); }
+
+        let mut gradients_out = vec![0.0; w * num_outputs * num_inputs];
+        let mut adjoints = vec![0.0; num_nodes * w];
+        let mut lhs_partials = vec![0.0; w];
+        let mut rhs_partials = vec![0.0; w];
+        for (output_idx, output) in self.outputs.iter().enumerate() {
+            adjoints.iter_mut().for_each(|x| *x = 0.0);
+            adjoints[output * w..(output + 1) * w].iter_mut().for_each(|x| *x = 1.0);
+
+            // sweeps over the whole tape, like `Tape::backwards_mode_grad`, so gradients match the ones
+            // from `backwards_mode_grad` exactly.
+            for node_idx in (0..num_nodes).rev() {
+                let (node_type_class, node_operands_mode, operands) = self.nodes[node_idx];
+                if node_type_class == NodeTypeClass::InputVariable || node_operands_mode == NodeOperandsMode::NoParents { continue; }
+                let (before, rest) = adjoints.split_at_mut(node_idx * w);
+                let node_adjoints = &rest[..w];
+
+                let a = lanes(&values, operands[0], w, &mut lhs_constants);
+                let b = operands[1].map(|x| lanes(&values, Some(x), w, &mut rhs_constants));
+                compute_local_partials(node_type_class, node_operands_mode, a, b, &self.derivative_policy, &mut lhs_partials, &mut rhs_partials);
+
+                for (operand, partials) in operands.iter().zip([&lhs_partials, &rhs_partials]) {
+                    if let Some(Operand::Variable(parent)) = operand {
+                        let parent_adjoints = &mut before[parent * w..(parent + 1) * w];
+                        for ((p, n), d) in parent_adjoints.iter_mut().zip(node_adjoints).zip(partials.iter()) { *p += n * d; }
+                    }
+                }
+            }
+
+            for (input_idx, input) in self.inputs.iter().enumerate() {
+                for lane in 0..w {
+                    gradients_out[(lane * num_outputs + output_idx) * num_inputs + input_idx] = adjoints[input * w + lane];
+                }
+            }
+        }
+
+        (values_out, gradients_out)
+    }
+}
+
+/// The values of an operand for all points of a chunk.  Constants are broadcast into `constants`.
+fn lanes<'a>(values: &'a [f64], operand: Option<Operand>, w: usize, constants: &'a mut [f64]) -> &'a [f64] {
+    match operand.unwrap() {
+        Operand::Variable(parent) => { &values[parent * w..(parent + 1) * w] }
+        Operand::Constant(constant) => {
+            constants.iter_mut().for_each(|x| *x = constant);
+            constants
+        }
+    }
+}
+
+/// Same values as `compute_value_f64`, with the arithmetic operations written as plain loops.
+fn compute_values(node_type_class: NodeTypeClass, a: &[f64], b: Option<&[f64]>, out: &mut [f64]) {
+    match (node_type_class, b) {
+        (NodeTypeClass::Add, Some(b)) => { for ((o, x), y) in out.iter_mut().zip(a).zip(b) { *o = x + y; } }
+        (NodeTypeClass::Sub, Some(b)) => { for ((o, x), y) in out.iter_mut().zip(a).zip(b) { *o = x - y; } }
+        (NodeTypeClass::Mul, Some(b)) => { for ((o, x), y) in out.iter_mut().zip(a).zip(b) { *o = x * y; } }
+        (NodeTypeClass::Div, Some(b)) => { for ((o, x), y) in out.iter_mut().zip(a).zip(b) { *o = x / y; } }
+        (NodeTypeClass::Neg, None) => { for (o, x) in out.iter_mut().zip(a) { *o = -x; } }
+        (_, None) => { for (o, x) in out.iter_mut().zip(a) { *o = compute_value_f64(f64ad::f64(*x), None, node_type_class); } }
+        (_, Some(b)) => { for ((o, x), y) in out.iter_mut().zip(a).zip(b) { *o = compute_value_f64(f64ad::f64(*x), Some(f64ad::f64(*y)), node_type_class); } }
+    }
+}
+
+/// Same slot aligned local partials as `local_partials_from_values`, with the arithmetic operations written
+/// as plain loops.
+fn compute_local_partials(node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, a: &[f64], b: Option<&[f64]>, derivative_policy: &DerivativePolicy, lhs_partials: &mut [f64], rhs_partials: &mut [f64]) {
+    match (node_type_class, b) {
+        (NodeTypeClass::Add, _) => {
+            lhs_partials.iter_mut().for_each(|x| *x = 1.0);
+            rhs_partials.iter_mut().for_each(|x| *x = 1.0);
+        }
+        (NodeTypeClass::Sub, _) => {
+            lhs_partials.iter_mut().for_each(|x| *x = 1.0);
+            rhs_partials.iter_mut().for_each(|x| *x = -1.0);
+        }
+        (NodeTypeClass::Neg, _) => { lhs_partials.iter_mut().for_each(|x| *x = -1.0); }
+        (NodeTypeClass::Mul, Some(b)) => {
+            lhs_partials.copy_from_slice(b);
+            rhs_partials.copy_from_slice(a);
+        }
+        (NodeTypeClass::Div, Some(b)) => {
+            for (d, y) in lhs_partials.iter_mut().zip(b) { *d = 1.0 / y; }
+            for ((d, x), y) in rhs_partials.iter_mut().zip(a).zip(b) { *d = -x / (y * y); }
+        }
+        _ => {
+            for lane in 0..a.len() {
+                let [lhs_partial, rhs_partial] = local_partials_from_values(node_type_class, node_operands_mode, a[lane], b.map(|x| x[lane]), derivative_policy);
+                lhs_partials[lane] = lhs_partial;
+                rhs_partials[lane] = rhs_partial;
+            }
+        }
+    }
+}
@@ -0,0 +1,779 @@
+// Complex automatic differentiation.  A `c64ad` is a pair of `f64ad`s, its real and imaginary
+// parts, so complex operations are recorded as real operations on the same computation graphs as
+// `f64ad` and every derivative sweep applies to them unchanged.  Complex derivatives are recovered
+// from the real partial derivatives of the parts with Wirtinger calculus: for `z = x + iy` and
+// `f = u + iv`,
+//
+//     ∂f/∂z = ((∂u/∂x + ∂v/∂y) + i(∂v/∂x - ∂u/∂y)) / 2
+//     ∂f/∂z̄ = ((∂u/∂x - ∂v/∂y) + i(∂v/∂x + ∂u/∂y)) / 2
+//
+// If `f` is holomorphic in `z`, `∂f/∂z̄` is zero and `∂f/∂z` is the usual complex derivative.  If
+// `f` is real valued, e.g., a loss, `2 ∂f/∂z̄` is the direction of steepest ascent in `z`.
+//
+// Elementary functions use their principal branches, with the branch cuts of `num_complex`.
+//
+// The graphs hold real nodes only, rather than nodes carrying complex values.  This costs tape:
+// a complex addition records two nodes and a complex multiplication six, where complex nodes
+// would record one each.  In exchange, every graph type, derivative sweep, derivative policy,
+// higher order derivative, lock, and generated function works on `c64ad` without a complex
+// variant of its own, and a complex node would have to carry both Wirtinger partials on each
+// edge anyway, since most functions of interest, e.g., losses, are not holomorphic.
+
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign};
+use nalgebra::{Complex, SimdValue};
+use num_traits::{FromPrimitive, Num, One, Zero};
+use simba::scalar::{ComplexField, Field, SubsetOf};
+use crate::f64ad::{BackwardsModeGradOutput, f64ad, F64adType, ForwardModeGradOutput};
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct c64ad {
+    pub re: f64ad,
+    pub im: f64ad
+}
+impl c64ad {
+    #[inline(always)]
+    pub fn new(re: f64ad, im: f64ad) -> Self {
+        Self { re, im }
+    }
+    #[inline(always)]
+    pub fn i() -> Self {
+        Self::new(f64ad::f64(0.0), f64ad::f64(1.0))
+    }
+    #[inline(always)]
+    pub fn value(&self) -> Complex<f64> {
+        Complex::new(self.re.value(), self.im.value())
+    }
+    /// Returns the value of this `c64ad` as a constant, i.e., no derivatives will flow back through
+    /// the result (stop-gradient).
+    #[inline(always)]
+    pub fn detach(&self) -> c64ad {
+        Self::new(self.re.detach(), self.im.detach())
+    }
+    #[inline(always)]
+    pub fn from_polar(r: f64ad, theta: f64ad) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+    /// Sweeps forward from the real and imaginary parts of this input.  Parts that are constants
+    /// are skipped.
+    pub fn forward_mode_grad(&self, add_to_computation_graph: bool) -> ComplexForwardModeGradOutput {
+        ComplexForwardModeGradOutput {
+            re: sweep_if_variable(&self.re, |x| x.forward_mode_grad(add_to_computation_graph)),
+            im: sweep_if_variable(&self.im, |x| x.forward_mode_grad(add_to_computation_graph))
+        }
+    }
+    /// Sweeps backwards from the real and imaginary parts of this output.  Parts that are constants
+    /// are skipped, so the derivatives of a real valued output can be taken with
+    /// `c64ad::from(output).backwards_mode_grad(..)`.
+    pub fn backwards_mode_grad(&self, add_to_computation_graph: bool) -> ComplexBackwardsModeGradOutput {
+        ComplexBackwardsModeGradOutput {
+            re: sweep_if_variable(&self.re, |x| x.backwards_mode_grad(add_to_computation_graph)),
+            im: sweep_if_variable(&self.im, |x| x.backwards_mode_grad(add_to_computation_graph))
+        }
+    }
+    #[inline(always)]
+    fn mul_i(self) -> Self {
+        Self::new(-self.im, self.re)
+    }
+}
+
+fn sweep_if_variable<G, F: Fn(&f64ad) -> G>(v: &f64ad, f: F) -> Option<G> {
+    if v.map_to_type() == F64adType::F64 { None } else { Some(f(v)) }
+}
+
+/// `(∂f/∂z, ∂f/∂z̄)` from the partial derivatives `(∂u/∂x, ∂u/∂y, ∂v/∂x, ∂v/∂y)`.
+fn wirtinger_derivatives(u_x: f64ad, u_y: f64ad, v_x: f64ad, v_y: f64ad) -> (c64ad, c64ad) {
+    let d_z = c64ad::new((u_x + v_y) * 0.5, (v_x - u_y) * 0.5);
+    let d_z_conjugate = c64ad::new((u_x - v_y) * 0.5, (v_x + u_y) * 0.5);
+    (d_z, d_z_conjugate)
+}
+
+#[derive(Clone, Debug)]
+pub struct ComplexForwardModeGradOutput {
+    re: Option<ForwardModeGradOutput>,
+    im: Option<ForwardModeGradOutput>
+}
+impl ComplexForwardModeGradOutput {
+    /// `∂output/∂z`, the complex derivative if `output` is holomorphic in the input.
+    pub fn wrt(&self, output: &c64ad) -> c64ad {
+        self.wirtinger(output).0
+    }
+    /// `(∂output/∂z, ∂output/∂z̄)`.
+    pub fn wirtinger(&self, output: &c64ad) -> (c64ad, c64ad) {
+        let partial = |grad: &Option<ForwardModeGradOutput>, v: &f64ad| {
+            match grad {
+                Some(grad) if v.map_to_type() != F64adType::F64 => { grad.wrt(v) }
+                _ => { f64ad::f64(0.0) }
+            }
+        };
+        wirtinger_derivatives(partial(&self.re, &output.re), partial(&self.im, &output.re), partial(&self.re, &output.im), partial(&self.im, &output.im))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ComplexBackwardsModeGradOutput {
+    re: Option<BackwardsModeGradOutput>,
+    im: Option<BackwardsModeGradOutput>
+}
+impl ComplexBackwardsModeGradOutput {
+    /// `∂output/∂input`, the complex derivative if the output is holomorphic in `input`.
+    pub fn wrt(&self, input: &c64ad) -> c64ad {
+        self.wirtinger(input).0
+    }
+    /// `(∂output/∂input, ∂output/∂input̄)`.
+    pub fn wirtinger(&self, input: &c64ad) -> (c64ad, c64ad) {
+        wirtinger_derivatives(self.partial_re(&input.re), self.partial_re(&input.im), self.partial_im(&input.re), self.partial_im(&input.im))
+    }
+    /// Derivative of the output with respect to a real input, `∂u/∂x + i∂v/∂x`.
+    pub fn wrt_real(&self, input: &f64ad) -> c64ad {
+        c64ad::new(self.partial_re(input), self.partial_im(input))
+    }
+    fn partial_re(&self, input: &f64ad) -> f64ad {
+        partial(&self.re, input)
+    }
+    fn partial_im(&self, input: &f64ad) -> f64ad {
+        partial(&self.im, input)
+    }
+}
+
+fn partial(grad: &Option<BackwardsModeGradOutput>, input: &f64ad) -> f64ad {
+    match grad {
+        Some(grad) if input.map_to_type() != F64adType::F64 => { grad.wrt(input) }
+        _ => { f64ad::f64(0.0) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl ComplexField for c64ad {
+    type RealField = f64ad;
+
+    fn from_real(re: Self::RealField) -> Self { Self::new(re, f64ad::f64(0.0)) }
+
+    fn real(self) -> Self::RealField { self.re }
+
+    fn imaginary(self) -> Self::RealField { self.im }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn modulus(self) -> Self::RealField { self.modulus_squared().sqrt() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn modulus_squared(self) -> Self::RealField { self.re * self.re + self.im * self.im }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn argument(self) -> Self::RealField { self.im.atan2(self.re) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn norm1(self) -> Self::RealField { self.re.abs() + self.im.abs() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn scale(self, factor: Self::RealField) -> Self { self * factor }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn unscale(self, factor: Self::RealField) -> Self { self / factor }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn floor(self) -> Self { Self::new(self.re.floor(), self.im.floor()) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn ceil(self) -> Self { Self::new(self.re.ceil(), self.im.ceil()) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn round(self) -> Self { Self::new(self.re.round(), self.im.round()) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn trunc(self) -> Self { Self::new(self.re.trunc(), self.im.trunc()) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn fract(self) -> Self { Self::new(self.re.fract(), self.im.fract()) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul_add(self, a: Self, b: Self) -> Self { self * a + b }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn abs(self) -> Self::RealField { self.modulus() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn hypot(self, other: Self) -> Self::RealField { (self.modulus_squared() + other.modulus_squared()).sqrt() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn recip(self) -> Self {
+        let d = self.modulus_squared();
+        Self::new(self.re / d, -self.im / d)
+    }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn conjugate(self) -> Self { Self::new(self.re, -self.im) }
+
+    // sin(a + bi) = sin(a)cosh(b) + i cos(a)sinh(b)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sin(self) -> Self { Self::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh()) }
+
+    // cos(a + bi) = cos(a)cosh(b) - i sin(a)sinh(b)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn cos(self) -> Self { Self::new(self.re.cos() * self.im.cosh(), -(self.re.sin() * self.im.sinh())) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sin_cos(self) -> (Self, Self) { (self.sin(), self.cos()) }
+
+    // tan(a + bi) = (sin(2a) + i sinh(2b)) / (cos(2a) + cosh(2b))
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn tan(self) -> Self {
+        let (two_re, two_im) = (self.re * 2.0, self.im * 2.0);
+        Self::new(two_re.sin(), two_im.sinh()) / (two_re.cos() + two_im.cosh())
+    }
+
+    // asin(z) = -i ln(sqrt(1 - z^2) + iz)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn asin(self) -> Self { -(((1.0 - self * self).sqrt() + self.mul_i()).ln().mul_i()) }
+
+    // acos(z) = -i ln(i sqrt(1 - z^2) + z)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn acos(self) -> Self { -(((1.0 - self * self).sqrt().mul_i() + self).ln().mul_i()) }
+
+    // atan(z) = i (ln(1 - iz) - ln(1 + iz)) / 2
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn atan(self) -> Self {
+        let iz = self.mul_i();
+        ((1.0 - iz).ln() - (1.0 + iz).ln()).mul_i() * 0.5
+    }
+
+    // sinh(a + bi) = sinh(a)cos(b) + i cosh(a)sin(b)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sinh(self) -> Self { Self::new(self.re.sinh() * self.im.cos(), self.re.cosh() * self.im.sin()) }
+
+    // cosh(a + bi) = cosh(a)cos(b) + i sinh(a)sin(b)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn cosh(self) -> Self { Self::new(self.re.cosh() * self.im.cos(), self.re.sinh() * self.im.sin()) }
+
+    // tanh(a + bi) = (sinh(2a) + i sin(2b)) / (cosh(2a) + cos(2b))
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn tanh(self) -> Self {
+        let (two_re, two_im) = (self.re * 2.0, self.im * 2.0);
+        Self::new(two_re.sinh(), two_im.sin()) / (two_re.cosh() + two_im.cos())
+    }
+
+    // asinh(z) = ln(z + sqrt(z^2 + 1))
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn asinh(self) -> Self { (self + (self * self + 1.0).sqrt()).ln() }
+
+    // acosh(z) = 2 ln(sqrt((z + 1) / 2) + sqrt((z - 1) / 2))
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn acosh(self) -> Self { (((self + 1.0) * 0.5).sqrt() + ((self - 1.0) * 0.5).sqrt()).ln() * 2.0 }
+
+    // atanh(z) = (ln(1 + z) - ln(1 - z)) / 2
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn atanh(self) -> Self { ((1.0 + self).ln() - (1.0 - self).ln()) * 0.5 }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn log(self, base: Self::RealField) -> Self { self.ln() / base.ln() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn log2(self) -> Self { self.ln() / std::f64::consts::LN_2 }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn log10(self) -> Self { self.ln() / std::f64::consts::LN_10 }
+
+    // ln(z) = ln|z| + i arg(z), with ln|z| = ln(|z|^2) / 2 so there is no square root.
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn ln(self) -> Self { Self::new(self.modulus_squared().ln() * 0.5, self.argument()) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn ln_1p(self) -> Self { (1.0 + self).ln() }
+
+    // sqrt(r e^(it)) = sqrt(r) e^(it / 2)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sqrt(self) -> Self { Self::from_polar(self.modulus().sqrt(), self.argument() * 0.5) }
+
+    // e^(a + bi) = e^a (cos(b) + i sin(b))
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn exp(self) -> Self { Self::from_polar(self.re.exp(), self.im) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn exp2(self) -> Self { (self * std::f64::consts::LN_2).exp() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn exp_m1(self) -> Self { self.exp() - 1.0 }
+
+    /// Repeated squaring, so integer powers are exact products and differentiable at zero.
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn powi(self, n: i32) -> Self {
+        let mut out = Self::one();
+        let mut base = self;
+        let mut e = n.unsigned_abs();
+        while e > 0 {
+            if e & 1 == 1 { out *= base; }
+            e >>= 1;
+            if e > 0 { base = base * base; }
+        }
+        if n < 0 { out.recip() } else { out }
+    }
+
+    // (r e^(it))^n = r^n e^(itn)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn powf(self, n: Self::RealField) -> Self { Self::from_polar(self.modulus().powf(n), self.argument() * n) }
+
+    // z^w = e^(w ln(z))
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn powc(self, n: Self) -> Self { (n * self.ln()).exp() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn cbrt(self) -> Self { self.powf(f64ad::f64(1.0 / 3.0)) }
+
+    fn is_finite(&self) -> bool { self.re.value().is_finite() && self.im.value().is_finite() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn try_sqrt(self) -> Option<Self> { Some(self.sqrt()) }
+}
+
+impl Field for c64ad {}
+
+impl SimdValue for c64ad {
+    type Element = c64ad;
+    type SimdBool = bool;
+
+    fn lanes() -> usize {
+        1
+    }
+
+    fn splat(val: Self::Element) -> Self {
+        val
+    }
+
+    fn extract(&self, _: usize) -> Self::Element {
+        *self
+    }
+
+    unsafe fn extract_unchecked(&self, _: usize) -> Self::Element {
+        *self
+    }
+
+    fn replace(&mut self, _: usize, val: Self::Element) {
+        *self = val
+    }
+
+    unsafe fn replace_unchecked(&mut self, _: usize, val: Self::Element) {
+        *self = val
+    }
+
+    fn select(self, cond: Self::SimdBool, other: Self) -> Self {
+        if cond {
+            self
+        } else {
+            other
+        }
+    }
+}
+
+impl SubsetOf<c64ad> for c64ad {
+    fn to_superset(&self) -> c64ad {
+        *self
+    }
+
+    fn from_superset_unchecked(element: &c64ad) -> Self {
+        *element
+    }
+
+    fn is_in_subset(_element: &c64ad) -> bool {
+        true
+    }
+}
+
+impl SubsetOf<c64ad> for f64 {
+    fn to_superset(&self) -> c64ad {
+        c64ad::from(*self)
+    }
+
+    fn from_superset_unchecked(element: &c64ad) -> Self {
+        element.re.value()
+    }
+
+    fn is_in_subset(element: &c64ad) -> bool {
+        element.im.value() == 0.0
+    }
+}
+
+impl Zero for c64ad {
+    fn zero() -> Self {
+        Self::new(f64ad::f64(0.0), f64ad::f64(0.0))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.re.is_zero() && self.im.is_zero()
+    }
+}
+
+impl One for c64ad {
+    fn one() -> Self {
+        Self::new(f64ad::f64(1.0), f64ad::f64(0.0))
+    }
+}
+
+impl Num for c64ad {
+    type FromStrRadixErr = ();
+
+    /// Parses `a`, `bi`, or `a+bi`, like `num_complex`.
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        Complex::<f64>::from_str_radix(str, radix).map(Self::from).map_err(|_| ())
+    }
+}
+
+impl FromPrimitive for c64ad {
+    fn from_i64(n: i64) -> Option<Self> {
+        Some(Self::from(n as f64))
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        Some(Self::from(n as f64))
+    }
+
+    fn from_f64(n: f64) -> Option<Self> {
+        Some(Self::from(n))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl Add<c64ad> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add(self, rhs: c64ad) -> Self::Output {
+        c64ad::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+impl Add<f64ad> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add(self, rhs: f64ad) -> Self::Output {
+        c64ad::new(self.re + rhs, self.im)
+    }
+}
+impl Add<c64ad> for f64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add(self, rhs: c64ad) -> Self::Output {
+        c64ad::new(self + rhs.re, rhs.im)
+    }
+}
+impl Add<f64> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add(self, rhs: f64) -> Self::Output {
+        self + f64ad::f64(rhs)
+    }
+}
+impl Add<c64ad> for f64 {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add(self, rhs: c64ad) -> Self::Output {
+        f64ad::f64(self) + rhs
+    }
+}
+impl AddAssign for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl AddAssign<f64ad> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add_assign(&mut self, rhs: f64ad) {
+        *self = *self + rhs;
+    }
+}
+impl AddAssign<f64> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add_assign(&mut self, rhs: f64) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<c64ad> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub(self, rhs: c64ad) -> Self::Output {
+        c64ad::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+impl Sub<f64ad> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub(self, rhs: f64ad) -> Self::Output {
+        c64ad::new(self.re - rhs, self.im)
+    }
+}
+impl Sub<c64ad> for f64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub(self, rhs: c64ad) -> Self::Output {
+        c64ad::new(self - rhs.re, -rhs.im)
+    }
+}
+impl Sub<f64> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub(self, rhs: f64) -> Self::Output {
+        self - f64ad::f64(rhs)
+    }
+}
+impl Sub<c64ad> for f64 {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub(self, rhs: c64ad) -> Self::Output {
+        f64ad::f64(self) - rhs
+    }
+}
+impl SubAssign for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl SubAssign<f64ad> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub_assign(&mut self, rhs: f64ad) {
+        *self = *self - rhs;
+    }
+}
+impl SubAssign<f64> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub_assign(&mut self, rhs: f64) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<c64ad> for c64ad {
+    type Output = c64ad;
+
+    // (a + bi)(c + di) = (ac - bd) + (ad + bc)i
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul(self, rhs: c64ad) -> Self::Output {
+        c64ad::new(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+impl Mul<f64ad> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul(self, rhs: f64ad) -> Self::Output {
+        c64ad::new(self.re * rhs, self.im * rhs)
+    }
+}
+impl Mul<c64ad> for f64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul(self, rhs: c64ad) -> Self::Output {
+        rhs * self
+    }
+}
+impl Mul<f64> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul(self, rhs: f64) -> Self::Output {
+        self * f64ad::f64(rhs)
+    }
+}
+impl Mul<c64ad> for f64 {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul(self, rhs: c64ad) -> Self::Output {
+        rhs * f64ad::f64(self)
+    }
+}
+impl MulAssign for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+impl MulAssign<f64ad> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul_assign(&mut self, rhs: f64ad) {
+        *self = *self * rhs;
+    }
+}
+impl MulAssign<f64> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl Div<c64ad> for c64ad {
+    type Output = c64ad;
+
+    // (a + bi) / (c + di) = ((ac + bd) + (bc - ad)i) / (c^2 + d^2)
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div(self, rhs: c64ad) -> Self::Output {
+        let d = rhs.modulus_squared();
+        c64ad::new((self.re * rhs.re + self.im * rhs.im) / d, (self.im * rhs.re - self.re * rhs.im) / d)
+    }
+}
+impl Div<f64ad> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div(self, rhs: f64ad) -> Self::Output {
+        c64ad::new(self.re / rhs, self.im / rhs)
+    }
+}
+impl Div<c64ad> for f64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div(self, rhs: c64ad) -> Self::Output {
+        let d = rhs.modulus_squared();
+        c64ad::new(self * rhs.re / d, -(self * rhs.im) / d)
+    }
+}
+impl Div<f64> for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div(self, rhs: f64) -> Self::Output {
+        self / f64ad::f64(rhs)
+    }
+}
+impl Div<c64ad> for f64 {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div(self, rhs: c64ad) -> Self::Output {
+        f64ad::f64(self) / rhs
+    }
+}
+impl DivAssign for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+impl DivAssign<f64ad> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div_assign(&mut self, rhs: f64ad) {
+        *self = *self / rhs;
+    }
+}
+impl DivAssign<f64> for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
+impl Rem<c64ad> for c64ad {
+    type Output = c64ad;
+
+    /// Same as `num_complex`: `self - rhs * trunc(self / rhs)`, with `trunc` taken on each part.
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn rem(self, rhs: c64ad) -> Self::Output {
+        self - rhs * (self / rhs).trunc()
+    }
+}
+impl RemAssign for c64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl Neg for c64ad {
+    type Output = c64ad;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn neg(self) -> Self::Output {
+        c64ad::new(-self.re, -self.im)
+    }
+}
+
+impl PartialEq for c64ad {
+    fn eq(&self, other: &Self) -> bool {
+        self.re == other.re && self.im == other.im
+    }
+}
+
+impl Display for c64ad {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(&format!("{:?}", self)).expect("error");
+        Ok(())
+    }
+}
+
+impl From<f64ad> for c64ad {
+    fn from(a: f64ad) -> Self {
+        c64ad::new(a, f64ad::f64(0.0))
+    }
+}
+impl From<f64> for c64ad {
+    fn from(a: f64) -> Self {
+        c64ad::new(f64ad::f64(a), f64ad::f64(0.0))
+    }
+}
+impl From<Complex<f64>> for c64ad {
+    fn from(a: Complex<f64>) -> Self {
+        c64ad::new(f64ad::f64(a.re), f64ad::f64(a.im))
+    }
+}
+impl From<Complex<f64ad>> for c64ad {
+    fn from(a: Complex<f64ad>) -> Self {
+        c64ad::new(a.re, a.im)
+    }
+}
+impl From<c64ad> for Complex<f64ad> {
+    fn from(a: c64ad) -> Self {
+        Complex::new(a.re, a.im)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Complex;
+    use num_traits::Num;
+    use simba::scalar::SubsetOf;
+    use crate::f64ad::c64ad_mod::c64ad;
+    use crate::f64ad::{f64ad, GlobalComputationGraphs};
+
+    #[test]
+    fn from_str_radix() {
+        assert_eq!(c64ad::from_str_radix("1.5+2i", 10).unwrap().value(), Complex::new(1.5, 2.0));
+        assert_eq!(c64ad::from_str_radix("-3i", 10).unwrap().value(), Complex::new(0.0, -3.0));
+        assert!(c64ad::from_str_radix("1+", 10).is_err());
+        assert!(c64ad::from_str_radix("abc", 10).is_err());
+        assert!(c64ad::from_str_radix("", 10).is_err());
+    }
+
+    #[test]
+    fn real_subset() {
+        assert!(<f64 as SubsetOf<c64ad>>::is_in_subset(&c64ad::from(2.5)));
+        assert!(!<f64 as SubsetOf<c64ad>>::is_in_subset(&c64ad::new(f64ad::f64(2.5), f64ad::f64(1.0))));
+        assert_eq!(<f64 as SubsetOf<c64ad>>::from_superset(&c64ad::from(2.5)), Some(2.5));
+        assert_eq!(<f64 as SubsetOf<c64ad>>::from_superset(&c64ad::new(f64ad::f64(2.5), f64ad::f64(1.0))), None);
+    }
+
+    #[test]
+    fn tape_cost() {
+        let computation_graph = GlobalComputationGraphs::get(Some("c64ad_tests"), None);
+        computation_graph.reset();
+        let a = computation_graph.spawn_complex_variable(Complex::new(1.0, 2.0));
+        let b = computation_graph.spawn_complex_variable(Complex::new(3.0, -1.0));
+        assert_eq!(computation_graph.num_nodes(), 4);
+        let _ = a + b;
+        assert_eq!(computation_graph.num_nodes(), 6);
+        let _ = a * b;
+        assert_eq!(computation_graph.num_nodes(), 12);
+    }
+}
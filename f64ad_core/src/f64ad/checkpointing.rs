@@ -0,0 +1,312 @@
+// Checkpointing: trades compute for memory in backwards mode by only storing the inputs and outputs
+// of a region, then recomputing the inside of the region during the derivative sweep.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use crate::f64ad::{ComputationGraph, ComputationGraphType, f64ad, F64adType, GlobalComputationGraph};
+use crate::f64ad::composite_operations::CompositeOperation;
+
+type RegionFunction = Rc<dyn Fn(&[f64ad]) -> Vec<f64ad>>;
+
+/// Runs `f` on `inputs` as a checkpoint region.  Only the inputs and outputs of the region are
+/// stored on the computation graph; all intermediate nodes produced by `f` are discarded and are
+/// recomputed on a scratch graph when derivatives are requested.
+///
+/// `f` must be a pure function of its inputs.  Any variable that should receive derivatives has to
+/// be passed in through `inputs` (captured variables from the outer graph are not supported).
+/// Checkpoint regions only support first order derivatives.
+pub fn f64ad_checkpoint<F>(inputs: &[f64ad], f: F) -> Vec<f64ad> where F: Fn(&[f64ad]) -> Vec<f64ad> + 'static {
+    let computation_graph = inputs.iter().find(|x| x.map_to_type() != F64adType::F64).map(|x| x.computation_graph());
+    match computation_graph {
+        None => { f(inputs) }
+        Some(computation_graph) => {
+            computation_graph.add_composite(inputs, Rc::new(CheckpointRegion { f: Rc::new(f) }))
+        }
+    }
+}
+
+/// Applies `step` to `inputs` `num_steps` times, i.e., `state_{k+1} = step(state_k)`, with the
+/// binomial checkpointing schedule of Revolve (Griewank & Walther, 2000).  The whole loop is stored
+/// on the computation graph as its inputs and outputs only.  During a backwards sweep, at most
+/// `num_snapshots` states of the loop (counting its input) are kept at once, along with the
+/// recorded nodes of a single step.
+///
+/// With `s = num_snapshots` and `r` the smallest integer such that `C(s + r, s) >= num_steps`, the
+/// backwards sweep re-evaluates the steps `r * num_steps - C(s + r, s + 1)` times in total, which is
+/// the minimum for `s` snapshots, and no step more than `r` times.  On top of that, each step is
+/// evaluated once when the loop is run and once more, recorded, when its derivatives are taken.
+/// Forward mode needs no snapshots and propagates tangents one step at a time.
+///
+/// As with `f64ad_checkpoint`, parameters that should receive derivatives must be carried in the
+/// state (e.g., `step` returns them unchanged), `step` must return a state of the same length, and
+/// only first order derivatives are supported.
+pub fn f64ad_checkpointed_loop<F>(inputs: &[f64ad], num_steps: usize, num_snapshots: usize, step: F) -> Vec<f64ad> where F: Fn(&[f64ad]) -> Vec<f64ad> + 'static {
+    assert!(num_snapshots >= 1, "num_snapshots must be at least 1.");
+    let computation_graph = inputs.iter().find(|x| x.map_to_type() != F64adType::F64).map(|x| x.computation_graph());
+    match computation_graph {
+        None => {
+            let mut state = inputs.to_vec();
+            for _ in 0..num_steps { state = step(&state); }
+            state
+        }
+        Some(computation_graph) => {
+            computation_graph.add_composite(inputs, Rc::new(CheckpointedLoop { step: CheckpointRegion { f: Rc::new(step) }, num_steps, num_snapshots }))
+        }
+    }
+}
+
+/// `C(n, k)`, saturating at `usize::MAX`.
+fn binomial(n: usize, k: usize) -> usize {
+    let k = k.min(n - k);
+    let mut out: usize = 1;
+    for i in 0..k {
+        // exact at every step, since `out * (n - i) / (i + 1)` is `C(n, i + 1)`.
+        out = match out.checked_mul(n - i) {
+            Some(x) => x / (i + 1),
+            None => return usize::MAX
+        };
+    }
+    out
+}
+
+struct CheckpointedLoop {
+    step: CheckpointRegion,
+    num_steps: usize,
+    num_snapshots: usize
+}
+impl CheckpointedLoop {
+    fn advance(&self, state: &[f64], num_steps: usize) -> Vec<f64> {
+        let mut state = state.to_vec();
+        for _ in 0..num_steps {
+            let next = self.step.compute_outputs(&state);
+            assert_eq!(next.len(), state.len(), "step must return a state of the same length as its input.");
+            state = next;
+        }
+        state
+    }
+
+    /// Takes the adjoints of the state `num_steps` steps after `state` back to `state`, keeping at
+    /// most `num_snapshots` states at once, `state` included.
+    fn reverse(&self, state: &[f64], num_steps: usize, num_snapshots: usize, adjoints: Vec<f64>) -> Vec<f64> {
+        match num_steps {
+            0 => return adjoints,
+            1 => return self.step.backwards_mode(state, &adjoints),
+            _ => { }
+        }
+
+        if num_snapshots == 1 {
+            let mut adjoints = adjoints;
+            for k in (0..num_steps).rev() {
+                adjoints = self.step.backwards_mode(&self.advance(state, k), &adjoints);
+            }
+            return adjoints;
+        }
+
+        // with `r` repetitions, `C(s + r, s)` steps can be reversed: the first `m <= C(s + r - 1, s)`
+        // with all snapshots and one repetition fewer, the remaining `C(s + r - 1, s - 1)` with one
+        // snapshot fewer.  Of the feasible splits, the smallest is optimal (Griewank, 1992).
+        let s = num_snapshots;
+        let mut r = 1;
+        while binomial(s + r, s) < num_steps { r += 1; }
+        let m = if r >= 2 { binomial(s + r - 2, s) } else { 1 };
+        let m = m.max(num_steps.saturating_sub(binomial(s + r - 1, s - 1)));
+
+        let snapshot = self.advance(state, m);
+        let adjoints = self.reverse(&snapshot, num_steps - m, num_snapshots - 1, adjoints);
+        drop(snapshot);
+        self.reverse(state, m, num_snapshots, adjoints)
+    }
+}
+impl CompositeOperation for CheckpointedLoop {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        self.advance(input_values, self.num_steps)
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        self.reverse(input_values, self.num_steps, self.num_snapshots, output_adjoints.to_vec())
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        let mut state = input_values.to_vec();
+        let mut tangents = input_tangents.to_vec();
+        for _ in 0..self.num_steps {
+            tangents = self.step.forward_mode(&state, &tangents);
+            state = self.advance(&state, 1);
+        }
+        tangents
+    }
+}
+
+struct CheckpointRegion {
+    f: RegionFunction
+}
+impl CompositeOperation for CheckpointRegion {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        let inputs: Vec<f64ad> = input_values.iter().map(|x| f64ad::f64(*x)).collect();
+        (self.f)(&inputs).iter().map(|x| x.value()).collect()
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        with_recomputation_graph(|computation_graph| {
+            let inputs: Vec<f64ad> = input_values.iter().map(|x| computation_graph.spawn_variable(*x)).collect();
+            let outputs = (self.f)(&inputs);
+            assert_eq!(outputs.len(), output_adjoints.len(), "checkpoint region returned a different number of outputs when recomputed.");
+
+            // a single backwards pass on the adjoint-weighted sum of outputs gives the vector-Jacobian product.
+            let mut weighted_sum = f64ad::f64(0.0);
+            for (output, output_adjoint) in outputs.iter().zip(output_adjoints.iter()) {
+                if *output_adjoint != 0.0 { weighted_sum += *output * *output_adjoint; }
+            }
+            if weighted_sum.map_to_type() == F64adType::F64 { return vec![0.0; inputs.len()]; }
+
+            let grad = weighted_sum.backwards_mode_grad(false);
+            inputs.iter().map(|x| grad.wrt(x).value()).collect()
+        })
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        with_recomputation_graph(|computation_graph| {
+            // the region is evaluated along the line `input_values + t * input_tangents` at t = 0.
+            let t = computation_graph.spawn_variable(0.0);
+            let inputs: Vec<f64ad> = input_values.iter().zip(input_tangents.iter()).map(|(x, dx)| {
+                if *dx == 0.0 { f64ad::f64(*x) } else { *x + t * *dx }
+            }).collect();
+            let outputs = (self.f)(&inputs);
+
+            let grad = t.forward_mode_grad(false);
+            outputs.iter().map(|x| {
+                if x.map_to_type() == F64adType::F64 { 0.0 } else { grad.wrt(x).value() }
+            }).collect()
+        })
+    }
+}
+
+thread_local! {
+    static RECOMPUTATION_GRAPHS: RefCell<Vec<&'static ComputationGraph>> = const { RefCell::new(vec![]) };
+    static RECOMPUTATION_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `f` with a freshly reset scratch graph.  Nested checkpoint regions (i.e., regions that are
+/// recomputed while another region is being recomputed) each get their own scratch graph.
+fn with_recomputation_graph<R, F: FnOnce(GlobalComputationGraph) -> R>(f: F) -> R {
+    let depth = RECOMPUTATION_DEPTH.with(|x| {
+        let depth = x.get();
+        x.set(depth + 1);
+        depth
+    });
+    // restores the depth even if `f` unwinds, so later regions go back to using the shallower graphs.
+    let _depth_guard = RecomputationDepthGuard(depth);
+    let computation_graph = RECOMPUTATION_GRAPHS.with(|x| {
+        let mut binding = x.borrow_mut();
+        while binding.len() <= depth {
+            let c: &'static ComputationGraph = Box::leak(Box::new(ComputationGraph::new(ComputationGraphType::ComputationGraphF)));
+            binding.push(c);
+        }
+        binding[depth]
+    });
+
+    computation_graph.reset();
+    let out = f(GlobalComputationGraph(computation_graph));
+    computation_graph.reset();
+
+    out
+}
+
+struct RecomputationDepthGuard(usize);
+impl Drop for RecomputationDepthGuard {
+    fn drop(&mut self) {
+        RECOMPUTATION_DEPTH.with(|x| x.set(self.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::f64ad::{f64ad, GlobalComputationGraph, GlobalComputationGraphs};
+    use crate::f64ad::checkpointing::{binomial, f64ad_checkpointed_loop};
+
+    fn pendulum_step(x: &[f64ad]) -> Vec<f64ad> {
+        vec![x[0] + 0.1 * x[1], x[1] - 0.1 * x[2] * x[0].sin(), x[2]]
+    }
+
+    /// Jacobian of the loop's outputs with respect to its inputs, from backwards and forward mode.
+    fn jacobians(computation_graph: &GlobalComputationGraph, f: &dyn Fn(&[f64ad]) -> Vec<f64ad>) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        computation_graph.reset();
+        let inputs: Vec<f64ad> = [0.3, -0.2, 2.0].iter().map(|x| computation_graph.spawn_variable(*x)).collect();
+        let outputs = f(&inputs);
+        let backwards = outputs.iter().map(|output| {
+            let grad = output.backwards_mode_grad(false);
+            inputs.iter().map(|input| grad.wrt(input).value()).collect()
+        }).collect();
+        let forward = outputs.iter().map(|output| {
+            inputs.iter().map(|input| input.forward_mode_grad(false).wrt(output).value()).collect()
+        }).collect();
+        (backwards, forward)
+    }
+
+    #[test]
+    fn checkpointed_loop_matches_unrolled_loop() {
+        for computation_graph in [GlobalComputationGraphs::get_first_order(Some("checkpointing_tests"), None), GlobalComputationGraphs::get(Some("checkpointing_tests"), None)] {
+            for num_steps in [0, 1, 2, 7, 20, 57] {
+                let (expected, _) = jacobians(&computation_graph, &|x| {
+                    let mut state = x.to_vec();
+                    for _ in 0..num_steps { state = pendulum_step(&state); }
+                    state
+                });
+                for num_snapshots in [1, 2, 3, 5] {
+                    let (backwards, forward) = jacobians(&computation_graph, &|x| f64ad_checkpointed_loop(x, num_steps, num_snapshots, pendulum_step));
+                    for (mode, actual) in [("backwards", backwards), ("forward", forward)] {
+                        for (actual_row, expected_row) in actual.iter().zip(expected.iter()) {
+                            for (a, e) in actual_row.iter().zip(expected_row.iter()) {
+                                assert!((a - e).abs() <= 1e-12 * (1.0 + e.abs()), "{} mode Jacobian of {} steps with {} snapshots is {:?}, but the unrolled loop gives {:?}.", mode, num_steps, num_snapshots, actual, expected);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn checkpointed_loop_follows_binomial_schedule() {
+        let computation_graph = GlobalComputationGraphs::get_first_order(Some("checkpointing_tests_schedule"), None);
+        for num_snapshots in [1, 2, 3, 4] {
+            for num_steps in [2, 3, 5, 10, 16, 35, 36, 100] {
+                // the last entry of the state is the step index, so each evaluation can be counted.
+                let evaluations = Rc::new(RefCell::new(vec![0; num_steps]));
+                let e = evaluations.clone();
+                let step = move |x: &[f64ad]| {
+                    e.borrow_mut()[x[3].value() as usize] += 1;
+                    let mut out = pendulum_step(&x[..3]);
+                    out.push(x[3] + 1.0);
+                    out
+                };
+
+                computation_graph.reset();
+                let inputs: Vec<f64ad> = [0.3, -0.2, 2.0].iter().map(|x| computation_graph.spawn_variable(*x)).chain([f64ad::f64(0.0)]).collect();
+                let outputs = f64ad_checkpointed_loop(&inputs, num_steps, num_snapshots, step);
+                assert!(evaluations.borrow().iter().all(|x| *x == 1));
+                evaluations.borrow_mut().fill(0);
+                outputs[0].backwards_mode_grad(false);
+
+                let s = num_snapshots;
+                let mut r = 1;
+                while binomial(s + r, s) < num_steps { r += 1; }
+                // one recorded evaluation per step, on top of the recomputations.
+                let recomputations: usize = evaluations.borrow().iter().sum::<usize>() - num_steps;
+                assert_eq!(recomputations, r * num_steps - binomial(s + r, s + 1), "{} steps with {} snapshots were recomputed {:?} times.", num_steps, num_snapshots, evaluations.borrow());
+                assert!(evaluations.borrow().iter().all(|x| *x <= r + 1), "{} steps with {} snapshots were recomputed {:?} times.", num_steps, num_snapshots, evaluations.borrow());
+            }
+        }
+    }
+
+    #[test]
+    fn binomial_coefficients() {
+        assert_eq!(binomial(5, 2), 10);
+        assert_eq!(binomial(5, 0), 1);
+        assert_eq!(binomial(5, 5), 1);
+        assert_eq!(binomial(60, 30), 118264581564861424);
+        assert_eq!(binomial(200, 100), usize::MAX);
+    }
+}
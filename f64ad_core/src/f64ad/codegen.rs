@@ -0,0 +1,393 @@
+// Rust source code generation from tracer and locked graphs.  The generated code is straight-line
+// code over `f64` with no dependency on f64ad: one function that computes the marked outputs from
+// the inputs, and one that computes the Jacobian of the outputs with respect to the inputs using
+// backwards mode automatic differentiation.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use crate::f64ad::{ComputationGraph, DerivativePolicy, KinkPolicy, NodeTypeClass, RoundingPolicy};
+use crate::f64ad::dot_export::reachable_nodes;
+use crate::f64ad::tape::{Operand, Tape};
+
+pub(crate) fn to_rust(computation_graph: &ComputationGraph, function_name: &str, outputs: &[usize]) -> String {
+    assert!(!outputs.is_empty(), "the outputs of the graph must be marked with `set_outputs` before generating code.");
+    let derivative_policy = computation_graph.derivative_policy();
+
+    computation_graph.with_tape(|tape| {
+        let inputs: Vec<usize> = (0..tape.len()).filter(|x| tape.node_type_class(*x) == NodeTypeClass::InputVariable).collect();
+        let needed: BTreeSet<usize> = outputs.iter().flat_map(|x| reachable_nodes(computation_graph, tape, *x)).collect();
+
+        let mut out = String::new();
+        writeln!(out, "// Generated by f64ad from a graph with {} nodes, {} inputs, and {} outputs.", tape.len(), inputs.len(), outputs.len()).unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(out, "#[allow(unused_variables, clippy::all)]").unwrap();
+        writeln!(out, "pub fn {}(x: &[f64]) -> Vec<f64> {{", function_name).unwrap();
+        write_values(&mut out, tape, &inputs, &needed);
+        writeln!(out, "    vec![{}]", outputs.iter().map(|x| format!("v{}", x)).collect::<Vec<String>>().join(", ")).unwrap();
+        writeln!(out, "}}").unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(out, "/// Row `i` is the gradient of output `i` of `{}` with respect to `x`.", function_name).unwrap();
+        writeln!(out, "#[allow(unused_variables, unused_mut, clippy::all)]").unwrap();
+        writeln!(out, "pub fn {}_jacobian(x: &[f64]) -> Vec<Vec<f64>> {{", function_name).unwrap();
+        write_values(&mut out, tape, &inputs, &needed);
+        writeln!(out, "    let mut jacobian = vec![vec![0.0; {}]; {}];", inputs.len(), outputs.len()).unwrap();
+        for (output_idx, output) in outputs.iter().enumerate() {
+            writeln!(out, "    {{").unwrap();
+            // adjoints are only declared for the nodes this output depends on; the entries of all
+            // other inputs stay zero.
+            let reachable = write_adjoints(&mut out, computation_graph, tape, *output, &derivative_policy);
+            for (input_idx, input) in inputs.iter().enumerate() {
+                if reachable.contains(input) {
+                    writeln!(out, "        jacobian[{}][{}] = a{};", output_idx, input_idx, input).unwrap();
+                }
+            }
+            writeln!(out, "    }}").unwrap();
+        }
+        writeln!(out, "    jacobian").unwrap();
+        writeln!(out, "}}").unwrap();
+
+        out
+    })
+}
+
+fn write_values(out: &mut String, tape: &Tape, inputs: &[usize], needed: &BTreeSet<usize>) {
+    writeln!(out, "    assert_eq!(x.len(), {});", inputs.len()).unwrap();
+    for (input_idx, input) in inputs.iter().enumerate() {
+        writeln!(out, "    let v{} = x[{}];", input, input_idx).unwrap();
+    }
+    for node_idx in needed {
+        let node_type_class = tape.node_type_class(*node_idx);
+        if node_type_class == NodeTypeClass::InputVariable { continue; }
+        let [lhs, rhs] = operand_exprs(tape, *node_idx);
+        writeln!(out, "    let v{} = {};", node_idx, value_expr(node_type_class, &lhs.unwrap(), rhs.as_deref())).unwrap();
+    }
+}
+
+/// Writes the backwards sweep from `output` and returns the nodes it declared adjoints for.
+fn write_adjoints(out: &mut String, computation_graph: &ComputationGraph, tape: &Tape, output: usize, derivative_policy: &DerivativePolicy) -> BTreeSet<usize> {
+    let reachable = reachable_nodes(computation_graph, tape, output);
+    for node_idx in &reachable {
+        writeln!(out, "        let mut a{} = {};", node_idx, if *node_idx == output { "1.0" } else { "0.0" }).unwrap();
+    }
+    for node_idx in reachable.iter().rev() {
+        let node_type_class = tape.node_type_class(*node_idx);
+        if node_type_class == NodeTypeClass::InputVariable { continue; }
+        let [lhs, rhs] = operand_exprs(tape, *node_idx);
+        let partials = local_partial_exprs(node_type_class, &lhs.unwrap(), rhs.as_deref(), derivative_policy);
+        for (slot, parent) in tape.variable_parents(*node_idx).iter().enumerate() {
+            if let Some(parent) = parent {
+                writeln!(out, "        a{} += a{} * ({});", parent, node_idx, partials[slot]).unwrap();
+            }
+        }
+    }
+    reachable
+}
+
+fn operand_exprs(tape: &Tape, node_idx: usize) -> [Option<String>; 2] {
+    tape.operands(node_idx).map(|x| {
+        x.map(|y| {
+            match y {
+                Operand::Variable(parent) => { format!("v{}", parent) }
+                Operand::Constant(constant) => { constant_expr(constant) }
+            }
+        })
+    })
+}
+
+fn constant_expr(constant: f64) -> String {
+    if constant.is_nan() { "f64::NAN".to_string() }
+    else if constant == f64::INFINITY { "f64::INFINITY".to_string() }
+    else if constant == f64::NEG_INFINITY { "f64::NEG_INFINITY".to_string() }
+    else if constant.is_sign_negative() { format!("({:?}_f64)", constant) }
+    else { format!("{:?}_f64", constant) }
+}
+
+fn value_expr(node_type_class: NodeTypeClass, l: &str, r: Option<&str>) -> String {
+    let r = || r.unwrap();
+    match node_type_class {
+        NodeTypeClass::InputVariable => { unreachable!() }
+        NodeTypeClass::Add => { format!("{} + {}", l, r()) }
+        NodeTypeClass::Mul => { format!("{} * {}", l, r()) }
+        NodeTypeClass::Sub => { format!("{} - {}", l, r()) }
+        NodeTypeClass::Div => { format!("{} / {}", l, r()) }
+        NodeTypeClass::Neg => { format!("-{}", l) }
+        NodeTypeClass::Abs => { format!("{}.abs()", l) }
+        NodeTypeClass::Signum => { format!("{}.signum()", l) }
+        NodeTypeClass::Max => { format!("{}.max({})", l, r()) }
+        NodeTypeClass::Min => { format!("{}.min({})", l, r()) }
+        NodeTypeClass::Atan2 => { format!("{}.atan2({})", l, r()) }
+        NodeTypeClass::Floor => { format!("{}.floor()", l) }
+        NodeTypeClass::Ceil => { format!("{}.ceil()", l) }
+        NodeTypeClass::Round => { format!("{}.round()", l) }
+        NodeTypeClass::Trunc => { format!("{}.trunc()", l) }
+        NodeTypeClass::Fract => { format!("{}.fract()", l) }
+        NodeTypeClass::Sin => { format!("{}.sin()", l) }
+        NodeTypeClass::Cos => { format!("{}.cos()", l) }
+        NodeTypeClass::Tan => { format!("{}.tan()", l) }
+        NodeTypeClass::Asin => { format!("{}.asin()", l) }
+        NodeTypeClass::Acos => { format!("{}.acos()", l) }
+        NodeTypeClass::Atan => { format!("{}.atan()", l) }
+        NodeTypeClass::Sinh => { format!("{}.sinh()", l) }
+        NodeTypeClass::Cosh => { format!("{}.cosh()", l) }
+        NodeTypeClass::Tanh => { format!("{}.tanh()", l) }
+        NodeTypeClass::Asinh => { format!("{}.asinh()", l) }
+        NodeTypeClass::Acosh => { format!("{}.acosh()", l) }
+        NodeTypeClass::Atanh => { format!("{}.atanh()", l) }
+        NodeTypeClass::Log => { format!("{}.log({})", l, r()) }
+        NodeTypeClass::Ln => { format!("{}.ln()", l) }
+        NodeTypeClass::Sqrt => { format!("{}.sqrt()", l) }
+        NodeTypeClass::Exp => { format!("{}.exp()", l) }
+        NodeTypeClass::Powf => { format!("{}.powf({})", l, r()) }
+        NodeTypeClass::Manual { .. } | NodeTypeClass::Composite { .. } => { panic!("cannot generate code for {:?} nodes.", node_type_class) }
+    }
+}
+
+/// Same local partial derivatives as `compute_derivatives`, as Rust expressions for both operand
+/// slots.
+fn local_partial_exprs(node_type_class: NodeTypeClass, l: &str, r: Option<&str>, derivative_policy: &DerivativePolicy) -> [String; 2] {
+    let r = || r.unwrap();
+    let one = |x: String| [x, "0.0".to_string()];
+    let rounding = match derivative_policy.rounding {
+        RoundingPolicy::Zero => { "0.0".to_string() }
+        RoundingPolicy::StraightThrough => { "1.0".to_string() }
+    };
+    match node_type_class {
+        NodeTypeClass::InputVariable => { unreachable!() }
+        NodeTypeClass::Add => { ["1.0".to_string(), "1.0".to_string()] }
+        NodeTypeClass::Mul => { [r().to_string(), l.to_string()] }
+        NodeTypeClass::Sub => { ["1.0".to_string(), "-1.0".to_string()] }
+        NodeTypeClass::Div => { [format!("1.0 / {}", r()), format!("-{} / ({} * {})", l, r(), r())] }
+        NodeTypeClass::Neg => { one("-1.0".to_string()) }
+        NodeTypeClass::Abs => {
+            match derivative_policy.kinks {
+                KinkPolicy::OneSided => { one(format!("if {} >= 0.0 {{ 1.0 }} else {{ -1.0 }}", l)) }
+                KinkPolicy::Midpoint => { one(format!("if {} > 0.0 {{ 1.0 }} else if {} < 0.0 {{ -1.0 }} else {{ 0.0 }}", l, l)) }
+                KinkPolicy::Smooth { temperature } => { one(format!("({} / {}).tanh()", l, constant_expr(temperature))) }
+            }
+        }
+        NodeTypeClass::Max => { max_partial_exprs(l, r(), &derivative_policy.kinks) }
+        NodeTypeClass::Min => {
+            match derivative_policy.kinks {
+                KinkPolicy::OneSided => { [format!("if {} <= {} {{ 1.0 }} else {{ 0.0 }}", l, r()), format!("if {} <= {} {{ 0.0 }} else {{ 1.0 }}", l, r())] }
+                KinkPolicy::Midpoint => {
+                    [format!("if {} < {} {{ 1.0 }} else if {} > {} {{ 0.0 }} else {{ 0.5 }}", l, r(), l, r()),
+                     format!("if {} < {} {{ 0.0 }} else if {} > {} {{ 1.0 }} else {{ 0.5 }}", l, r(), l, r())]
+                }
+                KinkPolicy::Smooth { temperature } => {
+                    let s = format!("1.0 / (1.0 + (-({} - {}) / {}).exp())", r(), l, constant_expr(temperature));
+                    [s.clone(), format!("1.0 - {}", s)]
+                }
+            }
+        }
+        NodeTypeClass::Atan2 => { [format!("{} / ({} * {} + {} * {})", r(), l, l, r(), r()), format!("-{} / ({} * {} + {} * {})", l, l, l, r(), r())] }
+        NodeTypeClass::Signum | NodeTypeClass::Floor | NodeTypeClass::Ceil | NodeTypeClass::Round | NodeTypeClass::Trunc => { one(rounding) }
+        NodeTypeClass::Fract => {
+            // `fract(x) = x - trunc(x)`.
+            match derivative_policy.rounding {
+                RoundingPolicy::Zero => { one("1.0".to_string()) }
+                RoundingPolicy::StraightThrough => { one("0.0".to_string()) }
+            }
+        }
+        NodeTypeClass::Sin => { one(format!("{}.cos()", l)) }
+        NodeTypeClass::Cos => { one(format!("-{}.sin()", l)) }
+        NodeTypeClass::Tan => { one(format!("1.0 / ({}.cos() * {}.cos())", l, l)) }
+        NodeTypeClass::Asin => { one(format!("1.0 / (1.0 - {} * {}).sqrt()", l, l)) }
+        NodeTypeClass::Acos => { one(format!("-1.0 / (1.0 - {} * {}).sqrt()", l, l)) }
+        NodeTypeClass::Atan => { one(format!("1.0 / ({} * {} + 1.0)", l, l)) }
+        NodeTypeClass::Sinh => { one(format!("{}.cosh()", l)) }
+        NodeTypeClass::Cosh => { one(format!("{}.sinh()", l)) }
+        NodeTypeClass::Tanh => { one(format!("1.0 / ({}.cosh() * {}.cosh())", l, l)) }
+        NodeTypeClass::Asinh => { one(format!("1.0 / ({} * {} + 1.0).sqrt()", l, l)) }
+        NodeTypeClass::Acosh => { one(format!("1.0 / (({} - 1.0).sqrt() * ({} + 1.0).sqrt())", l, l)) }
+        NodeTypeClass::Atanh => { one(format!("1.0 / (1.0 - {} * {})", l, l)) }
+        NodeTypeClass::Log => { [format!("1.0 / ({} * {}.ln())", l, r()), format!("-{}.ln() / ({} * {}.ln() * {}.ln())", l, r(), r(), r())] }
+        NodeTypeClass::Ln => { one(format!("1.0 / {}", l)) }
+        NodeTypeClass::Sqrt => { one(format!("1.0 / (2.0 * {}.sqrt())", l)) }
+        NodeTypeClass::Exp => { one(format!("{}.exp()", l)) }
+        NodeTypeClass::Powf => { [format!("{} * {}.powf({} - 1.0)", r(), l, r()), format!("{}.powf({}) * {}.ln()", l, r(), l)] }
+        NodeTypeClass::Manual { .. } | NodeTypeClass::Composite { .. } => { panic!("cannot generate code for {:?} nodes.", node_type_class) }
+    }
+}
+
+fn max_partial_exprs(l: &str, r: &str, kink_policy: &KinkPolicy) -> [String; 2] {
+    match kink_policy {
+        KinkPolicy::OneSided => { [format!("if {} >= {} {{ 1.0 }} else {{ 0.0 }}", l, r), format!("if {} >= {} {{ 0.0 }} else {{ 1.0 }}", l, r)] }
+        KinkPolicy::Midpoint => {
+            [format!("if {} > {} {{ 1.0 }} else if {} < {} {{ 0.0 }} else {{ 0.5 }}", l, r, l, r),
+             format!("if {} > {} {{ 0.0 }} else if {} < {} {{ 1.0 }} else {{ 0.5 }}", l, r, l, r)]
+        }
+        KinkPolicy::Smooth { temperature } => {
+            let s = format!("1.0 / (1.0 + (-({} - {}) / {}).exp())", l, r, constant_expr(*temperature));
+            [s.clone(), format!("1.0 - {}", s)]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use crate::f64ad::{DerivativePolicy, f64ad, GlobalComputationGraphs, KinkPolicy, RoundingPolicy};
+    use crate::f64ad::optimization::OptimizationPasses;
+
+    /// Uses every node type class that code can be generated for, with variable and constant
+    /// operands on either side.  The first two outputs depend on different inputs.
+    fn model(x: &[f64ad]) -> Vec<f64ad> {
+        let (x0, x1, x2) = (x[0], x[1], x[2]);
+        let c = f64ad::f64;
+        let terms = [
+            x0 * x1, x0 / x1, x2 - x0, -x1, (x0 - x1).abs(), (x0 - x1).signum(), x0.max(x1), x0.min(x1),
+            x0.atan2(x1), x2.floor(), x2.ceil(), x2.round(), x2.trunc(), x2.fract(), x0.tan(), x0.asin(),
+            x1.acos(), x2.atan(), x0.sinh(), x1.cosh(), x2.tanh(), x0.asinh(), x2.acosh(), x1.atanh(),
+            x2.log(x0 + 1.0), x2.log(c(std::f64::consts::E)), x2.sqrt(), x0.exp(), x2.powf(x0),
+            2.0 * x0, x1 - 3.0, 1.5 / x2, x2.powf(c(2.5)), c(2.0).powf(x0), x0.max(c(0.5)), c(0.5).min(x1)
+        ];
+        let mut sum = terms[0];
+        for term in &terms[1..] { sum += *term; }
+        vec![x0.sin(), x1.cos(), sum]
+    }
+
+    /// Generates code for `model` under several derivative policies, compiles it with `rustc`, and
+    /// checks that the generated values and Jacobians match the locked graph's values and
+    /// `backwards_mode_grad`.  This keeps `local_partial_exprs` in sync with `derivative_rules`.
+    #[test]
+    fn generated_code_matches_graph_derivatives() {
+        let policies = [
+            DerivativePolicy::new(KinkPolicy::OneSided, RoundingPolicy::Zero),
+            DerivativePolicy::new(KinkPolicy::Midpoint, RoundingPolicy::StraightThrough),
+            DerivativePolicy::new(KinkPolicy::Smooth { temperature: 0.25 }, RoundingPolicy::Zero)
+        ];
+        // the second point has ties in `max`/`min` and a kink in `abs`.
+        let points = [[0.3, 0.7, 1.6], [0.5, 0.5, 1.3], [0.8, 0.25, 1.9]];
+
+        let mut source = String::new();
+        let mut functions = vec![];
+        let mut expected = vec![];
+        for (policy_idx, policy) in policies.iter().enumerate() {
+            for optimized in [false, true] {
+                let tracer = GlobalComputationGraphs::get_tracer(Some("codegen_tests"), Some(policy_idx));
+                tracer.reset();
+                tracer.set_derivative_policy(*policy);
+                let inputs: Vec<f64ad> = points[0].iter().map(|x| tracer.spawn_variable(*x)).collect();
+                tracer.set_outputs(&model(&inputs));
+                // the optimized graph turns `x.log(E)` into an `Ln` node.
+                if optimized { tracer.lock_optimized(Some("codegen_tests"), Some(policy_idx), &OptimizationPasses::default()); } else { tracer.lock(Some("codegen_tests"), Some(policy_idx)); }
+                let locked = GlobalComputationGraphs::get_locked(Some("codegen_tests"), Some(policy_idx));
+
+                let function_name = format!("f_{}_{}", policy_idx, optimized);
+                source += &locked.to_rust(&function_name);
+                functions.push(function_name);
+                for point in &points {
+                    let outputs = locked.evaluate(point);
+                    let locked_inputs = locked.inputs();
+                    let mut row: Vec<f64> = outputs.iter().map(|x| x.value()).collect();
+                    for output in &outputs {
+                        let grad = output.backwards_mode_grad(false);
+                        row.extend(locked_inputs.iter().map(|x| grad.wrt(x).value()));
+                    }
+                    expected.push(row);
+                }
+            }
+        }
+
+        let rows = compile_and_run(source, &functions, &points);
+        assert_eq!(rows.len(), expected.len());
+        for (row, expected_row) in rows.iter().zip(expected.iter()) {
+            assert_eq!(row.len(), expected_row.len());
+            for (v, e) in row.iter().zip(expected_row.iter()) {
+                assert!((v - e).abs() <= 1e-12 * (1.0 + e.abs()), "generated {} but the graph gives {} (row {:?}).", v, e, expected_row);
+            }
+        }
+    }
+
+    /// `min` and `max` with variable and constant operands, where the tie point below has ties in
+    /// the first five outputs only.
+    fn min_max_model(x: &[f64ad]) -> Vec<f64ad> {
+        let (x0, x1, x2) = (x[0], x[1], x[2]);
+        let c = f64ad::f64;
+        vec![x0.min(x1), x1.min(x0), x0.max(x1), x0.min(c(0.5)), c(0.5).min(x1), (x0 * x2).min(x1 + 0.2), c(0.5).max(x2), x2.min(x0.exp())]
+    }
+
+    /// Checks generated Jacobians of `min`/`max` against central differences of the graph's values
+    /// under policies whose derivatives agree with them: `Midpoint` (including at ties, where
+    /// central differences split evenly) and `Smooth` with a temperature far below the gaps between
+    /// operands away from ties.
+    #[test]
+    fn generated_min_max_jacobians_match_finite_differences() {
+        let policies = [
+            DerivativePolicy::new(KinkPolicy::Midpoint, RoundingPolicy::Zero),
+            DerivativePolicy::new(KinkPolicy::Smooth { temperature: 1e-3 }, RoundingPolicy::Zero)
+        ];
+        let points = [[0.3, 0.7, 1.6], [0.5, 0.5, 1.3], [0.8, 0.25, 1.9]];
+        let h = 1e-7;
+
+        let mut source = String::new();
+        let mut functions = vec![];
+        let mut expected = vec![];
+        for (policy_idx, policy) in policies.iter().enumerate() {
+            let tracer = GlobalComputationGraphs::get_tracer(Some("codegen_min_max_tests"), Some(policy_idx));
+            tracer.reset();
+            tracer.set_derivative_policy(*policy);
+            let inputs: Vec<f64ad> = points[0].iter().map(|x| tracer.spawn_variable(*x)).collect();
+            tracer.set_outputs(&min_max_model(&inputs));
+            tracer.lock(Some("codegen_min_max_tests"), Some(policy_idx));
+            let locked = GlobalComputationGraphs::get_locked(Some("codegen_min_max_tests"), Some(policy_idx));
+
+            let function_name = format!("f_{}", policy_idx);
+            source += &locked.to_rust(&function_name);
+            functions.push(function_name);
+            let values = |x: &[f64]| -> Vec<f64> { locked.evaluate(x).iter().map(|x| x.value()).collect() };
+            for point in &points {
+                let mut row = values(point);
+                let columns: Vec<Vec<f64>> = (0..point.len()).map(|input_idx| {
+                    let (mut plus, mut minus) = (*point, *point);
+                    plus[input_idx] += h;
+                    minus[input_idx] -= h;
+                    values(&plus).iter().zip(values(&minus).iter()).map(|(p, m)| (p - m) / (2.0 * h)).collect()
+                }).collect();
+                for output_idx in 0..row.len() {
+                    row.extend(columns.iter().map(|column| column[output_idx]).collect::<Vec<f64>>());
+                }
+                expected.push(row);
+            }
+        }
+
+        let rows = compile_and_run(source, &functions, &points);
+        assert_eq!(rows.len(), expected.len());
+        for (row, expected_row) in rows.iter().zip(expected.iter()) {
+            assert_eq!(row.len(), expected_row.len());
+            for (v, e) in row.iter().zip(expected_row.iter()) {
+                assert!((v - e).abs() <= 1e-6, "generated {} but finite differences give {} (row {:?}).", v, e, expected_row);
+            }
+        }
+    }
+
+    /// Compiles `source` with a `main` that prints, for each function and point, the values
+    /// followed by the flattened Jacobian, and returns the printed rows.
+    fn compile_and_run(mut source: String, functions: &[String], points: &[[f64; 3]]) -> Vec<Vec<f64>> {
+        source += "\nfn main() {\n";
+        for function_name in functions {
+            for point in points {
+                source += &format!("    let x = {:?};\n", point);
+                source += &format!("    let row: Vec<f64> = {}(&x).into_iter().chain({}_jacobian(&x).into_iter().flatten()).collect();\n", function_name, function_name);
+                source += "    println!(\"{}\", row.iter().map(|v| format!(\"{:?}\", v)).collect::<Vec<_>>().join(\" \"));\n";
+            }
+        }
+        source += "}\n";
+
+        let dir = std::env::temp_dir().join(format!("f64ad_codegen_tests_{}_{}", std::process::id(), functions.join("_")));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source_path = dir.join("generated.rs");
+        let binary_path = dir.join("generated");
+        std::fs::write(&source_path, &source).unwrap();
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let compiled = Command::new(rustc).arg("--edition=2021").arg("-o").arg(&binary_path).arg(&source_path).output().unwrap();
+        assert!(compiled.status.success(), "generated code does not compile:\n{}", String::from_utf8_lossy(&compiled.stderr));
+        let run = Command::new(&binary_path).output().unwrap();
+        assert!(run.status.success());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        String::from_utf8(run.stdout).unwrap().lines().map(|line| line.split(' ').map(|x| x.parse().unwrap()).collect()).collect()
+    }
+}
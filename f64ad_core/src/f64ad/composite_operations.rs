@@ -0,0 +1,119 @@
+// Composite operations: a single recorded operation with many inputs and many outputs whose
+// derivatives are provided directly by the operation rather than by `compute_derivatives`.
+
+use std::rc::Rc;
+use crate::f64ad::{ComputationGraph, ComputationGraphType, f64ad, F64adType};
+
+pub(crate) trait CompositeOperation {
+    /// Computes the output values of the operation given its input values.
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64>;
+    /// Given the adjoints of all outputs, returns the adjoints of all inputs (vector-Jacobian product).
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64>;
+    /// Given the tangents of all inputs, returns the tangents of all outputs (Jacobian-vector product).
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64>;
+}
+
+#[derive(Clone)]
+pub(crate) struct CompositeRecord {
+    pub(crate) operation: Rc<dyn CompositeOperation>,
+    /// Node index of each input, or `None` if that input was a constant.
+    pub(crate) input_node_idxs: Vec<Option<usize>>,
+    pub(crate) input_values: Vec<f64>,
+    /// Outputs of a composite operation always occupy consecutive nodes starting at this index.
+    pub(crate) first_output_node_idx: usize,
+    pub(crate) num_outputs: usize
+}
+
+/// Records `operation` as a single composite operation on the computation graph of `inputs`, or
+/// just computes its outputs if all inputs are constants.
+pub(crate) fn record_composite(inputs: &[f64ad], operation: Rc<dyn CompositeOperation>) -> Vec<f64ad> {
+    match inputs.iter().find(|x| x.map_to_type() != F64adType::F64) {
+        None => {
+            let input_values: Vec<f64> = inputs.iter().map(|x| x.value()).collect();
+            operation.compute_outputs(&input_values).into_iter().map(f64ad::f64).collect()
+        }
+        Some(x) => { x.computation_graph().add_composite(inputs, operation) }
+    }
+}
+
+/// Whether an operation on `inputs` should be recorded as a composite operation, i.e., the inputs
+/// are all constants or are on a `ComputationGraph1`.  Composite operations only have first order
+/// derivatives, so on `ComputationGraphF` graphs, where higher order derivatives are computed, and
+/// on `ComputationGraphT` and `ComputationGraphL` graphs, which do not support them at all, callers
+/// record the operation with ordinary nodes instead.
+pub(crate) fn supports_composites(inputs: &[f64ad]) -> bool {
+    match inputs.iter().find(|x| x.map_to_type() != F64adType::F64) {
+        None => { true }
+        Some(x) => { x.computation_graph().map_to_type() == ComputationGraphType::ComputationGraph1 }
+    }
+}
+
+pub(crate) fn composite_input_node_idxs(inputs: &[f64ad], computation_graph_id: usize) -> Vec<Option<usize>> {
+    inputs.iter().map(|x| {
+        if x.map_to_type() == F64adType::F64 { return None; }
+        assert_eq!(x.computation_graph().computation_graph_id(), computation_graph_id, "all inputs to a composite operation must be on the same computation graph.");
+        Some(x.node_idx())
+    }).collect()
+}
+
+pub(crate) fn composite_backwards_mode_step(computation_graph: &ComputationGraph, composite_idx: usize, derivs: &mut [f64ad], add_to_computation_graph: bool) {
+    assert!(!add_to_computation_graph, "composite operations (e.g., checkpoint regions) only support first order derivatives.");
+
+    let record = computation_graph.get_composite(composite_idx);
+    let output_adjoints: Vec<f64> = (0..record.num_outputs).map(|i| derivs[record.first_output_node_idx + i].value()).collect();
+    if output_adjoints.iter().all(|x| *x == 0.0) { return; }
+
+    let input_adjoints = record.operation.backwards_mode(&record.input_values, &output_adjoints);
+    for (input_node_idx, input_adjoint) in record.input_node_idxs.iter().zip(input_adjoints.iter()) {
+        if let Some(input_node_idx) = input_node_idx {
+            derivs[*input_node_idx] += *input_adjoint;
+        }
+    }
+}
+
+/// Same as `composite_backwards_mode_step`, for sweeps that only track plain f64 adjoints.
+pub(crate) fn composite_backwards_mode_step_f64(record: &CompositeRecord, derivs: &mut [f64]) {
+    let output_adjoints = &derivs[record.first_output_node_idx..record.first_output_node_idx + record.num_outputs];
+    if output_adjoints.iter().all(|x| *x == 0.0) { return; }
+
+    let input_adjoints = record.operation.backwards_mode(&record.input_values, output_adjoints);
+    for (input_node_idx, input_adjoint) in record.input_node_idxs.iter().zip(input_adjoints.iter()) {
+        if let Some(input_node_idx) = input_node_idx {
+            derivs[*input_node_idx] += *input_adjoint;
+        }
+    }
+}
+
+/// Same as `composite_forward_mode_step`, for sweeps that only track plain f64 tangents.
+pub(crate) fn composite_forward_mode_step_f64(record: &CompositeRecord, derivs: &mut [f64]) {
+    let input_tangents: Vec<f64> = record.input_node_idxs.iter().map(|x| {
+        match x {
+            None => { 0.0 }
+            Some(input_node_idx) => { derivs[*input_node_idx] }
+        }
+    }).collect();
+    if input_tangents.iter().all(|x| *x == 0.0) { return; }
+
+    let output_tangents = record.operation.forward_mode(&record.input_values, &input_tangents);
+    for (i, output_tangent) in output_tangents.iter().enumerate() {
+        derivs[record.first_output_node_idx + i] += *output_tangent;
+    }
+}
+
+pub(crate) fn composite_forward_mode_step(computation_graph: &ComputationGraph, composite_idx: usize, derivs: &mut [f64ad], add_to_computation_graph: bool) {
+    assert!(!add_to_computation_graph, "composite operations (e.g., checkpoint regions) only support first order derivatives.");
+
+    let record = computation_graph.get_composite(composite_idx);
+    let input_tangents: Vec<f64> = record.input_node_idxs.iter().map(|x| {
+        match x {
+            None => { 0.0 }
+            Some(input_node_idx) => { derivs[*input_node_idx].value() }
+        }
+    }).collect();
+    if input_tangents.iter().all(|x| *x == 0.0) { return; }
+
+    let output_tangents = record.operation.forward_mode(&record.input_values, &input_tangents);
+    for (i, output_tangent) in output_tangents.iter().enumerate() {
+        derivs[record.first_output_node_idx + i] += *output_tangent;
+    }
+}
@@ -0,0 +1,531 @@
+// Derivative rules of the elementary node types, shared by every scalar type they are evaluated on.
+// Graphs use them with `f64` values (fast tape sweeps) or `f64ad` values (higher order
+// derivatives), and interval evaluation uses them with `Interval`s, so all of them compute the same
+// derivatives.
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use nalgebra::ComplexField;
+use tinyvec::{tiny_vec, TinyVec};
+use crate::f64ad::{DerivativePolicy, f64ad, KinkPolicy, NodeOperandsMode, NodeTypeClass, RoundingPolicy};
+
+/// A scalar type that the derivative rules can be evaluated on.
+pub(crate) trait DerivativeScalar: Copy + Default + PartialOrd + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self> + Div<Output=Self> + Neg<Output=Self> {
+    fn constant(v: f64) -> Self;
+    fn to_f64_value(self) -> f64;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn sinh(self) -> Self;
+    fn cosh(self) -> Self;
+    fn tanh(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn powf(self, n: Self) -> Self;
+}
+
+impl DerivativeScalar for f64ad {
+    #[inline(always)]
+    fn constant(v: f64) -> Self { f64ad::f64(v) }
+    #[inline(always)]
+    fn to_f64_value(self) -> f64 { self.value() }
+    #[inline(always)]
+    fn sin(self) -> Self { ComplexField::sin(self) }
+    #[inline(always)]
+    fn cos(self) -> Self { ComplexField::cos(self) }
+    #[inline(always)]
+    fn sinh(self) -> Self { ComplexField::sinh(self) }
+    #[inline(always)]
+    fn cosh(self) -> Self { ComplexField::cosh(self) }
+    #[inline(always)]
+    fn tanh(self) -> Self { ComplexField::tanh(self) }
+    #[inline(always)]
+    fn sqrt(self) -> Self { ComplexField::sqrt(self) }
+    #[inline(always)]
+    fn exp(self) -> Self { ComplexField::exp(self) }
+    #[inline(always)]
+    fn ln(self) -> Self { ComplexField::ln(self) }
+    #[inline(always)]
+    fn powf(self, n: Self) -> Self { ComplexField::powf(self, n) }
+}
+
+impl DerivativeScalar for f64 {
+    #[inline(always)]
+    fn constant(v: f64) -> Self { v }
+    #[inline(always)]
+    fn to_f64_value(self) -> f64 { self }
+    #[inline(always)]
+    fn sin(self) -> Self { f64::sin(self) }
+    #[inline(always)]
+    fn cos(self) -> Self { f64::cos(self) }
+    #[inline(always)]
+    fn sinh(self) -> Self { f64::sinh(self) }
+    #[inline(always)]
+    fn cosh(self) -> Self { f64::cosh(self) }
+    #[inline(always)]
+    fn tanh(self) -> Self { f64::tanh(self) }
+    #[inline(always)]
+    fn sqrt(self) -> Self { f64::sqrt(self) }
+    #[inline(always)]
+    fn exp(self) -> Self { f64::exp(self) }
+    #[inline(always)]
+    fn ln(self) -> Self { f64::ln(self) }
+    #[inline(always)]
+    fn powf(self, n: Self) -> Self { f64::powf(self, n) }
+}
+
+/// Local partial derivatives of a node of class `node_type_class` with respect to its parents.  The
+/// same rules are used by every scalar type: with `f64` (or `Interval`) the derivatives are plain
+/// values, and with `f64ad` they are themselves recorded on a computation graph, which is how
+/// higher order derivatives are computed.
+#[inline(always)]
+pub(crate) fn derivatives<S: DerivativeScalar>(lhs: S, rhs: Option<S>, node_type_class: NodeTypeClass, operands_mode: NodeOperandsMode, derivative_policy: &DerivativePolicy) -> TinyVec<[S; 2]> {
+    match node_type_class {
+        NodeTypeClass::InputVariable => { tiny_vec!([S; 2]) }
+        NodeTypeClass::Add => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { tiny_vec!([S; 2] => S::constant(1.0), S::constant(1.0)) }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)) }
+                NodeOperandsMode::OneParentRHS => { tiny_vec!([S; 2] => S::constant(1.0)) }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Mul => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { tiny_vec!([S; 2] => rhs.unwrap(), lhs) }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => rhs.unwrap()) }
+                NodeOperandsMode::OneParentRHS => { tiny_vec!([S; 2] => lhs) }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Sub => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { tiny_vec!([S; 2] => S::constant(1.0), S::constant(-1.0)) }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)) }
+                NodeOperandsMode::OneParentRHS => { tiny_vec!([S; 2] => S::constant(-1.0)) }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Div => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => S::constant(1.0)/rhs, -lhs / (rhs*rhs))
+                }
+                NodeOperandsMode::OneParentLHS => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => S::constant(1.0)/rhs)
+                }
+                NodeOperandsMode::OneParentRHS => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => -lhs / (rhs*rhs))
+                }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Neg => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(-1.0)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Abs => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => abs_derivative(lhs, &derivative_policy.kinks)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Signum => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => rounding_derivative(&derivative_policy.rounding)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Max => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => {
+                    let (d_lhs, d_rhs) = max_derivatives(lhs, rhs.unwrap(), &derivative_policy.kinks);
+                    tiny_vec!([S; 2] => d_lhs, d_rhs)
+                }
+                NodeOperandsMode::OneParentLHS => {
+                    let (d_lhs, _) = max_derivatives(lhs, rhs.unwrap(), &derivative_policy.kinks);
+                    tiny_vec!([S; 2] => d_lhs)
+                }
+                NodeOperandsMode::OneParentRHS => {
+                    let (_, d_rhs) = max_derivatives(lhs, rhs.unwrap(), &derivative_policy.kinks);
+                    tiny_vec!([S; 2] => d_rhs)
+                }
+                NodeOperandsMode::NoParents => {
+                    tiny_vec!([S; 2])
+                }
+            }
+        }
+        NodeTypeClass::Min => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => {
+                    let (d_lhs, d_rhs) = min_derivatives(lhs, rhs.unwrap(), &derivative_policy.kinks);
+                    tiny_vec!([S; 2] => d_lhs, d_rhs)
+                }
+                NodeOperandsMode::OneParentLHS => {
+                    let (d_lhs, _) = min_derivatives(lhs, rhs.unwrap(), &derivative_policy.kinks);
+                    tiny_vec!([S; 2] => d_lhs)
+                }
+                NodeOperandsMode::OneParentRHS => {
+                    let (_, d_rhs) = min_derivatives(lhs, rhs.unwrap(), &derivative_policy.kinks);
+                    tiny_vec!([S; 2] => d_rhs)
+                }
+                NodeOperandsMode::NoParents => {
+                    tiny_vec!([S; 2])
+                }
+            }
+        }
+        NodeTypeClass::Atan2 => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => rhs/(lhs*lhs + rhs*rhs), -lhs/(lhs*lhs + rhs*rhs))
+                }
+                NodeOperandsMode::OneParentLHS => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => rhs/(lhs*lhs + rhs*rhs))
+                }
+                NodeOperandsMode::OneParentRHS => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => -lhs/(lhs*lhs + rhs*rhs))
+                }
+                NodeOperandsMode::NoParents => {
+                    tiny_vec!([S; 2])
+                }
+            }
+        }
+        NodeTypeClass::Floor => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => rounding_derivative(&derivative_policy.rounding)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Ceil => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => rounding_derivative(&derivative_policy.rounding)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Round => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => rounding_derivative(&derivative_policy.rounding)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Trunc => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => rounding_derivative(&derivative_policy.rounding)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Fract => {
+            // `fract(x) = x - trunc(x)`.
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0) - rounding_derivative(&derivative_policy.rounding)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Sin => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => lhs.cos()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Cos => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => -lhs.sin()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Tan => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => {
+                    let c = lhs.cos();
+                    tiny_vec!([S; 2] => S::constant(1.0)/(c*c))
+                }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Asin => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0) / (S::constant(1.0) - lhs * lhs).sqrt()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Acos => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(-1.0)/(S::constant(1.0) - lhs * lhs).sqrt()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Atan => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)/(lhs*lhs + S::constant(1.0))) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Sinh => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => lhs.cosh()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Cosh => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => lhs.sinh()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Tanh => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => {
+                    let c = lhs.cosh();
+                    tiny_vec!([S; 2] => S::constant(1.0) / (c*c))
+                }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Asinh => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)/(lhs*lhs + S::constant(1.0)).sqrt()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Acosh => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)/((lhs - S::constant(1.0)).sqrt()*(lhs + S::constant(1.0)).sqrt()) ) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Atanh => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)/(S::constant(1.0) - lhs*lhs)) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Log => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => {
+                    let rhs = rhs.unwrap();
+                    let ln_rhs = rhs.ln();
+                    let ln_lhs = lhs.ln();
+                    tiny_vec!([S; 2] => S::constant(1.0)/(lhs * ln_rhs), -ln_lhs / (rhs * ln_rhs * ln_rhs))
+                }
+                NodeOperandsMode::OneParentLHS => {
+                    let rhs = rhs.unwrap();
+                    let ln_rhs = rhs.ln();
+                    tiny_vec!([S; 2] => S::constant(1.0)/(lhs * ln_rhs))
+                }
+                NodeOperandsMode::OneParentRHS => {
+                    let rhs = rhs.unwrap();
+                    let ln_rhs = rhs.ln();
+                    let ln_lhs = lhs.ln();
+                    tiny_vec!([S; 2] => -ln_lhs / (rhs * ln_rhs * ln_rhs))
+                }
+                NodeOperandsMode::NoParents => {
+                    tiny_vec!([S; 2])
+                }
+            }
+        }
+        NodeTypeClass::Ln => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)/lhs) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Sqrt => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => S::constant(1.0)/(S::constant(2.0)*lhs.sqrt())) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Exp => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => { unreachable!() }
+                NodeOperandsMode::OneParentLHS => { tiny_vec!([S; 2] => lhs.exp()) }
+                NodeOperandsMode::OneParentRHS => { unreachable!() }
+                NodeOperandsMode::NoParents => { tiny_vec!([S; 2]) }
+            }
+        }
+        NodeTypeClass::Powf => {
+            match operands_mode {
+                NodeOperandsMode::TwoParents => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => rhs * lhs.powf(rhs - S::constant(1.0)), lhs.powf(rhs) * lhs.ln())
+                }
+                NodeOperandsMode::OneParentLHS => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => rhs * lhs.powf(rhs - S::constant(1.0)))
+                }
+                NodeOperandsMode::OneParentRHS => {
+                    let rhs = rhs.unwrap();
+                    tiny_vec!([S; 2] => lhs.powf(rhs) * lhs.ln())
+                }
+                NodeOperandsMode::NoParents => {
+                    tiny_vec!([S; 2])
+                }
+            }
+        }
+        NodeTypeClass::Manual { derivative, .. } => {
+            tiny_vec!([S; 2] => S::constant(derivative))
+        }
+        NodeTypeClass::Composite { .. } => { unreachable!("composite derivatives are handled by the composite operation itself.") }
+    }
+}
+
+#[inline(always)]
+fn abs_derivative<S: DerivativeScalar>(lhs: S, kink_policy: &KinkPolicy) -> S {
+    let val = lhs.to_f64_value();
+    match kink_policy {
+        KinkPolicy::OneSided => { if val >= 0.0 { S::constant(1.0) } else { S::constant(-1.0) } }
+        KinkPolicy::Midpoint => {
+            if val > 0.0 { S::constant(1.0) } else if val < 0.0 { S::constant(-1.0) } else { S::constant(0.0) }
+        }
+        KinkPolicy::Smooth { temperature } => { (lhs / S::constant(*temperature)).tanh() }
+    }
+}
+
+/// Returns the derivatives of `max(lhs, rhs)` with respect to lhs and rhs.
+#[inline(always)]
+fn max_derivatives<S: DerivativeScalar>(lhs: S, rhs: S, kink_policy: &KinkPolicy) -> (S, S) {
+    match kink_policy {
+        KinkPolicy::OneSided => {
+            if lhs >= rhs { (S::constant(1.0), S::constant(0.0)) } else { (S::constant(0.0), S::constant(1.0)) }
+        }
+        KinkPolicy::Midpoint => {
+            if lhs > rhs { (S::constant(1.0), S::constant(0.0)) }
+            else if lhs < rhs { (S::constant(0.0), S::constant(1.0)) }
+            else { (S::constant(0.5), S::constant(0.5)) }
+        }
+        KinkPolicy::Smooth { temperature } => {
+            let s = sigmoid((lhs - rhs) / S::constant(*temperature));
+            (s, S::constant(1.0) - s)
+        }
+    }
+}
+
+/// Returns the derivatives of `min(lhs, rhs)` with respect to lhs and rhs.
+#[inline(always)]
+fn min_derivatives<S: DerivativeScalar>(lhs: S, rhs: S, kink_policy: &KinkPolicy) -> (S, S) {
+    match kink_policy {
+        KinkPolicy::OneSided => {
+            if lhs <= rhs { (S::constant(1.0), S::constant(0.0)) } else { (S::constant(0.0), S::constant(1.0)) }
+        }
+        KinkPolicy::Midpoint => {
+            if lhs < rhs { (S::constant(1.0), S::constant(0.0)) }
+            else if lhs > rhs { (S::constant(0.0), S::constant(1.0)) }
+            else { (S::constant(0.5), S::constant(0.5)) }
+        }
+        KinkPolicy::Smooth { temperature } => {
+            // `min(a, b) ~ b - t*softplus((b - a)/t)`.
+            let s = sigmoid((rhs - lhs) / S::constant(*temperature));
+            (s, S::constant(1.0) - s)
+        }
+    }
+}
+
+#[inline(always)]
+fn rounding_derivative<S: DerivativeScalar>(rounding_policy: &RoundingPolicy) -> S {
+    match rounding_policy {
+        RoundingPolicy::Zero => { S::constant(0.0) }
+        RoundingPolicy::StraightThrough => { S::constant(1.0) }
+    }
+}
+
+#[inline(always)]
+fn sigmoid<S: DerivativeScalar>(x: S) -> S {
+    S::constant(1.0) / (S::constant(1.0) + (-x).exp())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::f64ad::{DerivativePolicy, GlobalComputationGraphs, KinkPolicy, RoundingPolicy};
+
+    /// Checks `d min(a, b) / d(a, b)` under each kink policy against central differences of `min`,
+    /// or of its softplus smoothing under `Smooth`, on both a first order and a higher order graph.
+    /// At ties, `OneSided` gives the whole derivative to `a`, which is checked against the backward
+    /// difference in `a` and the forward difference in `b`.
+    #[test]
+    fn min_derivatives_match_finite_differences() {
+        let temperature = 0.01;
+        let policies = [KinkPolicy::OneSided, KinkPolicy::Midpoint, KinkPolicy::Smooth { temperature }, KinkPolicy::Smooth { temperature: 0.5 }];
+        let points = [(1.0, 3.0), (3.0, 1.0), (2.0, 2.0), (0.9, 1.1)];
+        let h = 1e-6;
+
+        for policy in &policies {
+            let f = |a: f64, b: f64| -> f64 {
+                match policy {
+                    KinkPolicy::Smooth { temperature } => { b - temperature * ((b - a) / temperature).exp().ln_1p() }
+                    _ => { a.min(b) }
+                }
+            };
+            for (a, b) in points {
+                let expected = if *policy == KinkPolicy::OneSided && a == b {
+                    [(f(a, b) - f(a - h, b)) / h, (f(a, b + h) - f(a, b)) / h]
+                } else {
+                    [(f(a + h, b) - f(a - h, b)) / (2.0 * h), (f(a, b + h) - f(a, b - h)) / (2.0 * h)]
+                };
+
+                for computation_graph in [GlobalComputationGraphs::get_first_order(Some("derivative_rules_tests"), None), GlobalComputationGraphs::get(Some("derivative_rules_tests"), None)] {
+                    computation_graph.reset();
+                    computation_graph.set_derivative_policy(DerivativePolicy::new(*policy, RoundingPolicy::Zero));
+                    let x = [computation_graph.spawn_variable(a), computation_graph.spawn_variable(b)];
+                    let out = x[0].min(x[1]);
+                    let grad = out.backwards_mode_grad(false);
+                    for (input, e) in x.iter().zip(expected.iter()) {
+                        let d = grad.wrt(input).value();
+                        assert!((d - e).abs() < 1e-6, "d min({}, {}) under {:?} is {}, but finite differences give {}.", a, b, policy, d, e);
+                    }
+                }
+            }
+        }
+    }
+}
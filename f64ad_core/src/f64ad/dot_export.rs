@@ -0,0 +1,88 @@
+// Graphviz DOT export of computation graphs, for inspecting what was recorded on a graph.  The
+// output can be rendered with, e.g., `dot -Tsvg graph.dot -o graph.svg`.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use crate::f64ad::{BackwardsModeGradOutput, ComputationGraph, NodeTypeClass};
+use crate::f64ad::tape::Tape;
+
+/// Returns the nodes `node_idx` depends on, including itself.
+pub(crate) fn reachable_nodes(computation_graph: &ComputationGraph, tape: &Tape, node_idx: usize) -> BTreeSet<usize> {
+    let mut out = BTreeSet::new();
+    let mut stack = vec![node_idx];
+    while let Some(idx) = stack.pop() {
+        if !out.insert(idx) { continue; }
+        match tape.node_type_class(idx) {
+            NodeTypeClass::Composite { composite_idx, .. } => {
+                stack.extend(computation_graph.get_composite(composite_idx).input_node_idxs.iter().flatten());
+            }
+            _ => { stack.extend(tape.variable_parents(idx).iter().flatten()); }
+        }
+    }
+    out
+}
+
+/// Writes the nodes of `computation_graph` (or only the ones `output` depends on) in DOT format.
+/// Node labels contain the node idx, node type class, value, and, if `adjoints` is given, the
+/// adjoint of the node from that backwards mode sweep.
+pub(crate) fn to_dot(computation_graph: &'static ComputationGraph, output: Option<usize>, adjoints: Option<&BackwardsModeGradOutput>) -> String {
+    if let Some(adjoints) = adjoints {
+        assert_eq!(adjoints.computation_graph.computation_graph_id(), computation_graph.computation_graph_id(), "adjoints are from a different computation graph.");
+    }
+
+    computation_graph.with_tape(|tape| {
+        let node_idxs: BTreeSet<usize> = match output {
+            None => { (0..tape.len()).collect() }
+            Some(output) => { reachable_nodes(computation_graph, tape, output) }
+        };
+
+        let mut out = String::new();
+        writeln!(out, "digraph computation_graph {{").unwrap();
+        writeln!(out, "    rankdir=BT;").unwrap();
+        writeln!(out, "    node [fontname=\"monospace\", fontsize=10];").unwrap();
+
+        for node_idx in &node_idxs {
+            let node_idx = *node_idx;
+            let node_type_class = tape.node_type_class(node_idx);
+            let mut label = format!("{}: {:?}\\nvalue: {:?}", node_idx, node_type_class, tape.value(node_idx));
+            if let Some(adjoint) = adjoints.and_then(|x| x.derivs.get(node_idx)) {
+                label += &format!("\\nadjoint: {:?}", adjoint.value());
+            }
+            let shape = if node_type_class == NodeTypeClass::InputVariable { "ellipse" } else { "box" };
+            let peripheries = if output == Some(node_idx) { 2 } else { 1 };
+            writeln!(out, "    n{} [label=\"{}\", shape={}, peripheries={}];", node_idx, label, shape, peripheries).unwrap();
+
+            if let NodeTypeClass::Composite { composite_idx, output_idx } = node_type_class {
+                let record = computation_graph.get_composite(composite_idx);
+                for (input_idx, input) in record.input_node_idxs.iter().enumerate() {
+                    match input {
+                        Some(parent) => { writeln!(out, "    n{} -> n{} [label=\"in {} / out {}\"];", parent, node_idx, input_idx, output_idx).unwrap(); }
+                        None => {
+                            writeln!(out, "    c{}_{} [label=\"{:?}\", shape=plaintext];", node_idx, input_idx, record.input_values[input_idx]).unwrap();
+                            writeln!(out, "    c{}_{} -> n{} [label=\"in {} / out {}\"];", node_idx, input_idx, node_idx, input_idx, output_idx).unwrap();
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let variable_parents = tape.variable_parents(node_idx);
+            let operand_values = tape.operand_values(node_idx);
+            let two_operands = operand_values.iter().all(|x| x.is_some());
+            for slot in 0..2 {
+                let edge_label = if two_operands { if slot == 0 { " [label=\"lhs\"]" } else { " [label=\"rhs\"]" } } else { "" };
+                match (variable_parents[slot], operand_values[slot]) {
+                    (Some(parent), _) => { writeln!(out, "    n{} -> n{}{};", parent, node_idx, edge_label).unwrap(); }
+                    (None, Some(constant)) => {
+                        writeln!(out, "    c{}_{} [label=\"{:?}\", shape=plaintext];", node_idx, slot, constant).unwrap();
+                        writeln!(out, "    c{}_{} -> n{}{};", node_idx, slot, node_idx, edge_label).unwrap();
+                    }
+                    (None, None) => { }
+                }
+            }
+        }
+
+        writeln!(out, "}}").unwrap();
+        out
+    })
+}
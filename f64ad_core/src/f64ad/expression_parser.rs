@@ -0,0 +1,443 @@
+// Parser for a small expression language, e.g., `sin(x) * y^2 + atan2(y, x)`, that is evaluated
+// with f64ad operations, so expressions from configuration files can be differentiated like any
+// other computation.
+//
+// expressions:  ("let" name "=" sum ";")* sum
+// sum:          product (("+" | "-") product)*
+// product:      unary (("*" | "/") unary)*
+// unary:        "-" unary | power
+// power:        primary ("^" unary)?
+// primary:      number | "pi" | "e" | name | name "(" sum ("," sum)* ")" | "(" sum ")"
+//
+// Functions are the node type classes in lowercase (e.g., `sin`, `atan2`, `log(x, base)`,
+// `powf(x, y)`), plus `ln`.  This is the same syntax `to_expression_string` prints.
+//
+// Expressions usually come from user input, so parsing and evaluating return errors instead of
+// panicking.  Parsing, evaluating, and dropping expressions all recurse over their nesting, so
+// expressions nested deeper than `MAX_DEPTH` are rejected rather than overflowing the stack.
+
+use std::fmt::{Display, Formatter};
+use crate::f64ad::{f64ad, f64ad_universal_function, GlobalComputationGraph, NodeTypeClass};
+use crate::f64ad::expression_printing::function_name;
+
+/// Maximum nesting depth of a parsed expression.  Every parenthesis, function call, unary `-`, `^`,
+/// `let`, and each operator in a chain of `+`/`-` or `*`/`/` counts as one level.
+pub const MAX_DEPTH: usize = 256;
+
+const ONE_OPERAND_FUNCTIONS: [NodeTypeClass; 21] = [
+    NodeTypeClass::Abs, NodeTypeClass::Signum, NodeTypeClass::Floor, NodeTypeClass::Ceil, NodeTypeClass::Round,
+    NodeTypeClass::Trunc, NodeTypeClass::Fract, NodeTypeClass::Sin, NodeTypeClass::Cos, NodeTypeClass::Tan,
+    NodeTypeClass::Asin, NodeTypeClass::Acos, NodeTypeClass::Atan, NodeTypeClass::Sinh, NodeTypeClass::Cosh,
+    NodeTypeClass::Tanh, NodeTypeClass::Asinh, NodeTypeClass::Acosh, NodeTypeClass::Atanh, NodeTypeClass::Sqrt,
+    NodeTypeClass::Exp
+];
+const TWO_OPERAND_FUNCTIONS: [NodeTypeClass; 5] = [NodeTypeClass::Max, NodeTypeClass::Min, NodeTypeClass::Atan2, NodeTypeClass::Log, NodeTypeClass::Powf];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Constant(f64),
+    Variable(String),
+    /// `let name = value; body`
+    Let { name: String, value: Box<Expression>, body: Box<Expression> },
+    Operation { node_type_class: NodeTypeClass, operands: Vec<Expression> }
+}
+impl Expression {
+    /// Returns an error with the position of the problem if `s` is not a valid expression.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser { tokens: tokenize(s)?, position: 0, end: s.chars().count(), depth: 0 };
+        let out = parser.expressions()?;
+        if let Some((token, position)) = parser.tokens.get(parser.position) {
+            return Err(ParseError::UnexpectedToken { token: token.to_string(), position: *position });
+        }
+        Ok(out)
+    }
+    /// Names of the free variables of the expression, in the order they first appear.
+    pub fn variables(&self) -> Vec<String> {
+        let mut out = vec![];
+        self.collect_variables(&mut vec![], &mut out);
+        out
+    }
+    fn collect_variables(&self, bound: &mut Vec<String>, out: &mut Vec<String>) {
+        match self {
+            Expression::Constant(_) => { }
+            Expression::Variable(name) => {
+                if !bound.contains(name) && !out.contains(name) { out.push(name.clone()); }
+            }
+            Expression::Let { name, value, body } => {
+                value.collect_variables(bound, out);
+                bound.push(name.clone());
+                body.collect_variables(bound, out);
+                bound.pop();
+            }
+            Expression::Operation { operands, .. } => {
+                for operand in operands { operand.collect_variables(bound, out); }
+            }
+        }
+    }
+    /// Evaluates the expression with f64ad operations, so the result is on the graph of the given
+    /// variables.  Returns an error if no value was given for one of its free variables.
+    pub fn evaluate(&self, variables: &[(&str, f64ad)]) -> Result<f64ad, UnknownVariable> {
+        let mut scope: Vec<(String, f64ad)> = variables.iter().map(|(x, y)| (x.to_string(), *y)).collect();
+        self.evaluate_in_scope(&mut scope)
+    }
+    /// Spawns the free variables of the expression on `computation_graph`, in the order of
+    /// `variables()`, with their values taken from `values`, and evaluates the expression.  Returns
+    /// the result and the spawned variables.  Returns an error, before spawning anything, if no
+    /// value was given for one of the free variables.
+    pub fn evaluate_on(&self, computation_graph: &GlobalComputationGraph, values: &[(&str, f64)]) -> Result<(f64ad, Vec<f64ad>), UnknownVariable> {
+        let names = self.variables();
+        let values = names.iter().map(|name| {
+            values.iter().find(|x| x.0 == name).map(|x| x.1).ok_or_else(|| UnknownVariable { name: name.clone() })
+        }).collect::<Result<Vec<f64>, UnknownVariable>>()?;
+        let inputs: Vec<f64ad> = values.iter().map(|x| computation_graph.spawn_variable(*x)).collect();
+        let variables: Vec<(&str, f64ad)> = names.iter().map(|x| x.as_str()).zip(inputs.iter().copied()).collect();
+        Ok((self.evaluate(&variables)?, inputs))
+    }
+    fn evaluate_in_scope(&self, scope: &mut Vec<(String, f64ad)>) -> Result<f64ad, UnknownVariable> {
+        match self {
+            Expression::Constant(constant) => { Ok(f64ad::f64(*constant)) }
+            Expression::Variable(name) => {
+                // later bindings shadow earlier ones.
+                scope.iter().rev().find(|x| &x.0 == name).map(|x| x.1).ok_or_else(|| UnknownVariable { name: name.clone() })
+            }
+            Expression::Let { name, value, body } => {
+                let value = value.evaluate_in_scope(scope)?;
+                scope.push((name.clone(), value));
+                let out = body.evaluate_in_scope(scope);
+                scope.pop();
+                out
+            }
+            Expression::Operation { node_type_class, operands } => {
+                let lhs = operands[0].evaluate_in_scope(scope)?;
+                let rhs = match operands.get(1) {
+                    None => { None }
+                    Some(rhs) => { Some(rhs.evaluate_in_scope(scope)?) }
+                };
+                Ok(f64ad_universal_function(lhs, rhs, *node_type_class))
+            }
+        }
+    }
+}
+
+/// Why an expression could not be parsed.  Positions are in characters from the start of the
+/// string.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    UnexpectedCharacter { character: char, position: usize },
+    InvalidNumber { text: String, position: usize },
+    UnexpectedToken { token: String, position: usize },
+    /// The expression ended where `expected` was expected.
+    UnexpectedEnd { expected: String, position: usize },
+    Expected { expected: String, found: String, position: usize },
+    UnknownFunction { name: String, position: usize },
+    WrongNumberOfArguments { name: String, expected: usize, given: usize, position: usize },
+    /// The expression is nested deeper than `max_depth` (see `MAX_DEPTH`) at `position`.
+    NestedTooDeeply { max_depth: usize, position: usize }
+}
+impl ParseError {
+    pub fn position(&self) -> usize {
+        match self {
+            ParseError::UnexpectedCharacter { position, .. } |
+            ParseError::InvalidNumber { position, .. } |
+            ParseError::UnexpectedToken { position, .. } |
+            ParseError::UnexpectedEnd { position, .. } |
+            ParseError::Expected { position, .. } |
+            ParseError::UnknownFunction { position, .. } |
+            ParseError::WrongNumberOfArguments { position, .. } |
+            ParseError::NestedTooDeeply { position, .. } => { *position }
+        }
+    }
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not parse expression: ")?;
+        match self {
+            ParseError::UnexpectedCharacter { character, position } => { write!(f, "unexpected character {:?} at position {}.", character, position) }
+            ParseError::InvalidNumber { text, position } => { write!(f, "invalid number {:?} at position {}.", text, position) }
+            ParseError::UnexpectedToken { token, position } => { write!(f, "unexpected `{}` at position {}.", token, position) }
+            ParseError::UnexpectedEnd { expected, position } => { write!(f, "expected {} at position {}, found the end of the expression.", expected, position) }
+            ParseError::Expected { expected, found, position } => { write!(f, "expected {} at position {}, found `{}`.", expected, position, found) }
+            ParseError::UnknownFunction { name, position } => { write!(f, "unknown function {:?} at position {}.", name, position) }
+            ParseError::WrongNumberOfArguments { name, expected, given, position } => { write!(f, "function {:?} at position {} takes {} arguments, but {} were given.", name, position, expected, given) }
+            ParseError::NestedTooDeeply { max_depth, position } => { write!(f, "expression is nested deeper than {} levels at position {}.", max_depth, position) }
+        }
+    }
+}
+impl std::error::Error for ParseError { }
+
+/// A free variable of an expression that no value was given for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownVariable {
+    pub name: String
+}
+impl Display for UnknownVariable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no value was given for variable {:?} of the expression.", self.name)
+    }
+}
+impl std::error::Error for UnknownVariable { }
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char)
+}
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(number) => { write!(f, "{}", number) }
+            Token::Name(name) => { write!(f, "{}", name) }
+            Token::Symbol(symbol) => { write!(f, "{}", symbol) }
+        }
+    }
+}
+
+/// Tokens with their position in the string.
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') { i += 1; }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') { j += 1; }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() { i += 1; }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text.parse::<f64>().map_err(|_| ParseError::InvalidNumber { text: text.clone(), position: start })?;
+            out.push((Token::Number(number), start));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') { i += 1; }
+            out.push((Token::Name(chars[start..i].iter().collect()), start));
+        } else if "+-*/^(),=;".contains(c) {
+            out.push((Token::Symbol(c), start));
+            i += 1;
+        } else {
+            return Err(ParseError::UnexpectedCharacter { character: c, position: start });
+        }
+    }
+    Ok(out)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    /// Position of the end of the string, for errors at the end of the expression.
+    end: usize,
+    /// Current nesting depth, see `MAX_DEPTH`.
+    depth: usize
+}
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|x| &x.0)
+    }
+    /// The next token and its position.  `expected` describes what was expected, for the error if
+    /// the expression ends here.
+    fn next(&mut self, expected: &str) -> Result<(Token, usize), ParseError> {
+        match self.tokens.get(self.position) {
+            None => { Err(ParseError::UnexpectedEnd { expected: expected.to_string(), position: self.end }) }
+            Some(token) => {
+                self.position += 1;
+                Ok(token.clone())
+            }
+        }
+    }
+    fn accept(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, symbol: char) -> Result<(), ParseError> {
+        if self.accept(symbol) { return Ok(()); }
+        let expected = format!("`{}`", symbol);
+        match self.tokens.get(self.position) {
+            None => { Err(ParseError::UnexpectedEnd { expected, position: self.end }) }
+            Some((token, position)) => { Err(ParseError::Expected { expected, found: token.to_string(), position: *position }) }
+        }
+    }
+    /// Enters one more level of nesting, opened by the token that was just consumed.  Callers reset
+    /// `depth` when they are done, errors abandon the whole parse.
+    fn descend(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            let position = self.tokens[self.position - 1].1;
+            return Err(ParseError::NestedTooDeeply { max_depth: MAX_DEPTH, position });
+        }
+        Ok(())
+    }
+    fn expressions(&mut self) -> Result<Expression, ParseError> {
+        if self.peek() == Some(&Token::Name("let".to_string())) {
+            self.position += 1;
+            self.descend()?;
+            let name = match self.next("a name after `let`")? {
+                (Token::Name(name), _) => { name }
+                (token, position) => { return Err(ParseError::Expected { expected: "a name after `let`".to_string(), found: token.to_string(), position }); }
+            };
+            self.expect('=')?;
+            let value = self.sum()?;
+            self.expect(';')?;
+            let body = self.expressions()?;
+            self.depth -= 1;
+            return Ok(Expression::Let { name, value: Box::new(value), body: Box::new(body) });
+        }
+        self.sum()
+    }
+    fn sum(&mut self) -> Result<Expression, ParseError> {
+        let depth = self.depth;
+        let mut out = self.product()?;
+        loop {
+            let node_type_class = if self.accept('+') { NodeTypeClass::Add } else if self.accept('-') { NodeTypeClass::Sub } else { break; };
+            // chains are left nested, i.e., `out` gets one level deeper with every operator.
+            self.descend()?;
+            let rhs = self.product()?;
+            out = Expression::Operation { node_type_class, operands: vec![out, rhs] };
+        }
+        self.depth = depth;
+        Ok(out)
+    }
+    fn product(&mut self) -> Result<Expression, ParseError> {
+        let depth = self.depth;
+        let mut out = self.unary()?;
+        loop {
+            let node_type_class = if self.accept('*') { NodeTypeClass::Mul } else if self.accept('/') { NodeTypeClass::Div } else { break; };
+            self.descend()?;
+            let rhs = self.unary()?;
+            out = Expression::Operation { node_type_class, operands: vec![out, rhs] };
+        }
+        self.depth = depth;
+        Ok(out)
+    }
+    fn unary(&mut self) -> Result<Expression, ParseError> {
+        if self.accept('-') {
+            self.descend()?;
+            let operand = self.unary()?;
+            self.depth -= 1;
+            return Ok(Expression::Operation { node_type_class: NodeTypeClass::Neg, operands: vec![operand] });
+        }
+        self.power()
+    }
+    fn power(&mut self) -> Result<Expression, ParseError> {
+        let base = self.primary()?;
+        if self.accept('^') {
+            self.descend()?;
+            let exponent = self.unary()?;
+            self.depth -= 1;
+            return Ok(Expression::Operation { node_type_class: NodeTypeClass::Powf, operands: vec![base, exponent] });
+        }
+        Ok(base)
+    }
+    fn primary(&mut self) -> Result<Expression, ParseError> {
+        match self.next("an operand")? {
+            (Token::Number(number), _) => { Ok(Expression::Constant(number)) }
+            (Token::Symbol('('), _) => {
+                self.descend()?;
+                let out = self.sum()?;
+                self.expect(')')?;
+                self.depth -= 1;
+                Ok(out)
+            }
+            (Token::Name(name), position) => {
+                if !self.accept('(') {
+                    return Ok(match name.as_str() {
+                        "pi" => { Expression::Constant(std::f64::consts::PI) }
+                        "e" => { Expression::Constant(std::f64::consts::E) }
+                        _ => { Expression::Variable(name) }
+                    });
+                }
+                self.descend()?;
+                let mut operands = vec![self.sum()?];
+                while self.accept(',') { operands.push(self.sum()?); }
+                self.expect(')')?;
+                self.depth -= 1;
+                function(&name, operands, position)
+            }
+            (token, position) => { Err(ParseError::UnexpectedToken { token: token.to_string(), position }) }
+        }
+    }
+}
+
+fn function(name: &str, mut operands: Vec<Expression>, position: usize) -> Result<Expression, ParseError> {
+    let (node_type_class, num_operands) = if name == "ln" {
+        (NodeTypeClass::Log, 1)
+    } else if let Some(node_type_class) = ONE_OPERAND_FUNCTIONS.iter().find(|x| function_name(**x) == name) {
+        (*node_type_class, 1)
+    } else if let Some(node_type_class) = TWO_OPERAND_FUNCTIONS.iter().find(|x| function_name(**x) == name) {
+        (*node_type_class, 2)
+    } else {
+        return Err(ParseError::UnknownFunction { name: name.to_string(), position });
+    };
+    if operands.len() != num_operands {
+        return Err(ParseError::WrongNumberOfArguments { name: name.to_string(), expected: num_operands, given: operands.len(), position });
+    }
+    // same as `ln` on f64ad, which is `log` with base `E`.
+    if name == "ln" { operands.push(Expression::Constant(std::f64::consts::E)); }
+
+    Ok(Expression::Operation { node_type_class, operands })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::f64ad::{f64ad, GlobalComputationGraphs};
+    use super::{Expression, MAX_DEPTH, ParseError, UnknownVariable};
+
+    #[test]
+    fn parse_errors_have_positions() {
+        let cases = [
+            ("sin(x", ParseError::UnexpectedEnd { expected: "`)`".to_string(), position: 5 }),
+            ("x + * y", ParseError::UnexpectedToken { token: "*".to_string(), position: 4 }),
+            ("x + foo(x)", ParseError::UnknownFunction { name: "foo".to_string(), position: 4 }),
+            ("atan2(x)", ParseError::WrongNumberOfArguments { name: "atan2".to_string(), expected: 2, given: 1, position: 0 }),
+            ("sin(x, y)", ParseError::WrongNumberOfArguments { name: "sin".to_string(), expected: 1, given: 2, position: 0 }),
+            ("x $ y", ParseError::UnexpectedCharacter { character: '$', position: 2 }),
+            ("let = 3; x", ParseError::Expected { expected: "a name after `let`".to_string(), found: "=".to_string(), position: 4 }),
+            ("2 * 1.2.3", ParseError::InvalidNumber { text: "1.2.3".to_string(), position: 4 }),
+            ("(x))", ParseError::UnexpectedToken { token: ")".to_string(), position: 3 }),
+            ("", ParseError::UnexpectedEnd { expected: "an operand".to_string(), position: 0 })
+        ];
+        for (s, error) in cases {
+            assert_eq!(Expression::parse(s), Err(error), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn unknown_variables() {
+        let expression = Expression::parse("let a = x; a + y").unwrap();
+        let unknown = UnknownVariable { name: "y".to_string() };
+        assert_eq!(expression.evaluate(&[("x", f64ad::f64(1.0))]), Err(unknown.clone()));
+        let computation_graph = GlobalComputationGraphs::get(Some("expression_parser_tests"), None);
+        assert_eq!(expression.evaluate_on(&computation_graph, &[("x", 1.0)]).err(), Some(unknown));
+        assert_eq!(computation_graph.num_nodes(), 0);
+        assert_eq!(expression.evaluate(&[("x", f64ad::f64(1.0)), ("y", f64ad::f64(2.0))]).unwrap().value(), 3.0);
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let n = 100_000;
+        let parentheses = format!("{}x{}", "(".repeat(n), ")".repeat(n));
+        let error = Expression::parse(&parentheses).unwrap_err();
+        assert_eq!(error, ParseError::NestedTooDeeply { max_depth: MAX_DEPTH, position: MAX_DEPTH });
+        for s in ["-".repeat(n) + "x", "x^".repeat(n) + "x", "x+".repeat(n) + "x", "x*".repeat(n) + "x", "sin(".repeat(n) + "x" + &")".repeat(n), "let a = 1; ".repeat(n) + "a"] {
+            assert!(matches!(Expression::parse(&s), Err(ParseError::NestedTooDeeply { .. })), "{}", &s[..20]);
+        }
+
+        // just within the limit, where the expression is evaluated and dropped as well.
+        let depth = MAX_DEPTH - 1;
+        let nested = Expression::parse(&format!("{}x{}", "(-".repeat(depth / 2), ")".repeat(depth / 2))).unwrap();
+        assert_eq!(nested.evaluate(&[("x", f64ad::f64(2.0))]).unwrap().value(), 2.0 * (-1.0f64).powi((depth / 2) as i32));
+        let sum = Expression::parse(&("x+".repeat(depth) + "x")).unwrap();
+        assert_eq!(sum.evaluate(&[("x", f64ad::f64(1.0))]).unwrap().value(), (depth + 1) as f64);
+    }
+}
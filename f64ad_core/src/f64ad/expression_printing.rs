@@ -0,0 +1,200 @@
+// Printing of the expression that produced a graph variable, as infix text or LaTeX.  Input
+// variables are named by the order they were spawned on their graph (`x0`, `x1`, ...).  The infix
+// format uses the same syntax as the expression parser, e.g., `sin(x0) * x1^2 + atan2(x1, x0)`.
+
+use std::fmt::Write;
+use crate::f64ad::{f64ad, ComputationGraph, NodeTypeClass};
+use crate::f64ad::dot_export::reachable_nodes;
+use crate::f64ad::tape::Operand;
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum ExpressionFormat {
+    Infix,
+    Latex
+}
+
+// Precedence levels, from loosest to tightest binding.  The rhs of a binary operator is wrapped in
+// parentheses unless it binds tighter, so the printed expression keeps the order of evaluation of
+// the graph.
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const NEGATION: u8 = 3;
+const POWER: u8 = 4;
+const ATOM: u8 = 5;
+
+/// If `share_subexpressions` is true, every node that is used more than once is printed once as a
+/// `let` binding (an aligned line in LaTeX) named by its node idx, and referred to by that name.
+pub(crate) fn to_expression_string(v: &f64ad, format: ExpressionFormat, share_subexpressions: bool) -> String {
+    let v = match v {
+        f64ad::f64(constant) => { return constant_string(*constant).0; }
+        _ => { *v }
+    };
+    let computation_graph = v.computation_graph();
+    let output = v.node_idx();
+
+    computation_graph.with_tape(|tape| {
+        let node_idxs = reachable_nodes(computation_graph, tape, output);
+        let mut input_count = 0;
+        let mut input_names = vec![None; output + 1];
+        for (node_idx, input_name) in input_names.iter_mut().enumerate() {
+            if tape.node_type_class(node_idx) == NodeTypeClass::InputVariable {
+                *input_name = Some(input_count);
+                input_count += 1;
+            }
+        }
+
+        let mut num_uses = vec![0usize; output + 1];
+        for node_idx in &node_idxs {
+            for parent in node_parents(computation_graph, tape.node_type_class(*node_idx), tape.operands(*node_idx)).iter().flatten() {
+                if let Operand::Variable(parent) = parent { num_uses[*parent] += 1; }
+            }
+        }
+
+        // The rendering of every node, and how it must be referred to from its children.
+        let mut rendered: Vec<Option<(String, u8)>> = vec![None; output + 1];
+        let mut bindings = vec![];
+        for node_idx in &node_idxs {
+            let node_idx = *node_idx;
+            let node_type_class = match (tape.node_type_class(node_idx), tape.operands(node_idx)) {
+                // `ln` is recorded as `log` with base `E`.
+                (NodeTypeClass::Log, [_, Some(Operand::Constant(base))]) if base == std::f64::consts::E => { NodeTypeClass::Ln }
+                (node_type_class, _) => { node_type_class }
+            };
+            let (s, precedence) = match input_names[node_idx] {
+                Some(input_idx) => { (variable_name("x", input_idx, format), ATOM) }
+                None => {
+                    let mut parents = node_parents(computation_graph, node_type_class, tape.operands(node_idx));
+                    if node_type_class == NodeTypeClass::Ln { parents.truncate(1); }
+                    let operands: Vec<Option<(String, u8)>> = parents.iter().map(|x| {
+                        x.map(|y| {
+                            match y {
+                                Operand::Variable(parent) => { rendered[parent].clone().unwrap() }
+                                Operand::Constant(constant) => { constant_string(constant) }
+                            }
+                        })
+                    }).collect();
+                    render_node(node_type_class, &operands, format)
+                }
+            };
+
+            if share_subexpressions && num_uses[node_idx] > 1 && input_names[node_idx].is_none() {
+                let name = variable_name("v", node_idx, format);
+                bindings.push((name.clone(), s));
+                rendered[node_idx] = Some((name, ATOM));
+            } else {
+                rendered[node_idx] = Some((s, precedence));
+            }
+        }
+
+        let expression = rendered[output].take().unwrap().0;
+        let mut out = String::new();
+        match format {
+            ExpressionFormat::Infix => {
+                for (name, s) in &bindings { writeln!(out, "let {} = {};", name, s).unwrap(); }
+                out += &expression;
+            }
+            ExpressionFormat::Latex => {
+                if bindings.is_empty() { return expression; }
+                writeln!(out, "\\begin{{aligned}}").unwrap();
+                for (name, s) in &bindings { writeln!(out, "{} &= {} \\\\", name, s).unwrap(); }
+                writeln!(out, "y &= {}", expression).unwrap();
+                out += "\\end{aligned}";
+            }
+        }
+        out
+    })
+}
+
+/// Operands of a node, or the inputs of the composite operation it is an output of.
+fn node_parents(computation_graph: &ComputationGraph, node_type_class: NodeTypeClass, operands: [Option<Operand>; 2]) -> Vec<Option<Operand>> {
+    match node_type_class {
+        NodeTypeClass::Composite { composite_idx, .. } => {
+            let record = computation_graph.get_composite(composite_idx);
+            record.input_node_idxs.iter().zip(record.input_values.iter()).map(|(x, y)| {
+                Some(match x {
+                    Some(parent) => { Operand::Variable(*parent) }
+                    None => { Operand::Constant(*y) }
+                })
+            }).collect()
+        }
+        _ => { operands.to_vec() }
+    }
+}
+
+fn variable_name(prefix: &str, idx: usize, format: ExpressionFormat) -> String {
+    match format {
+        ExpressionFormat::Infix => { format!("{}{}", prefix, idx) }
+        ExpressionFormat::Latex => { format!("{}_{{{}}}", prefix, idx) }
+    }
+}
+
+fn constant_string(constant: f64) -> (String, u8) {
+    let precedence = if constant.is_sign_negative() { NEGATION } else { ATOM };
+    let s = if constant == 0.0 || !constant.is_finite() || (1e-5..1e16).contains(&constant.abs()) { format!("{}", constant) } else { format!("{:e}", constant) };
+    (s, precedence)
+}
+
+/// Wraps `operand` in parentheses if it binds looser than `precedence`.
+fn wrap(operand: &(String, u8), precedence: u8, format: ExpressionFormat) -> String {
+    if operand.1 >= precedence { return operand.0.clone(); }
+    match format {
+        ExpressionFormat::Infix => { format!("({})", operand.0) }
+        ExpressionFormat::Latex => { format!("\\left({}\\right)", operand.0) }
+    }
+}
+
+fn render_node(node_type_class: NodeTypeClass, operands: &[Option<(String, u8)>], format: ExpressionFormat) -> (String, u8) {
+    let arg = |i: usize| operands[i].as_ref().unwrap();
+    let all_args = || operands.iter().flatten().map(|x| x.0.clone()).collect::<Vec<String>>().join(", ");
+    match format {
+        ExpressionFormat::Infix => {
+            let function = |name: &str| (format!("{}({})", name, all_args()), ATOM);
+            match node_type_class {
+                NodeTypeClass::InputVariable => { unreachable!() }
+                NodeTypeClass::Add => { (format!("{} + {}", wrap(arg(0), SUM, format), wrap(arg(1), PRODUCT, format)), SUM) }
+                NodeTypeClass::Sub => { (format!("{} - {}", wrap(arg(0), SUM, format), wrap(arg(1), PRODUCT, format)), SUM) }
+                NodeTypeClass::Mul => { (format!("{} * {}", wrap(arg(0), PRODUCT, format), wrap(arg(1), NEGATION, format)), PRODUCT) }
+                NodeTypeClass::Div => { (format!("{} / {}", wrap(arg(0), PRODUCT, format), wrap(arg(1), NEGATION, format)), PRODUCT) }
+                NodeTypeClass::Neg => { (format!("-{}", wrap(arg(0), POWER, format)), NEGATION) }
+                NodeTypeClass::Powf => { (format!("{}^{}", wrap(arg(0), ATOM, format), wrap(arg(1), POWER, format)), POWER) }
+                NodeTypeClass::Manual { .. } => { function("manual") }
+                NodeTypeClass::Composite { composite_idx, output_idx } => { function(&format!("composite{}_{}", composite_idx, output_idx)) }
+                _ => { function(&function_name(node_type_class)) }
+            }
+        }
+        ExpressionFormat::Latex => {
+            let function = |name: &str| (format!("{}\\left({}\\right)", name, all_args()), ATOM);
+            match node_type_class {
+                NodeTypeClass::InputVariable => { unreachable!() }
+                NodeTypeClass::Add => { (format!("{} + {}", wrap(arg(0), SUM, format), wrap(arg(1), PRODUCT, format)), SUM) }
+                NodeTypeClass::Sub => { (format!("{} - {}", wrap(arg(0), SUM, format), wrap(arg(1), PRODUCT, format)), SUM) }
+                NodeTypeClass::Mul => { (format!("{} \\cdot {}", wrap(arg(0), PRODUCT, format), wrap(arg(1), NEGATION, format)), PRODUCT) }
+                NodeTypeClass::Div => { (format!("\\frac{{{}}}{{{}}}", arg(0).0, arg(1).0), ATOM) }
+                NodeTypeClass::Neg => { (format!("-{}", wrap(arg(0), POWER, format)), NEGATION) }
+                NodeTypeClass::Powf => { (format!("{{{}}}^{{{}}}", wrap(arg(0), ATOM, format), arg(1).0), POWER) }
+                NodeTypeClass::Exp => { (format!("e^{{{}}}", arg(0).0), POWER) }
+                NodeTypeClass::Sqrt => { (format!("\\sqrt{{{}}}", arg(0).0), ATOM) }
+                NodeTypeClass::Abs => { (format!("\\left|{}\\right|", arg(0).0), ATOM) }
+                NodeTypeClass::Floor => { (format!("\\left\\lfloor {}\\right\\rfloor", arg(0).0), ATOM) }
+                NodeTypeClass::Ceil => { (format!("\\left\\lceil {}\\right\\rceil", arg(0).0), ATOM) }
+                NodeTypeClass::Log => { (format!("\\log_{{{}}}\\left({}\\right)", arg(1).0, arg(0).0), ATOM) }
+                NodeTypeClass::Sin | NodeTypeClass::Cos | NodeTypeClass::Tan | NodeTypeClass::Sinh | NodeTypeClass::Cosh | NodeTypeClass::Tanh |
+                NodeTypeClass::Ln | NodeTypeClass::Max | NodeTypeClass::Min => {
+                    function(&format!("\\{}", function_name(node_type_class)))
+                }
+                NodeTypeClass::Asin => { function("\\arcsin") }
+                NodeTypeClass::Acos => { function("\\arccos") }
+                NodeTypeClass::Atan => { function("\\arctan") }
+                NodeTypeClass::Signum => { function("\\operatorname{sgn}") }
+                NodeTypeClass::Manual { .. } => { function("\\operatorname{manual}") }
+                NodeTypeClass::Composite { composite_idx, output_idx } => { function(&format!("\\operatorname{{composite}}_{{{},{}}}", composite_idx, output_idx)) }
+                _ => { function(&format!("\\operatorname{{{}}}", function_name(node_type_class))) }
+            }
+        }
+    }
+}
+
+/// Name of the function a node type class is written as in infix expressions.
+pub(crate) fn function_name(node_type_class: NodeTypeClass) -> String {
+    format!("{:?}", node_type_class).to_lowercase()
+}
@@ -2,36 +2,44 @@
 
 use std::cell::RefCell;
 use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
 use rand::{Rng, thread_rng};
-use crate::f64ad::{ComputationGraph, f64ad, GenericComputationGraph, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::{ComputationGraph, f64ad, DerivativePolicy, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::non_finite_tracking::NonFiniteTracker;
+use crate::f64ad::graph_stats::SweepTimings;
+use crate::f64ad::composite_operations::{composite_input_node_idxs, CompositeOperation, CompositeRecord};
+use crate::f64ad::tape::{Tape, TapeMark};
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy)]
 pub struct f64ad_var_1 {
-    computation_graph_id: usize,
-    node_idx: usize,
+    node_idx: u32,
+    generation: u32,
     computation_graph: &'static ComputationGraph
 }
 impl f64ad_var_1 {
-    #[allow(dead_code)]
-    pub (crate) fn new(computation_graph_id: usize, node_idx: usize, computation_graph: &'static ComputationGraph) -> Self {
+    pub fn new(node_idx: usize, generation: u32, computation_graph: &'static ComputationGraph) -> Self {
         Self {
-            computation_graph_id,
-            node_idx,
+            node_idx: node_idx as u32,
+            generation,
             computation_graph
         }
     }
     #[inline(always)]
     pub fn value(&self) -> f64 {
-        self.computation_graph.get_node_value(self.node_idx)
+        self.computation_graph.get_variable_value(self.node_idx(), self.generation)
     }
     #[inline(always)]
     pub fn computation_graph_id(&self) -> usize {
-        self.computation_graph_id
+        self.computation_graph.computation_graph_id()
     }
     #[inline(always)]
     pub fn node_idx(&self) -> usize {
-        self.node_idx
+        self.node_idx as usize
+    }
+    #[inline(always)]
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
     #[inline(always)]
     pub fn computation_graph(&self) -> &'static ComputationGraph {
@@ -51,8 +59,14 @@ impl Debug for f64ad_var_1 {
 
 pub struct ComputationGraph1 {
     computation_graph_id: usize,
-    generic_computation_graph: RefCell<GenericComputationGraph<F64ADNode1>>,
-    pub (crate) paused: bool
+    pub (crate) derivative_policy: DerivativePolicy,
+    pub (crate) non_finite_tracker: NonFiniteTracker,
+    pub (crate) sweep_timings: SweepTimings,
+    tape: RefCell<Tape>,
+    /// Local partial derivatives of every node on the tape, aligned with its operand slots.
+    local_partials: RefCell<Vec<[f64; 2]>>,
+    composites: RefCell<Vec<CompositeRecord>>,
+    pub (crate) pause_depth: usize
 }
 impl ComputationGraph1 {
     pub (crate) fn new() -> Self {
@@ -60,84 +74,110 @@ impl ComputationGraph1 {
         let id: usize = rng.gen();
         Self {
             computation_graph_id: id,
-            generic_computation_graph: RefCell::new(GenericComputationGraph::new()),
-            paused: false
+            derivative_policy: DerivativePolicy::default(),
+            non_finite_tracker: NonFiniteTracker::default(),
+            sweep_timings: SweepTimings::default(),
+            tape: RefCell::new(Tape::new()),
+            local_partials: RefCell::new(Vec::with_capacity(1_000_000)),
+            composites: RefCell::new(vec![]),
+            pause_depth: 0
         }
     }
+    /// Local partial derivatives are computed here, using the derivative policy that is set when
+    /// the node is added, so derivative sweeps on this graph never re-evaluate them.
     #[inline(always)]
     pub fn add_node(&self, value: f64, node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, parent_0: Option<f64ad>, parent_1: Option<f64ad>, computation_graph: &'static ComputationGraph) -> f64ad {
-        let node_idx = self.generic_computation_graph.borrow().curr_idx;
-        self.generic_computation_graph.borrow_mut().push(F64ADNode1 {
-            node_idx,
-            node_type_class,
-            node_operands_mode,
-            value,
-            parent_0,
-            parent_1
-        });
-        let ret = f64ad::f64ad_var_1(f64ad_var_1 {
-            computation_graph_id: self.computation_graph_id,
-            node_idx,
-            computation_graph
+        let node_idx = self.tape.borrow_mut().push(value, node_type_class, parent_0, parent_1);
+        let binding = self.tape.borrow();
+        debug_assert_eq!(binding.node_operands_mode(node_idx), node_operands_mode);
+        self.local_partials.borrow_mut().push(binding.local_partials(node_idx, &self.derivative_policy));
+
+        f64ad::f64ad_var_1(f64ad_var_1::new(node_idx, binding.generation(), computation_graph))
+    }
+    pub(crate) fn add_composite(&self, inputs: &[f64ad], input_values: Vec<f64>, output_values: Vec<f64>, operation: Rc<dyn CompositeOperation>, computation_graph: &'static ComputationGraph) -> Vec<f64ad> {
+        let composite_idx = self.composites.borrow().len();
+        let input_node_idxs = composite_input_node_idxs(inputs, self.computation_graph_id);
+        let first_output_node_idx = self.num_nodes();
+        let num_outputs = output_values.len();
+
+        let mut out = vec![];
+        for (output_idx, value) in output_values.iter().enumerate() {
+            out.push(self.add_node(*value, NodeTypeClass::Composite { composite_idx, output_idx }, NodeOperandsMode::NoParents, None, None, computation_graph));
+        }
+
+        self.composites.borrow_mut().push(CompositeRecord {
+            operation,
+            input_node_idxs,
+            input_values,
+            first_output_node_idx,
+            num_outputs
         });
-        ret
+
+        out
+    }
+    pub(crate) fn composite(&self, composite_idx: usize) -> CompositeRecord {
+        self.composites.borrow()[composite_idx].clone()
+    }
+    /// First order backwards mode sweep over the local partials recorded in `add_node`.
+    pub (crate) fn backwards_mode_grad(&self, node_idx: usize) -> Vec<f64> {
+        let local_partials = self.local_partials.borrow();
+        self.tape.borrow().backwards_mode_grad(node_idx, &self.composites.borrow(), |x| local_partials[x])
+    }
+    /// First order forward mode sweep over the local partials recorded in `add_node`.
+    pub (crate) fn forward_mode_grad(&self, node_idx: usize) -> Vec<f64> {
+        let local_partials = self.local_partials.borrow();
+        self.tape.borrow().forward_mode_grad(node_idx, &self.composites.borrow(), |x| local_partials[x])
+    }
+    pub fn mark(&self) -> TapeMark {
+        self.tape.borrow().mark(self.computation_graph_id, self.composites.borrow().len())
+    }
+    pub fn rewind_to(&self, mark: &TapeMark) {
+        self.tape.borrow_mut().rewind_to(mark, self.computation_graph_id);
+        self.local_partials.borrow_mut().truncate(mark.num_nodes);
+        self.composites.borrow_mut().truncate(mark.num_composites);
+        self.non_finite_tracker.truncate(mark.num_nodes);
     }
     #[inline(always)]
     pub fn computation_graph_id(&self) -> usize {
         self.computation_graph_id
     }
     #[inline(always)]
-    pub fn computation_graph(&self) -> &RefCell<GenericComputationGraph<F64ADNode1>> {
-        &self.generic_computation_graph
+    pub fn tape(&self) -> &RefCell<Tape> {
+        &self.tape
     }
     #[inline(always)]
     pub fn num_nodes(&self) -> usize {
-        self.generic_computation_graph.borrow().curr_idx()
+        self.tape.borrow().len()
+    }
+    /// Includes the local partials recorded for every node.
+    pub fn size_in_bytes(&self) -> usize {
+        self.tape.borrow().size_in_bytes() + self.local_partials.borrow().len() * std::mem::size_of::<[f64; 2]>()
+    }
+    #[inline(always)]
+    pub fn derivative_policy(&self) -> DerivativePolicy {
+        self.derivative_policy
     }
     pub fn soft_reset(&mut self) {
         let mut rng = thread_rng();
         let id: usize = rng.gen();
         self.computation_graph_id = id;
-        self.generic_computation_graph.borrow_mut().reset();
+        self.tape.borrow_mut().clear();
+        self.local_partials.borrow_mut().clear();
+        self.composites.borrow_mut().clear();
+        self.non_finite_tracker.clear();
     }
     pub fn hard_reset(&mut self) {
+        // the derivative policy and debug mode are settings of the graph, not of its contents, so
+        // they are kept (as `ComputationGraphT::reset` does).
+        let derivative_policy = self.derivative_policy;
+        let debug_mode = self.non_finite_tracker.enabled;
+        let mut tape = std::mem::take(self.tape.get_mut());
+        tape.release();
         *self = Self::new();
+        *self.tape.get_mut() = tape;
+        self.derivative_policy = derivative_policy;
+        self.non_finite_tracker.enabled = debug_mode;
     }
     #[inline(always)]
-    pub (crate) fn paused(&self) -> bool { self.paused }
+    pub (crate) fn paused(&self) -> bool { self.pause_depth > 0 }
 }
-
-pub struct F64ADNode1 {
-    node_idx: usize,
-    node_type_class: NodeTypeClass,
-    node_operands_mode: NodeOperandsMode,
-    value: f64,
-    parent_0: Option<f64ad>,
-    parent_1: Option<f64ad>
-}
-impl F64ADNode1 {
-    #[inline(always)]
-    pub fn node_idx(&self) -> usize {
-        self.node_idx
-    }
-    #[inline(always)]
-    pub fn value(&self) -> f64 {
-        self.value
-    }
-    #[inline(always)]
-    pub fn parent_0(&self) -> &Option<f64ad> {
-        &self.parent_0
-    }
-    #[inline(always)]
-    pub fn parent_1(&self) -> &Option<f64ad> {
-        &self.parent_1
-    }
-    #[inline(always)]
-    pub fn node_type_class(&self) -> NodeTypeClass {
-        self.node_type_class
-    }
-    #[inline(always)]
-    pub fn node_operands_mode(&self) -> NodeOperandsMode {
-        self.node_operands_mode
-    }
-}
\ No newline at end of file
@@ -2,32 +2,41 @@
 
 use std::cell::RefCell;
 use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
 use rand::{Rng, thread_rng};
-use crate::f64ad::{ComputationGraph, f64ad, GenericComputationGraph, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::{ComputationGraph, f64ad, DerivativePolicy, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::non_finite_tracking::NonFiniteTracker;
+use crate::f64ad::graph_stats::SweepTimings;
+use crate::f64ad::nested_levels::NestedLevel;
+use crate::f64ad::composite_operations::{composite_input_node_idxs, CompositeOperation, CompositeRecord};
+use crate::f64ad::tape::{Tape, TapeMark};
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy)]
 pub struct f64ad_var_f {
-    computation_graph_id: usize,
-    node_idx: usize,
+    node_idx: u32,
+    generation: u32,
     computation_graph: &'static ComputationGraph
 }
 impl f64ad_var_f {
-    pub fn new(computation_graph_id: usize, node_idx: usize, computation_graph: &'static ComputationGraph) -> Self {
+    pub fn new(node_idx: usize, generation: u32, computation_graph: &'static ComputationGraph) -> Self {
         Self {
-            computation_graph_id,
-            node_idx,
+            node_idx: node_idx as u32,
+            generation,
             computation_graph
         }
     }
     pub fn value(&self) -> f64 {
-        self.computation_graph.get_node_value(self.node_idx)
+        self.computation_graph.get_variable_value(self.node_idx(), self.generation)
     }
     pub fn computation_graph_id(&self) -> usize {
-        self.computation_graph_id
+        self.computation_graph.computation_graph_id()
     }
     pub fn node_idx(&self) -> usize {
-        self.node_idx
+        self.node_idx as usize
+    }
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
     pub fn computation_graph(&self) -> &'static ComputationGraph {
         self.computation_graph
@@ -46,7 +55,15 @@ impl Debug for f64ad_var_f {
 
 pub struct ComputationGraphF {
     computation_graph_id: usize,
-    generic_computation_graph: RefCell<GenericComputationGraph<F64ADNodeF>>
+    pub (crate) derivative_policy: DerivativePolicy,
+    pub (crate) non_finite_tracker: NonFiniteTracker,
+    pub (crate) sweep_timings: SweepTimings,
+    tape: RefCell<Tape>,
+    composites: RefCell<Vec<CompositeRecord>>,
+    pub (crate) pause_depth: usize,
+    /// Set if this graph is an inner level of another graph.
+    pub (crate) nested_level: Option<NestedLevel>,
+    pub (crate) inner_level: Option<&'static ComputationGraph>
 }
 impl ComputationGraphF {
     pub (crate) fn new() -> Self {
@@ -54,77 +71,104 @@ impl ComputationGraphF {
         let id: usize = rng.gen();
         Self {
             computation_graph_id: id,
-            generic_computation_graph: RefCell::new(GenericComputationGraph::new())
+            derivative_policy: DerivativePolicy::default(),
+            non_finite_tracker: NonFiniteTracker::default(),
+            sweep_timings: SweepTimings::default(),
+            tape: RefCell::new(Tape::new()),
+            composites: RefCell::new(vec![]),
+            pause_depth: 0,
+            nested_level: None,
+            inner_level: None
         }
     }
     #[inline(always)]
     pub fn add_node(&self, value: f64, node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, parent_0: Option<f64ad>, parent_1: Option<f64ad>, computation_graph: &'static ComputationGraph) -> f64ad {
-        let node_idx = self.generic_computation_graph.borrow().curr_idx;
-        self.generic_computation_graph.borrow_mut().push(F64ADNodeF {
-            node_idx,
-            node_type_class,
-            node_operands_mode,
-            value,
-            parent_0,
-            parent_1
-        });
-        return f64ad::f64ad_var_f(f64ad_var_f {
-            computation_graph_id: self.computation_graph_id,
-            node_idx,
-            computation_graph
+        let node_idx = self.tape.borrow_mut().push(value, node_type_class, parent_0, parent_1);
+        debug_assert_eq!(self.tape.borrow().node_operands_mode(node_idx), node_operands_mode);
+        return f64ad::f64ad_var_f(f64ad_var_f::new(node_idx, self.tape.borrow().generation(), computation_graph));
+    }
+    pub(crate) fn add_composite(&self, inputs: &[f64ad], input_values: Vec<f64>, output_values: Vec<f64>, operation: Rc<dyn CompositeOperation>, computation_graph: &'static ComputationGraph) -> Vec<f64ad> {
+        let composite_idx = self.composites.borrow().len();
+        let input_node_idxs = composite_input_node_idxs(inputs, self.computation_graph_id);
+        let first_output_node_idx = self.num_nodes();
+        let num_outputs = output_values.len();
+
+        let mut out = vec![];
+        for (output_idx, value) in output_values.iter().enumerate() {
+            out.push(self.add_node(*value, NodeTypeClass::Composite { composite_idx, output_idx }, NodeOperandsMode::NoParents, None, None, computation_graph));
+        }
+
+        self.composites.borrow_mut().push(CompositeRecord {
+            operation,
+            input_node_idxs,
+            input_values,
+            first_output_node_idx,
+            num_outputs
         });
+
+        out
+    }
+    pub(crate) fn composite(&self, composite_idx: usize) -> CompositeRecord {
+        self.composites.borrow()[composite_idx].clone()
+    }
+    /// First order backwards mode sweep that only uses f64 values, for when the derivatives do not
+    /// need to be added to the graph.
+    pub (crate) fn backwards_mode_grad(&self, node_idx: usize) -> Vec<f64> {
+        let tape = self.tape.borrow();
+        tape.backwards_mode_grad(node_idx, &self.composites.borrow(), |x| tape.local_partials(x, &self.derivative_policy))
+    }
+    /// First order forward mode counterpart of `backwards_mode_grad`.
+    pub (crate) fn forward_mode_grad(&self, node_idx: usize) -> Vec<f64> {
+        let tape = self.tape.borrow();
+        tape.forward_mode_grad(node_idx, &self.composites.borrow(), |x| tape.local_partials(x, &self.derivative_policy))
+    }
+    pub fn mark(&self) -> TapeMark {
+        self.tape.borrow().mark(self.computation_graph_id, self.composites.borrow().len())
+    }
+    pub fn rewind_to(&self, mark: &TapeMark) {
+        self.tape.borrow_mut().rewind_to(mark, self.computation_graph_id);
+        self.composites.borrow_mut().truncate(mark.num_composites);
+        self.non_finite_tracker.truncate(mark.num_nodes);
+        if let Some(nested_level) = &self.nested_level { nested_level.truncate(mark.num_nodes); }
     }
     pub fn computation_graph_id(&self) -> usize {
         self.computation_graph_id
     }
-    pub fn computation_graph(&self) -> &RefCell<GenericComputationGraph<F64ADNodeF>> {
-        &self.generic_computation_graph
+    pub fn tape(&self) -> &RefCell<Tape> {
+        &self.tape
     }
     pub fn num_nodes(&self) -> usize {
-        self.generic_computation_graph.borrow().curr_idx()
+        self.tape.borrow().len()
+    }
+    pub fn derivative_policy(&self) -> DerivativePolicy {
+        self.derivative_policy
     }
     pub fn soft_reset(&mut self) {
         let mut rng = thread_rng();
         let id: usize = rng.gen();
         self.computation_graph_id = id;
-        self.generic_computation_graph.borrow_mut().reset();
+        self.tape.borrow_mut().clear();
+        self.composites.borrow_mut().clear();
+        self.non_finite_tracker.clear();
+        if let Some(nested_level) = &self.nested_level { nested_level.clear(); }
     }
     pub fn hard_reset(&mut self) {
+        let nested_level = self.nested_level.take();
+        let inner_level = self.inner_level;
+        // the derivative policy and debug mode are settings of the graph, not of its contents, so
+        // they are kept (as `ComputationGraphT::reset` does).
+        let derivative_policy = self.derivative_policy;
+        let debug_mode = self.non_finite_tracker.enabled;
+        let mut tape = std::mem::take(self.tape.get_mut());
+        tape.release();
         *self = Self::new();
-    }
-}
-
-pub struct F64ADNodeF {
-    node_idx: usize,
-    node_type_class: NodeTypeClass,
-    node_operands_mode: NodeOperandsMode,
-    value: f64,
-    parent_0: Option<f64ad>,
-    parent_1: Option<f64ad>
-}
-impl F64ADNodeF {
-    #[inline(always)]
-    pub fn node_idx(&self) -> usize {
-        self.node_idx
-    }
-    #[inline(always)]
-    pub fn value(&self) -> f64 {
-        self.value
+        *self.tape.get_mut() = tape;
+        self.derivative_policy = derivative_policy;
+        self.non_finite_tracker.enabled = debug_mode;
+        if let Some(nested_level) = &nested_level { nested_level.clear(); }
+        self.nested_level = nested_level;
+        self.inner_level = inner_level;
     }
     #[inline(always)]
-    pub fn parent_0(&self) -> &Option<f64ad> {
-        &self.parent_0
-    }
-    #[inline(always)]
-    pub fn parent_1(&self) -> &Option<f64ad> {
-        &self.parent_1
-    }
-    #[inline(always)]
-    pub fn node_type_class(&self) -> NodeTypeClass {
-        self.node_type_class
-    }
-    #[inline(always)]
-    pub fn node_operands_mode(&self) -> NodeOperandsMode {
-        self.node_operands_mode
-    }
-}
\ No newline at end of file
+    pub (crate) fn paused(&self) -> bool { self.pause_depth > 0 }
+}
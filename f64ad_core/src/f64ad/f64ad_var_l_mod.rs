@@ -2,34 +2,42 @@
 
 use std::cell::RefCell;
 use std::fmt::{Debug, Formatter};
-use crate::f64ad::{ComputationGraph, f64ad, NodeOperandsMode, NodeTypeClass};
+use rand::{Rng, thread_rng};
+use crate::f64ad::{ComputationGraph, compute_value_f64, f64ad, DerivativePolicy, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::non_finite_tracking::NonFiniteTracker;
+use crate::f64ad::graph_stats::SweepTimings;
+use crate::f64ad::tape::Tape;
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy)]
 pub struct f64ad_var_l {
-    computation_graph_id: usize,
-    node_idx: usize,
+    node_idx: u32,
+    generation: u32,
     computation_graph: &'static ComputationGraph
 }
 impl f64ad_var_l {
-    pub fn new(computation_graph_id: usize, node_idx: usize, computation_graph: &'static ComputationGraph) -> Self {
+    pub fn new(node_idx: usize, generation: u32, computation_graph: &'static ComputationGraph) -> Self {
         Self {
-            computation_graph_id,
-            node_idx,
+            node_idx: node_idx as u32,
+            generation,
             computation_graph
         }
     }
     #[inline(always)]
     pub fn value(&self) -> f64 {
-        self.computation_graph.get_node_value(self.node_idx)
+        self.computation_graph.get_variable_value(self.node_idx(), self.generation)
     }
     #[inline(always)]
     pub fn computation_graph_id(&self) -> usize {
-        self.computation_graph_id
+        self.computation_graph.computation_graph_id()
     }
     #[inline(always)]
     pub fn node_idx(&self) -> usize {
-        self.node_idx
+        self.node_idx as usize
+    }
+    #[inline(always)]
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
     #[inline(always)]
     pub fn computation_graph(&self) -> &'static ComputationGraph {
@@ -49,30 +57,41 @@ impl Debug for f64ad_var_l {
 
 pub struct ComputationGraphL {
     pub (crate) computation_graph_id: usize,
-    pub (crate) locked_nodes: RefCell<Vec<F64ADNodeL>>,
-    pub (crate) count: RefCell<usize>
+    pub (crate) derivative_policy: DerivativePolicy,
+    pub (crate) non_finite_tracker: NonFiniteTracker,
+    pub (crate) sweep_timings: SweepTimings,
+    pub (crate) tape: RefCell<Tape>,
+    pub (crate) count: RefCell<usize>,
+    pub (crate) pause_depth: usize,
+    /// Node idxs of the outputs marked on the tracer graph this graph was locked from.
+    pub (crate) outputs: Vec<usize>
 }
 impl ComputationGraphL {
+    pub (crate) fn new(derivative_policy: DerivativePolicy, tape: Tape, outputs: Vec<usize>) -> Self {
+        let mut rng = thread_rng();
+        let id: usize = rng.gen();
+        Self {
+            computation_graph_id: id,
+            derivative_policy,
+            non_finite_tracker: NonFiniteTracker::default(),
+            sweep_timings: SweepTimings::default(),
+            tape: RefCell::new(tape),
+            count: RefCell::new(0),
+            pause_depth: 0,
+            outputs
+        }
+    }
     #[inline(always)]
     pub fn add_node(&self, value: f64, node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, parent_0: Option<f64ad>, parent_1: Option<f64ad>, computation_graph: &'static ComputationGraph) -> f64ad {
-        let idx = self.count.borrow().clone();
-
-        assert_eq!(self.locked_nodes.borrow()[idx].node_type_class, node_type_class, "looks like this computation could not be locked!");
-        assert_eq!(self.locked_nodes.borrow()[idx].node_operands_mode, node_operands_mode, "looks like this computation could not be locked!");
+        let idx = *self.count.borrow();
 
-        let mut b = self.locked_nodes.borrow_mut();
-
-        b[idx].value = value;
-        b[idx].parent_0 = parent_0.clone();
-        b[idx].parent_1 = parent_1.clone();
+        let overwritten = self.tape.borrow_mut().overwrite(idx, value, node_type_class, parent_0, parent_1);
+        assert!(overwritten, "looks like this computation could not be locked!");
+        debug_assert_eq!(self.tape.borrow().node_operands_mode(idx), node_operands_mode);
 
         *self.count.borrow_mut() += 1;
 
-        f64ad::f64ad_var_l(f64ad_var_l {
-            computation_graph_id: self.computation_graph_id,
-            node_idx: idx,
-            computation_graph
-        })
+        f64ad::f64ad_var_l(f64ad_var_l::new(idx, self.tape.borrow().generation(), computation_graph))
     }
     #[inline(always)]
     pub fn computation_graph_id(&self) -> usize {
@@ -80,48 +99,56 @@ impl ComputationGraphL {
     }
     #[inline(always)]
     pub fn num_nodes(&self) -> usize {
-        self.locked_nodes.borrow().len()
+        self.tape.borrow().len()
     }
     #[inline(always)]
-    pub fn locked_nodes(&self) -> &RefCell<Vec<F64ADNodeL>> {
-        &self.locked_nodes
-    }
-    pub fn reset(&mut self) {
-        *self.count.borrow_mut() = 0;
-    }
-}
-
-pub struct F64ADNodeL {
-    pub (crate) node_idx: usize,
-    pub (crate) node_type_class: NodeTypeClass,
-    pub (crate) node_operands_mode: NodeOperandsMode,
-    pub (crate) value: f64,
-    pub (crate) parent_0: Option<f64ad>,
-    pub (crate) parent_1: Option<f64ad>
-}
-impl F64ADNodeL {
-    #[inline(always)]
-    pub fn node_idx(&self) -> usize {
-        self.node_idx
-    }
-    #[inline(always)]
-    pub fn value(&self) -> f64 {
-        self.value
+    pub fn derivative_policy(&self) -> DerivativePolicy {
+        self.derivative_policy
     }
     #[inline(always)]
-    pub fn parent_0(&self) -> &Option<f64ad> {
-        &self.parent_0
+    pub fn tape(&self) -> &RefCell<Tape> {
+        &self.tape
+    }
+    /// First order backwards mode sweep that only uses f64 values.  Locked graphs never contain
+    /// composite nodes.
+    pub (crate) fn backwards_mode_grad(&self, node_idx: usize) -> Vec<f64> {
+        let tape = self.tape.borrow();
+        tape.backwards_mode_grad(node_idx, &[], |x| tape.local_partials(x, &self.derivative_policy))
+    }
+    /// First order forward mode counterpart of `backwards_mode_grad`.
+    pub (crate) fn forward_mode_grad(&self, node_idx: usize) -> Vec<f64> {
+        let tape = self.tape.borrow();
+        tape.forward_mode_grad(node_idx, &[], |x| tape.local_partials(x, &self.derivative_policy))
+    }
+    /// Recomputes the values of all nodes from new values of the input variables, in the order
+    /// they were spawned, without replaying the computation that was locked.
+    pub fn evaluate(&self, inputs: &[f64]) {
+        let mut tape = self.tape.borrow_mut();
+        let mut inputs = inputs.iter();
+        for node_idx in 0..tape.len() {
+            let node_type_class = tape.node_type_class(node_idx);
+            let value = match node_type_class {
+                NodeTypeClass::InputVariable => { *inputs.next().expect("not enough inputs were given to evaluate the locked graph.") }
+                NodeTypeClass::Manual { .. } | NodeTypeClass::Composite { .. } => { panic!("cannot evaluate {:?} nodes on a locked graph.", node_type_class) }
+                _ => {
+                    let [lhs, rhs] = tape.operand_values(node_idx);
+                    compute_value_f64(f64ad::f64(lhs.unwrap()), rhs.map(f64ad::f64), node_type_class)
+                }
+            };
+            tape.set_value(node_idx, value);
+        }
+        assert!(inputs.next().is_none(), "too many inputs were given to evaluate the locked graph.");
+        *self.count.borrow_mut() = tape.len();
+        self.non_finite_tracker.clear();
     }
     #[inline(always)]
-    pub fn parent_1(&self) -> &Option<f64ad> {
-        &self.parent_1
+    pub fn outputs(&self) -> &Vec<usize> {
+        &self.outputs
     }
-    #[inline(always)]
-    pub fn node_type_class(&self) -> NodeTypeClass {
-        self.node_type_class
+    pub fn reset(&mut self) {
+        *self.count.borrow_mut() = 0;
+        self.non_finite_tracker.clear();
     }
     #[inline(always)]
-    pub fn node_operands_mode(&self) -> NodeOperandsMode {
-        self.node_operands_mode
-    }
-}
\ No newline at end of file
+    pub (crate) fn paused(&self) -> bool { self.pause_depth > 0 }
+}
@@ -3,34 +3,41 @@
 use std::cell::RefCell;
 use std::fmt::{Debug, Formatter};
 use rand::{Rng, thread_rng};
-use crate::f64ad::{ComputationGraph, f64ad, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::{ComputationGraph, f64ad, DerivativePolicy, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::non_finite_tracking::NonFiniteTracker;
+use crate::f64ad::graph_stats::SweepTimings;
+use crate::f64ad::tape::{Tape, TapeMark};
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy)]
 pub struct f64ad_var_t {
-    computation_graph_id: usize,
-    node_idx: usize,
+    node_idx: u32,
+    generation: u32,
     computation_graph: &'static ComputationGraph
 }
 impl f64ad_var_t {
-    pub fn new(computation_graph_id: usize, node_idx: usize, computation_graph: &'static ComputationGraph) -> Self {
+    pub fn new(node_idx: usize, generation: u32, computation_graph: &'static ComputationGraph) -> Self {
         Self {
-            computation_graph_id,
-            node_idx,
+            node_idx: node_idx as u32,
+            generation,
             computation_graph
         }
     }
     #[inline(always)]
     pub fn value(&self) -> f64 {
-        self.computation_graph.get_node_value(self.node_idx)
+        self.computation_graph.get_variable_value(self.node_idx(), self.generation)
     }
     #[inline(always)]
     pub fn computation_graph_id(&self) -> usize {
-        self.computation_graph_id
+        self.computation_graph.computation_graph_id()
     }
     #[inline(always)]
     pub fn node_idx(&self) -> usize {
-        self.node_idx
+        self.node_idx as usize
+    }
+    #[inline(always)]
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
     #[inline(always)]
     pub fn computation_graph(&self) -> &'static ComputationGraph {
@@ -50,7 +57,12 @@ impl Debug for f64ad_var_t {
 
 pub struct ComputationGraphT {
     computation_graph_id: usize,
-    computation_graph: RefCell<Vec<F64ADNodeT>>
+    pub (crate) derivative_policy: DerivativePolicy,
+    pub (crate) non_finite_tracker: NonFiniteTracker,
+    pub (crate) sweep_timings: SweepTimings,
+    tape: RefCell<Tape>,
+    pub (crate) pause_depth: usize,
+    outputs: RefCell<Vec<usize>>
 }
 impl ComputationGraphT {
     pub (crate) fn new() -> Self {
@@ -58,76 +70,60 @@ impl ComputationGraphT {
         let id: usize = rng.gen();
         Self {
             computation_graph_id: id,
-            computation_graph: RefCell::new(Vec::new())
+            derivative_policy: DerivativePolicy::default(),
+            non_finite_tracker: NonFiniteTracker::default(),
+            sweep_timings: SweepTimings::default(),
+            tape: RefCell::new(Tape::new()),
+            pause_depth: 0,
+            outputs: RefCell::new(vec![])
         }
     }
 
     #[inline(always)]
     pub fn add_node(&self, value: f64, node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, parent_0: Option<f64ad>, parent_1: Option<f64ad>, computation_graph: &'static ComputationGraph) -> f64ad {
-        let node_idx = self.computation_graph.borrow().len();
-        self.computation_graph.borrow_mut().push(F64ADNodeT {
-            node_idx,
-            node_type_class,
-            node_operands_mode,
-            value,
-            parent_0,
-            parent_1
-        });
-        let ret = f64ad::f64ad_var_t(f64ad_var_t {
-            computation_graph_id: self.computation_graph_id,
-            node_idx,
-            computation_graph
-        });
-        ret
+        let node_idx = self.tape.borrow_mut().push(value, node_type_class, parent_0, parent_1);
+        debug_assert_eq!(self.tape.borrow().node_operands_mode(node_idx), node_operands_mode);
+        f64ad::f64ad_var_t(f64ad_var_t::new(node_idx, self.tape.borrow().generation(), computation_graph))
     }
 
+    pub fn mark(&self) -> TapeMark {
+        self.tape.borrow().mark(self.computation_graph_id, 0)
+    }
+    pub fn rewind_to(&self, mark: &TapeMark) {
+        self.tape.borrow_mut().rewind_to(mark, self.computation_graph_id);
+        self.non_finite_tracker.truncate(mark.num_nodes);
+        self.outputs.borrow_mut().retain(|x| *x < mark.num_nodes);
+    }
     #[inline(always)]
     pub fn computation_graph_id(&self) -> usize {
         self.computation_graph_id
     }
     #[inline(always)]
     pub fn num_nodes(&self) -> usize {
-        self.computation_graph.borrow().len()
-    }
-    pub fn reset(&mut self) {
-        *self = Self::new();
-    }
-    pub fn computation_graph(&self) -> &RefCell<Vec<F64ADNodeT>> {
-        &self.computation_graph
-    }
-}
-
-pub struct F64ADNodeT {
-    node_idx: usize,
-    node_type_class: NodeTypeClass,
-    node_operands_mode: NodeOperandsMode,
-    value: f64,
-    parent_0: Option<f64ad>,
-    parent_1: Option<f64ad>
-}
-impl F64ADNodeT {
-    #[inline(always)]
-    pub fn node_idx(&self) -> usize {
-        self.node_idx
+        self.tape.borrow().len()
     }
     #[inline(always)]
-    pub fn value(&self) -> f64 {
-        self.value
+    pub fn derivative_policy(&self) -> DerivativePolicy {
+        self.derivative_policy
     }
-    #[inline(always)]
-    pub fn parent_0(&self) -> &Option<f64ad> {
-        &self.parent_0
+    pub fn reset(&mut self) {
+        let mut rng = thread_rng();
+        let id: usize = rng.gen();
+        self.computation_graph_id = id;
+        self.tape.borrow_mut().clear();
+        self.non_finite_tracker.clear();
+        self.outputs.borrow_mut().clear();
     }
-    #[inline(always)]
-    pub fn parent_1(&self) -> &Option<f64ad> {
-        &self.parent_1
+    /// Marks the nodes that are the outputs of the traced computation.
+    pub fn set_outputs(&self, outputs: Vec<usize>) {
+        *self.outputs.borrow_mut() = outputs;
     }
-    #[inline(always)]
-    pub fn node_type_class(&self) -> NodeTypeClass {
-        self.node_type_class
+    pub fn outputs(&self) -> Vec<usize> {
+        self.outputs.borrow().clone()
     }
     #[inline(always)]
-    pub fn node_operands_mode(&self) -> NodeOperandsMode {
-        self.node_operands_mode
+    pub (crate) fn paused(&self) -> bool { self.pause_depth > 0 }
+    pub fn tape(&self) -> &RefCell<Tape> {
+        &self.tape
     }
-}
\ No newline at end of file
+}
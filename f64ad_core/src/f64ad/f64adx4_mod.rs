@@ -0,0 +1,651 @@
+// Four `f64ad` lanes packed into one SIMD value, following nalgebra's AoSoA model: a
+// `Vector3<f64adx4>` is four `Vector3<f64ad>`s side by side, so nalgebra's SIMD-aware code (e.g.,
+// `UnitQuaternion<f64adx4>` or `Isometry3<f64adx4>`) evaluates four configurations at once.  Every
+// operation is applied lane-wise and records the usual `f64ad` nodes, so lanes are independent
+// evaluations, either on one computation graph or on different ones, and each lane can be
+// differentiated like any other `f64ad`.
+//
+// Comparisons give an `AutoBoolx4` mask, so branches in nalgebra's SIMD code are taken per lane
+// through `select`.
+
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign};
+use approx::AbsDiffEq;
+use nalgebra::SimdValue;
+use num_traits::{Num, One, Signed, Zero};
+use simba::scalar::{ComplexField, Field, RealField, SubsetOf};
+use simba::simd::{AutoBoolx4, SimdComplexField, SimdPartialOrd, SimdRealField, SimdSigned};
+use crate::f64ad::{BackwardsModeGradOutput, f64ad, F64adType, ForwardModeGradOutput};
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct f64adx4(pub [f64ad; 4]);
+impl f64adx4 {
+    #[inline(always)]
+    pub fn new(lanes: [f64ad; 4]) -> Self {
+        Self(lanes)
+    }
+    /// Four constant lanes.
+    #[inline(always)]
+    pub fn from_f64s(values: [f64; 4]) -> Self {
+        Self(values.map(f64ad::f64))
+    }
+    #[inline(always)]
+    pub fn lane(&self, i: usize) -> f64ad {
+        self.0[i]
+    }
+    #[inline(always)]
+    pub fn value(&self) -> [f64; 4] {
+        self.0.map(|x| x.value())
+    }
+    /// Returns the lanes of this `f64adx4` as constants, i.e., no derivatives will flow back through
+    /// the result (stop-gradient).
+    #[inline(always)]
+    pub fn detach(&self) -> f64adx4 {
+        self.map(|x| x.detach())
+    }
+    /// Sweeps forward from each lane of this input.  Lanes that are constants are skipped.
+    pub fn forward_mode_grad(&self, add_to_computation_graph: bool) -> F64adx4ForwardModeGradOutput {
+        F64adx4ForwardModeGradOutput(self.0.map(|x| sweep_if_variable(&x, |x| x.forward_mode_grad(add_to_computation_graph))))
+    }
+    /// Sweeps backwards from each lane of this output.  Lanes that are constants are skipped.
+    pub fn backwards_mode_grad(&self, add_to_computation_graph: bool) -> F64adx4BackwardsModeGradOutput {
+        F64adx4BackwardsModeGradOutput(self.0.map(|x| sweep_if_variable(&x, |x| x.backwards_mode_grad(add_to_computation_graph))))
+    }
+    #[inline(always)]
+    fn map<F: Fn(f64ad) -> f64ad>(self, f: F) -> Self {
+        Self(self.0.map(f))
+    }
+    #[inline(always)]
+    fn zip_map<F: Fn(f64ad, f64ad) -> f64ad>(self, other: Self, f: F) -> Self {
+        Self([f(self.0[0], other.0[0]), f(self.0[1], other.0[1]), f(self.0[2], other.0[2]), f(self.0[3], other.0[3])])
+    }
+    #[inline(always)]
+    fn mask<F: Fn(&f64ad, &f64ad) -> bool>(self, other: Self, f: F) -> AutoBoolx4 {
+        AutoBoolx4::new(f(&self.0[0], &other.0[0]), f(&self.0[1], &other.0[1]), f(&self.0[2], &other.0[2]), f(&self.0[3], &other.0[3]))
+    }
+    #[inline(always)]
+    fn unzip(lanes: [(f64ad, f64ad); 4]) -> (Self, Self) {
+        (Self(lanes.map(|x| x.0)), Self(lanes.map(|x| x.1)))
+    }
+}
+
+fn sweep_if_variable<G, F: Fn(&f64ad) -> G>(v: &f64ad, f: F) -> Option<G> {
+    if v.map_to_type() == F64adType::F64 { None } else { Some(f(v)) }
+}
+
+/// Lane `i` holds the derivatives with respect to lane `i` of the input.
+#[derive(Clone, Debug)]
+pub struct F64adx4ForwardModeGradOutput([Option<ForwardModeGradOutput>; 4]);
+impl F64adx4ForwardModeGradOutput {
+    /// Lane-wise `∂output/∂input`.
+    pub fn wrt(&self, output: &f64adx4) -> f64adx4 {
+        let mut out = f64adx4::zero();
+        for i in 0..4 {
+            if let Some(grad) = &self.0[i] {
+                if output.0[i].map_to_type() != F64adType::F64 { out.0[i] = grad.wrt(&output.0[i]); }
+            }
+        }
+        out
+    }
+}
+
+/// Lane `i` holds the derivatives of lane `i` of the output.
+#[derive(Clone, Debug)]
+pub struct F64adx4BackwardsModeGradOutput([Option<BackwardsModeGradOutput>; 4]);
+impl F64adx4BackwardsModeGradOutput {
+    /// Lane-wise `∂output/∂input`.
+    pub fn wrt(&self, input: &f64adx4) -> f64adx4 {
+        let mut out = f64adx4::zero();
+        for i in 0..4 {
+            if let Some(grad) = &self.0[i] {
+                if input.0[i].map_to_type() != F64adType::F64 { out.0[i] = grad.wrt(&input.0[i]); }
+            }
+        }
+        out
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl SimdValue for f64adx4 {
+    type Element = f64ad;
+    type SimdBool = AutoBoolx4;
+
+    fn lanes() -> usize {
+        4
+    }
+
+    fn splat(val: Self::Element) -> Self {
+        Self([val; 4])
+    }
+
+    fn extract(&self, i: usize) -> Self::Element {
+        self.0[i]
+    }
+
+    unsafe fn extract_unchecked(&self, i: usize) -> Self::Element {
+        *self.0.get_unchecked(i)
+    }
+
+    fn replace(&mut self, i: usize, val: Self::Element) {
+        self.0[i] = val
+    }
+
+    unsafe fn replace_unchecked(&mut self, i: usize, val: Self::Element) {
+        *self.0.get_unchecked_mut(i) = val
+    }
+
+    fn select(self, cond: Self::SimdBool, other: Self) -> Self {
+        Self([0, 1, 2, 3].map(|i| if cond.0[i] { self.0[i] } else { other.0[i] }))
+    }
+}
+
+impl SimdPartialOrd for f64adx4 {
+    fn simd_gt(self, other: Self) -> Self::SimdBool { self.mask(other, |a, b| a > b) }
+
+    fn simd_lt(self, other: Self) -> Self::SimdBool { self.mask(other, |a, b| a < b) }
+
+    fn simd_ge(self, other: Self) -> Self::SimdBool { self.mask(other, |a, b| a >= b) }
+
+    fn simd_le(self, other: Self) -> Self::SimdBool { self.mask(other, |a, b| a <= b) }
+
+    fn simd_eq(self, other: Self) -> Self::SimdBool { self.mask(other, |a, b| a == b) }
+
+    fn simd_ne(self, other: Self) -> Self::SimdBool { self.mask(other, |a, b| a != b) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_max(self, other: Self) -> Self { self.zip_map(other, f64ad::max) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_min(self, other: Self) -> Self { self.zip_map(other, f64ad::min) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_clamp(self, min: Self, max: Self) -> Self { self.simd_max(min).simd_min(max) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_horizontal_min(self) -> Self::Element { self.0[0].min(self.0[1]).min(self.0[2].min(self.0[3])) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_horizontal_max(self) -> Self::Element { self.0[0].max(self.0[1]).max(self.0[2].max(self.0[3])) }
+}
+
+impl SimdSigned for f64adx4 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_abs(&self) -> Self { self.map(f64ad::abs) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_abs_sub(&self, other: &Self) -> Self { self.zip_map(*other, |a, b| Signed::abs_sub(&a, &b)) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_signum(&self) -> Self { self.map(f64ad::signum) }
+
+    fn is_simd_positive(&self) -> Self::SimdBool { self.mask(*self, |a, _| a.is_positive()) }
+
+    fn is_simd_negative(&self) -> Self::SimdBool { self.mask(*self, |a, _| a.is_negative()) }
+}
+
+impl SimdComplexField for f64adx4 {
+    type SimdRealField = f64adx4;
+
+    fn from_simd_real(re: Self::SimdRealField) -> Self { re }
+
+    fn simd_real(self) -> Self::SimdRealField { self }
+
+    fn simd_imaginary(self) -> Self::SimdRealField { Self::zero() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_modulus(self) -> Self::SimdRealField { self.map(f64ad::abs) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_modulus_squared(self) -> Self::SimdRealField { self * self }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_argument(self) -> Self::SimdRealField { self.map(ComplexField::argument) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_norm1(self) -> Self::SimdRealField { self.map(f64ad::abs) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_scale(self, factor: Self::SimdRealField) -> Self { self * factor }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_unscale(self, factor: Self::SimdRealField) -> Self { self / factor }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_to_exp(self) -> (Self::SimdRealField, Self) { Self::unzip(self.0.map(ComplexField::to_exp)) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_signum(self) -> Self { self.map(f64ad::signum) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_floor(self) -> Self { self.map(f64ad::floor) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_ceil(self) -> Self { self.map(f64ad::ceil) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_round(self) -> Self { self.map(f64ad::round) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_trunc(self) -> Self { self.map(f64ad::trunc) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_fract(self) -> Self { self.map(f64ad::fract) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_mul_add(self, a: Self, b: Self) -> Self { self * a + b }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_abs(self) -> Self::SimdRealField { self.map(f64ad::abs) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_hypot(self, other: Self) -> Self::SimdRealField { self.zip_map(other, f64ad::hypot) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_recip(self) -> Self { self.map(f64ad::recip) }
+
+    fn simd_conjugate(self) -> Self { self }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_sin(self) -> Self { self.map(f64ad::sin) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_cos(self) -> Self { self.map(f64ad::cos) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_sin_cos(self) -> (Self, Self) { Self::unzip(self.0.map(f64ad::sin_cos)) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_tan(self) -> Self { self.map(f64ad::tan) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_asin(self) -> Self { self.map(f64ad::asin) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_acos(self) -> Self { self.map(f64ad::acos) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_atan(self) -> Self { self.map(f64ad::atan) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_sinh(self) -> Self { self.map(f64ad::sinh) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_cosh(self) -> Self { self.map(f64ad::cosh) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_tanh(self) -> Self { self.map(f64ad::tanh) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_asinh(self) -> Self { self.map(f64ad::asinh) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_acosh(self) -> Self { self.map(f64ad::acosh) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_atanh(self) -> Self { self.map(f64ad::atanh) }
+
+    // The default `sinc`-like functions test whether all lanes are zero at once.
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_sinc(self) -> Self { self.map(ComplexField::sinc) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_sinhc(self) -> Self { self.map(ComplexField::sinhc) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_cosc(self) -> Self { self.map(ComplexField::cosc) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_coshc(self) -> Self { self.map(ComplexField::coshc) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_log(self, base: Self::SimdRealField) -> Self { self.zip_map(base, f64ad::log) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_log2(self) -> Self { self.map(f64ad::log2) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_log10(self) -> Self { self.map(f64ad::log10) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_ln(self) -> Self { self.map(f64ad::ln) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_ln_1p(self) -> Self { self.map(f64ad::ln_1p) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_sqrt(self) -> Self { self.map(f64ad::sqrt) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_exp(self) -> Self { self.map(f64ad::exp) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_exp2(self) -> Self { self.map(f64ad::exp2) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_exp_m1(self) -> Self { self.map(f64ad::exp_m1) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_powi(self, n: i32) -> Self { self.map(|x| x.powi(n)) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_powf(self, n: Self::SimdRealField) -> Self { self.zip_map(n, f64ad::powf) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_powc(self, n: Self) -> Self { self.zip_map(n, f64ad::powf) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_cbrt(self) -> Self { self.map(f64ad::cbrt) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_horizontal_sum(self) -> Self::Element { self.0.iter().sum() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_horizontal_product(self) -> Self::Element { self.0.iter().product() }
+}
+
+impl SimdRealField for f64adx4 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_copysign(self, sign: Self) -> Self { self.zip_map(sign, f64ad::copysign) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn simd_atan2(self, other: Self) -> Self { self.zip_map(other, f64ad::atan2) }
+
+    fn simd_default_epsilon() -> Self { Self::splat(f64ad::default_epsilon()) }
+
+    fn simd_pi() -> Self { Self::splat(f64ad::pi()) }
+
+    fn simd_two_pi() -> Self { Self::splat(f64ad::two_pi()) }
+
+    fn simd_frac_pi_2() -> Self { Self::splat(f64ad::frac_pi_2()) }
+
+    fn simd_frac_pi_3() -> Self { Self::splat(f64ad::frac_pi_3()) }
+
+    fn simd_frac_pi_4() -> Self { Self::splat(f64ad::frac_pi_4()) }
+
+    fn simd_frac_pi_6() -> Self { Self::splat(f64ad::frac_pi_6()) }
+
+    fn simd_frac_pi_8() -> Self { Self::splat(f64ad::frac_pi_8()) }
+
+    fn simd_frac_1_pi() -> Self { Self::splat(f64ad::frac_1_pi()) }
+
+    fn simd_frac_2_pi() -> Self { Self::splat(f64ad::frac_2_pi()) }
+
+    fn simd_frac_2_sqrt_pi() -> Self { Self::splat(f64ad::frac_2_sqrt_pi()) }
+
+    fn simd_e() -> Self { Self::splat(f64ad::e()) }
+
+    fn simd_log2_e() -> Self { Self::splat(f64ad::log2_e()) }
+
+    fn simd_log10_e() -> Self { Self::splat(f64ad::log10_e()) }
+
+    fn simd_ln_2() -> Self { Self::splat(f64ad::ln_2()) }
+
+    fn simd_ln_10() -> Self { Self::splat(f64ad::ln_10()) }
+}
+
+impl Field for f64adx4 {}
+
+impl SubsetOf<f64adx4> for f64adx4 {
+    fn to_superset(&self) -> f64adx4 {
+        *self
+    }
+
+    fn from_superset_unchecked(element: &f64adx4) -> Self {
+        *element
+    }
+
+    fn is_in_subset(_element: &f64adx4) -> bool {
+        true
+    }
+}
+
+impl SubsetOf<f64adx4> for f64 {
+    fn to_superset(&self) -> f64adx4 {
+        f64adx4::splat(f64ad::f64(*self))
+    }
+
+    /// Panics unless all lanes hold the same value.
+    fn from_superset_unchecked(element: &f64adx4) -> Self {
+        assert!(Self::is_in_subset(element), "an f64adx4 is only an f64 if all of its lanes are equal, but its lanes are {:?}.", element.value());
+        element.0[0].value()
+    }
+
+    fn is_in_subset(element: &f64adx4) -> bool {
+        let value = element.value();
+        value.iter().all(|x| *x == value[0])
+    }
+}
+
+impl Zero for f64adx4 {
+    fn zero() -> Self {
+        Self::splat(f64ad::f64(0.0))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|x| x.is_zero())
+    }
+}
+
+impl One for f64adx4 {
+    fn one() -> Self {
+        Self::splat(f64ad::f64(1.0))
+    }
+}
+
+impl Num for f64adx4 {
+    type FromStrRadixErr = ();
+
+    /// Parses a single value into all four lanes.
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        Ok(Self::splat(f64ad::from_str_radix(str, radix)?))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl Add<f64adx4> for f64adx4 {
+    type Output = f64adx4;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add(self, rhs: f64adx4) -> Self::Output {
+        self.zip_map(rhs, |a, b| a + b)
+    }
+}
+impl AddAssign for f64adx4 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<f64adx4> for f64adx4 {
+    type Output = f64adx4;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub(self, rhs: f64adx4) -> Self::Output {
+        self.zip_map(rhs, |a, b| a - b)
+    }
+}
+impl SubAssign for f64adx4 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<f64adx4> for f64adx4 {
+    type Output = f64adx4;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul(self, rhs: f64adx4) -> Self::Output {
+        self.zip_map(rhs, |a, b| a * b)
+    }
+}
+impl MulAssign for f64adx4 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Div<f64adx4> for f64adx4 {
+    type Output = f64adx4;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div(self, rhs: f64adx4) -> Self::Output {
+        self.zip_map(rhs, |a, b| a / b)
+    }
+}
+impl DivAssign for f64adx4 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl Rem<f64adx4> for f64adx4 {
+    type Output = f64adx4;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn rem(self, rhs: f64adx4) -> Self::Output {
+        self.zip_map(rhs, |a, b| a % b)
+    }
+}
+impl RemAssign for f64adx4 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl Neg for f64adx4 {
+    type Output = f64adx4;
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn neg(self) -> Self::Output {
+        self.map(|x| -x)
+    }
+}
+
+impl PartialEq for f64adx4 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Display for f64adx4 {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(&format!("[{}, {}, {}, {}]", self.0[0], self.0[1], self.0[2], self.0[3])).expect("error");
+        Ok(())
+    }
+}
+
+impl From<[f64ad; 4]> for f64adx4 {
+    fn from(lanes: [f64ad; 4]) -> Self {
+        Self(lanes)
+    }
+}
+impl From<f64ad> for f64adx4 {
+    fn from(a: f64ad) -> Self {
+        Self::splat(a)
+    }
+}
+impl From<f64> for f64adx4 {
+    fn from(a: f64) -> Self {
+        Self::splat(f64ad::f64(a))
+    }
+}
+impl From<f64adx4> for [f64ad; 4] {
+    fn from(a: f64adx4) -> Self {
+        a.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Isometry3, Point3, UnitQuaternion, Vector3};
+    use simba::scalar::SubsetOf;
+    use crate::f64ad::{f64ad, GlobalComputationGraphs};
+    use crate::f64ad::f64adx4_mod::f64adx4;
+
+    /// Checks the lane-wise Jacobian of `f` at `x`, where `x[j]` holds input `j` of each lane, from
+    /// backwards and forward mode sweeps, against central differences of `f64_f` on each lane, and
+    /// that no lane depends on another.
+    fn assert_lane_gradients_match_finite_differences(name: &str, f: &dyn Fn(&[f64adx4]) -> Vec<f64adx4>, f64_f: &dyn Fn(&[f64]) -> Vec<f64>, x: &[[f64; 4]]) {
+        let computation_graph = GlobalComputationGraphs::get(Some("f64adx4_tests"), None);
+        computation_graph.reset();
+        let inputs: Vec<f64adx4> = x.iter().map(|x| f64adx4::new(x.map(|x| computation_graph.spawn_variable(x)))).collect();
+        let outputs = f(&inputs);
+
+        let h = 1e-6;
+        for lane in 0..4 {
+            let x_lane: Vec<f64> = x.iter().map(|x| x[lane]).collect();
+            for (i, output) in outputs.iter().enumerate() {
+                assert!((output.value()[lane] - f64_f(&x_lane)[i]).abs() < 1e-12, "lane {} of output {} of {} is {}, but should be {}.", lane, i, name, output.value()[lane], f64_f(&x_lane)[i]);
+                let backwards = output.backwards_mode_grad(false);
+                for (j, input) in inputs.iter().enumerate() {
+                    let (mut plus, mut minus) = (x_lane.clone(), x_lane.clone());
+                    plus[j] += h;
+                    minus[j] -= h;
+                    let expected = (f64_f(&plus)[i] - f64_f(&minus)[i]) / (2.0 * h);
+                    for (mode, actual) in [("backwards", backwards.wrt(input)), ("forward", input.forward_mode_grad(false).wrt(output))] {
+                        let actual = actual.value()[lane];
+                        assert!((actual - expected).abs() <= 1e-7 * (1.0 + expected.abs()), "{} mode d output {} / d input {} of {} on lane {} is {}, but finite differences give {}.", mode, i, j, name, lane, actual, expected);
+                    }
+
+                    let lane_grad = output.lane(lane).backwards_mode_grad(false);
+                    for other_lane in (0..4).filter(|l| *l != lane) {
+                        assert_eq!(lane_grad.wrt(&input.lane(other_lane)).value(), 0.0, "lane {} of output {} of {} depends on lane {} of input {}.", lane, i, name, other_lane, j);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unit_quaternion_lane_gradients() {
+        let rotate = |euler: &[f64adx4]| -> Vec<f64adx4> {
+            let v = UnitQuaternion::from_euler_angles(euler[0], euler[1], euler[2]) * Vector3::new(f64adx4::from_f64s([0.4; 4]), f64adx4::from_f64s([-1.2; 4]), f64adx4::from_f64s([0.7; 4]));
+            v.iter().copied().collect()
+        };
+        let rotate_f64 = |euler: &[f64]| -> Vec<f64> {
+            (UnitQuaternion::from_euler_angles(euler[0], euler[1], euler[2]) * Vector3::new(0.4, -1.2, 0.7)).iter().copied().collect()
+        };
+        assert_lane_gradients_match_finite_differences("UnitQuaternion::from_euler_angles", &rotate, &rotate_f64, &[[0.3, -1.1, 2.0, 0.0], [0.5, 0.2, -0.9, 1.4], [-0.7, 2.5, 0.1, -3.0]]);
+    }
+
+    #[test]
+    fn isometry_lane_gradients() {
+        let transform = |x: &[f64adx4]| -> Vec<f64adx4> {
+            let isometry = Isometry3::new(Vector3::new(x[0], x[1], x[2]), Vector3::new(x[3], x[4], x[5]));
+            let p = isometry * Point3::new(f64adx4::from_f64s([0.4; 4]), f64adx4::from_f64s([-1.2; 4]), f64adx4::from_f64s([0.7; 4]));
+            let q = isometry.inverse() * Point3::new(f64adx4::from_f64s([1.0; 4]), f64adx4::from_f64s([0.5; 4]), f64adx4::from_f64s([-2.0; 4]));
+            p.iter().chain(q.iter()).copied().collect()
+        };
+        let transform_f64 = |x: &[f64]| -> Vec<f64> {
+            let isometry = Isometry3::new(Vector3::new(x[0], x[1], x[2]), Vector3::new(x[3], x[4], x[5]));
+            let p = isometry * Point3::new(0.4, -1.2, 0.7);
+            let q = isometry.inverse() * Point3::new(1.0, 0.5, -2.0);
+            p.iter().chain(q.iter()).copied().collect()
+        };
+        let x = [[1.0, -2.0, 0.5, 0.0], [0.3, 0.3, -1.5, 2.0], [-0.8, 1.1, 0.0, 0.6], [0.2, -0.6, 1.3, 0.01], [0.9, 0.4, -0.2, -0.3], [-0.5, 1.7, 0.8, 0.02]];
+        assert_lane_gradients_match_finite_differences("Isometry3", &transform, &transform_f64, &x);
+    }
+
+    #[test]
+    fn f64_subset() {
+        assert!(<f64 as SubsetOf<f64adx4>>::is_in_subset(&f64adx4::from_f64s([2.5; 4])));
+        assert!(!<f64 as SubsetOf<f64adx4>>::is_in_subset(&f64adx4::from_f64s([2.5, 2.5, 1.0, 2.5])));
+        assert_eq!(<f64 as SubsetOf<f64adx4>>::from_superset(&f64adx4::from_f64s([2.5; 4])), Some(2.5));
+        assert_eq!(<f64 as SubsetOf<f64adx4>>::from_superset(&f64adx4::from_f64s([2.5, 2.5, 1.0, 2.5])), None);
+        assert_eq!(<f64 as SubsetOf<f64adx4>>::from_superset(&f64adx4::new([f64ad::f64(-1.0); 4])), Some(-1.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn f64_from_unequal_lanes() {
+        <f64 as SubsetOf<f64adx4>>::from_superset_unchecked(&f64adx4::from_f64s([2.5, 2.5, 1.0, 2.5]));
+    }
+}
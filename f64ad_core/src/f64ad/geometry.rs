@@ -0,0 +1,227 @@
+// Differentiable rotations and rigid transforms.  nalgebra's geometry types work with `f64ad`
+// through `RealField`, but several of their formulas are singular at the identity even though the
+// functions they compute are smooth there: `from_scaled_axis` divides by `|ω|`, and `angle()` is
+// `acos`-based, so `f64ad` derivatives come out as NaN (`0 * inf`) exactly where robotics code
+// tends to start from.
+//
+// The maps here are written in terms of `θ² = ωᵀω` and switch to Taylor expansions of their
+// coefficients (e.g., `sin(θ/2)/θ`) for small angles, so they stay smooth, including higher order
+// derivatives, through the identity.  The branch is chosen on values only, so both sides are
+// ordinary `f64ad` operations.
+
+use nalgebra::{Isometry3, Quaternion, Translation3, UnitQuaternion, Vector3};
+use crate::f64ad::f64ad;
+
+/// Below this `θ²`, coefficient functions are evaluated from their Taylor expansions, which are
+/// truncated where the first omitted term is below `10⁻¹⁶` relative to the coefficient.
+const SMALL_ANGLE_SQUARED: f64 = 1e-4;
+
+/// The SO(3) exponential map: the rotation by angle `|omega|` around axis `omega / |omega|`.
+pub fn f64ad_so3_exp(omega: &Vector3<f64ad>) -> UnitQuaternion<f64ad> {
+    let theta_squared = omega.dot(omega);
+    // `(cos(θ/2), sin(θ/2)/θ)`.
+    let (w, k) = if theta_squared.value() < SMALL_ANGLE_SQUARED {
+        (1.0 - theta_squared / 8.0 + theta_squared * theta_squared / 384.0,
+         0.5 - theta_squared / 48.0 + theta_squared * theta_squared / 3840.0)
+    } else {
+        let theta = theta_squared.sqrt();
+        let half_theta = theta * 0.5;
+        (half_theta.cos(), half_theta.sin() / theta)
+    };
+    UnitQuaternion::new_unchecked(Quaternion::new(w, omega[0] * k, omega[1] * k, omega[2] * k))
+}
+
+/// The SO(3) logarithm map: the scaled axis `omega` with `|omega| <= π` such that
+/// `f64ad_so3_exp(omega) == q`.
+pub fn f64ad_so3_log(q: &UnitQuaternion<f64ad>) -> Vector3<f64ad> {
+    // `q` and `-q` are the same rotation; the one with `w >= 0` gives the angle in `[0, π]`.
+    let q = if q.w.value() < 0.0 { -q.into_inner() } else { q.into_inner() };
+    let (w, v) = (q.w, q.imag());
+    let s_squared = v.dot(&v);
+    // `2 atan2(s, w) / s`.
+    let k = if s_squared.value() < SMALL_ANGLE_SQUARED {
+        let r = s_squared / (w * w);
+        (2.0 / w) * (1.0 - r / 3.0 + r * r / 5.0 - r * r * r / 7.0 + r * r * r * r / 9.0)
+    } else {
+        let s = s_squared.sqrt();
+        2.0 * s.atan2(w) / s
+    };
+    v * k
+}
+
+/// The rotation angle of `q`, in `[0, π]`.  The angle has a kink at the identity, where its
+/// derivative is taken to be zero (instead of the NaN given by `acos`).
+pub fn f64ad_rotation_angle(q: &UnitQuaternion<f64ad>) -> f64ad {
+    let v = q.imag();
+    2.0 * norm_with_zero_derivative_at_zero(v.dot(&v)).atan2(q.w.abs())
+}
+
+/// `q / |q|`.  Panics if `q` is zero.
+pub fn f64ad_quaternion_normalize(q: &Quaternion<f64ad>) -> UnitQuaternion<f64ad> {
+    let norm_squared = q.coords.dot(&q.coords);
+    assert_ne!(norm_squared.value(), 0.0, "cannot normalize a zero quaternion.");
+    UnitQuaternion::new_unchecked(q * (1.0 / norm_squared.sqrt()))
+}
+
+/// `a * b`, renormalized so that rounding errors do not accumulate over long chains of
+/// compositions.
+pub fn f64ad_compose_rotations(a: &UnitQuaternion<f64ad>, b: &UnitQuaternion<f64ad>) -> UnitQuaternion<f64ad> {
+    f64ad_quaternion_normalize(&(a.quaternion() * b.quaternion()))
+}
+
+/// The SE(3) exponential map of the twist `(rho, omega)`: the rotation `f64ad_so3_exp(omega)` and
+/// the translation `V(omega) * rho`, with `V = I + (1 - cos θ)/θ² [ω]× + (θ - sin θ)/θ³ [ω]×²`.
+pub fn f64ad_se3_exp(rho: &Vector3<f64ad>, omega: &Vector3<f64ad>) -> Isometry3<f64ad> {
+    let theta_squared = omega.dot(omega);
+    let (b, c) = if theta_squared.value() < SMALL_ANGLE_SQUARED {
+        (0.5 - theta_squared / 24.0 + theta_squared * theta_squared / 720.0,
+         1.0 / 6.0 - theta_squared / 120.0 + theta_squared * theta_squared / 5040.0)
+    } else {
+        let theta = theta_squared.sqrt();
+        ((1.0 - theta.cos()) / theta_squared, (theta - theta.sin()) / (theta_squared * theta))
+    };
+    let omega_cross_rho = omega.cross(rho);
+    let translation = rho + omega_cross_rho * b + omega.cross(&omega_cross_rho) * c;
+    Isometry3::from_parts(Translation3::from(translation), f64ad_so3_exp(omega))
+}
+
+/// The SE(3) logarithm map: the twist `(rho, omega)` such that `f64ad_se3_exp(rho, omega) == isometry`.
+pub fn f64ad_se3_log(isometry: &Isometry3<f64ad>) -> (Vector3<f64ad>, Vector3<f64ad>) {
+    let omega = f64ad_so3_log(&isometry.rotation);
+    let theta_squared = omega.dot(&omega);
+    // `V⁻¹ = I - [ω]×/2 + d [ω]×²`, with `d = (1 - θ sin θ / (2 (1 - cos θ))) / θ²`.
+    let d = if theta_squared.value() < SMALL_ANGLE_SQUARED {
+        1.0 / 12.0 + theta_squared / 720.0 + theta_squared * theta_squared / 30240.0
+    } else {
+        let theta = theta_squared.sqrt();
+        (1.0 - theta * theta.sin() / (2.0 * (1.0 - theta.cos()))) / theta_squared
+    };
+    let t = isometry.translation.vector;
+    let omega_cross_t = omega.cross(&t);
+    let rho = t - omega_cross_t * f64ad::f64(0.5) + omega.cross(&omega_cross_t) * d;
+    (rho, omega)
+}
+
+/// `sqrt(x_squared)`, except at zero, where `x_squared` itself is returned so the result has value
+/// 0 and a zero (rather than infinite) derivative.
+fn norm_with_zero_derivative_at_zero(x_squared: f64ad) -> f64ad {
+    if x_squared.value() == 0.0 { x_squared } else { x_squared.sqrt() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use nalgebra::{Isometry3, Quaternion, Translation3, UnitQuaternion, Vector3};
+    use crate::f64ad::{f64ad, GlobalComputationGraphs};
+    use super::*;
+
+    /// Rotation vectors at the identity, on both sides of `SMALL_ANGLE_SQUARED`, at a generic angle,
+    /// and close to `π`.
+    fn omegas() -> Vec<[f64; 3]> {
+        let axis = Vector3::new(0.3, -0.5, 0.7).normalize();
+        let at_angle = |theta: f64| { let v = axis * theta; [v[0], v[1], v[2]] };
+        vec![[0.0; 3], at_angle(0.99 * SMALL_ANGLE_SQUARED.sqrt()), at_angle(1.01 * SMALL_ANGLE_SQUARED.sqrt()), at_angle(1.1), at_angle(PI - 1e-3)]
+    }
+
+    fn vector(x: &[f64ad]) -> Vector3<f64ad> { Vector3::new(x[0], x[1], x[2]) }
+
+    fn quaternion(x: &[f64ad]) -> Quaternion<f64ad> { Quaternion::new(x[0], x[1], x[2], x[3]) }
+
+    fn coords(q: &UnitQuaternion<f64ad>) -> Vec<f64ad> { vec![q.w, q.i, q.j, q.k] }
+
+    fn unit_quaternion_values(omega: &[f64; 3]) -> Vec<f64> {
+        let q = f64ad_so3_exp(&vector(&omega.map(f64ad::f64)));
+        coords(&q).iter().map(|x| x.value()).collect()
+    }
+
+    /// Checks the Jacobian of `f` at `x`, from backwards mode sweeps, against central differences.
+    fn assert_jacobian_matches_finite_differences(name: &str, f: &dyn Fn(&[f64ad]) -> Vec<f64ad>, x: &[f64]) {
+        let computation_graph = GlobalComputationGraphs::get_first_order(Some("geometry_tests"), None);
+        computation_graph.reset();
+        let inputs: Vec<f64ad> = x.iter().map(|x| computation_graph.spawn_variable(*x)).collect();
+        let outputs = f(&inputs);
+        let values = |x: &[f64]| -> Vec<f64> { f(&x.iter().map(|x| f64ad::f64(*x)).collect::<Vec<f64ad>>()).iter().map(|x| x.value()).collect() };
+
+        // Large enough that the rounding errors of the closed forms just above `SMALL_ANGLE_SQUARED`,
+        // which lose a few digits to cancellation, do not dominate the differences.
+        let h = 1e-5;
+        for (j, input) in inputs.iter().enumerate() {
+            let (mut plus, mut minus) = (x.to_vec(), x.to_vec());
+            plus[j] += h;
+            minus[j] -= h;
+            let (plus, minus) = (values(&plus), values(&minus));
+            for (i, output) in outputs.iter().enumerate() {
+                let expected = (plus[i] - minus[i]) / (2.0 * h);
+                let actual = output.backwards_mode_grad(false).wrt(input).value();
+                assert!((actual - expected).abs() <= 1e-6 * (1.0 + expected.abs()), "d output {} / d input {} of {} at {:?} is {}, but finite differences give {}.", i, j, name, x, actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn so3_exp_and_log() {
+        for omega in omegas() {
+            assert_jacobian_matches_finite_differences("f64ad_so3_exp", &|x| coords(&f64ad_so3_exp(&vector(x))), &omega);
+            let q = unit_quaternion_values(&omega);
+            assert_jacobian_matches_finite_differences("f64ad_so3_log", &|x| f64ad_so3_log(&UnitQuaternion::new_unchecked(quaternion(x))).iter().copied().collect(), &q);
+
+            let log = f64ad_so3_log(&f64ad_so3_exp(&vector(&omega.map(f64ad::f64))));
+            for (a, b) in log.iter().zip(omega.iter()) { assert!((a.value() - b).abs() < 1e-12); }
+            let expected = UnitQuaternion::from_scaled_axis(Vector3::from(omega));
+            for (a, b) in q.iter().zip([expected.w, expected.i, expected.j, expected.k].iter()) { assert!((a - b).abs() < 1e-12); }
+        }
+    }
+
+    #[test]
+    fn se3_exp_and_log() {
+        let rho = [0.4, -1.2, 0.9];
+        for omega in omegas() {
+            let twist: Vec<f64> = rho.iter().chain(omega.iter()).copied().collect();
+            let exp = |x: &[f64ad]| -> Vec<f64ad> {
+                let isometry = f64ad_se3_exp(&vector(&x[..3]), &vector(&x[3..]));
+                isometry.translation.vector.iter().copied().chain(coords(&isometry.rotation)).collect()
+            };
+            assert_jacobian_matches_finite_differences("f64ad_se3_exp", &exp, &twist);
+
+            let transform: Vec<f64> = exp(&twist.iter().map(|x| f64ad::f64(*x)).collect::<Vec<f64ad>>()).iter().map(|x| x.value()).collect();
+            let log = |x: &[f64ad]| -> Vec<f64ad> {
+                let isometry = Isometry3::from_parts(Translation3::from(vector(&x[..3])), UnitQuaternion::new_unchecked(quaternion(&x[3..])));
+                let (rho, omega) = f64ad_se3_log(&isometry);
+                rho.iter().chain(omega.iter()).copied().collect()
+            };
+            assert_jacobian_matches_finite_differences("f64ad_se3_log", &log, &transform);
+
+            let round_trip = log(&transform.iter().map(|x| f64ad::f64(*x)).collect::<Vec<f64ad>>());
+            for (a, b) in round_trip.iter().zip(twist.iter()) { assert!((a.value() - b).abs() < 1e-9, "{:?} != {:?}", round_trip, twist); }
+        }
+    }
+
+    #[test]
+    fn normalize_and_compose() {
+        let q = [0.9, -0.3, 0.5, 0.2];
+        assert_jacobian_matches_finite_differences("f64ad_quaternion_normalize", &|x| coords(&f64ad_quaternion_normalize(&quaternion(x))), &q);
+
+        let a = unit_quaternion_values(&[0.3, -0.5, 0.7]);
+        let b = unit_quaternion_values(&[-1.1, 0.2, 0.4]);
+        let ab: Vec<f64> = a.iter().chain(b.iter()).copied().collect();
+        let compose = |x: &[f64ad]| coords(&f64ad_compose_rotations(&UnitQuaternion::new_unchecked(quaternion(&x[..4])), &UnitQuaternion::new_unchecked(quaternion(&x[4..]))));
+        assert_jacobian_matches_finite_differences("f64ad_compose_rotations", &compose, &ab);
+
+        let composed = compose(&ab.iter().map(|x| f64ad::f64(*x)).collect::<Vec<f64ad>>());
+        let expected = UnitQuaternion::from_scaled_axis(Vector3::new(0.3, -0.5, 0.7)) * UnitQuaternion::from_scaled_axis(Vector3::new(-1.1, 0.2, 0.4));
+        for (x, y) in composed.iter().zip([expected.w, expected.i, expected.j, expected.k].iter()) { assert!((x.value() - y).abs() < 1e-12); }
+    }
+
+    /// `w = cos(θ/2) = 1 - θ²/8 + ...`, so `∂²w/∂ω₀² = -1/4` at the identity.
+    #[test]
+    fn second_derivative_at_identity() {
+        let computation_graph = GlobalComputationGraphs::get(Some("geometry_tests"), None);
+        computation_graph.reset();
+        let omega = Vector3::new(computation_graph.spawn_variable(0.0), computation_graph.spawn_variable(0.0), computation_graph.spawn_variable(0.0));
+        let w = f64ad_so3_exp(&omega).w;
+        let d = w.backwards_mode_grad(true).wrt(&omega[0]);
+        let d2 = d.backwards_mode_grad(true).wrt(&omega[0]);
+        assert_eq!(d.value(), 0.0);
+        assert!((d2.value() + 0.25).abs() < 1e-15);
+    }
+}
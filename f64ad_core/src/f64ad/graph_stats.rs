@@ -0,0 +1,129 @@
+// Profiling statistics of computation graphs: what is on the tape, how much memory it uses, and how
+// long the last derivative sweeps on it took.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use crate::f64ad::{ComputationGraph, NodeTypeClass};
+
+/// Durations of the last backwards and forward mode sweeps on a graph.
+#[derive(Default)]
+pub(crate) struct SweepTimings {
+    pub(crate) backwards: Cell<Option<Duration>>,
+    pub(crate) forward: Cell<Option<Duration>>
+}
+
+#[derive(Clone, Debug)]
+pub struct GraphStats {
+    num_nodes: usize,
+    num_inputs: usize,
+    size_in_bytes: usize,
+    max_depth: usize,
+    node_type_class_counts: BTreeMap<String, usize>,
+    node_operands_mode_counts: BTreeMap<String, usize>,
+    last_backwards_sweep: Option<Duration>,
+    last_forward_sweep: Option<Duration>
+}
+impl GraphStats {
+    pub(crate) fn new(computation_graph: &ComputationGraph) -> Self {
+        let (last_backwards_sweep, last_forward_sweep) = computation_graph.with_sweep_timings(|x| (x.backwards.get(), x.forward.get()));
+
+        computation_graph.with_tape(|tape| {
+            let mut num_inputs = 0;
+            let mut node_type_class_counts = BTreeMap::new();
+            let mut node_operands_mode_counts = BTreeMap::new();
+            // Depth of a node is the length of the longest path to it from a node without variable parents.
+            let mut depths = vec![0usize; tape.len()];
+            for node_idx in 0..tape.len() {
+                let node_type_class = tape.node_type_class(node_idx);
+                if node_type_class == NodeTypeClass::InputVariable { num_inputs += 1; }
+                *node_type_class_counts.entry(node_type_class_name(&node_type_class)).or_insert(0) += 1;
+                *node_operands_mode_counts.entry(format!("{:?}", tape.node_operands_mode(node_idx))).or_insert(0) += 1;
+
+                let parent_depths = match node_type_class {
+                    NodeTypeClass::Composite { composite_idx, .. } => {
+                        computation_graph.get_composite(composite_idx).input_node_idxs.iter().flatten().map(|x| depths[*x]).max()
+                    }
+                    _ => { tape.variable_parents(node_idx).iter().flatten().map(|x| depths[*x]).max() }
+                };
+                depths[node_idx] = parent_depths.map(|x| x + 1).unwrap_or(0);
+            }
+
+            Self {
+                num_nodes: tape.len(),
+                num_inputs,
+                size_in_bytes: computation_graph.size_in_bytes(),
+                max_depth: depths.iter().copied().max().unwrap_or(0),
+                node_type_class_counts,
+                node_operands_mode_counts,
+                last_backwards_sweep,
+                last_forward_sweep
+            }
+        })
+    }
+    #[inline(always)]
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+    #[inline(always)]
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+    #[inline(always)]
+    pub fn size_in_bytes(&self) -> usize {
+        self.size_in_bytes
+    }
+    #[inline(always)]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+    /// Number of nodes per node type class, e.g., `"Mul"`.  All `Manual` nodes are counted together,
+    /// as are all `Composite` nodes.
+    #[inline(always)]
+    pub fn node_type_class_counts(&self) -> &BTreeMap<String, usize> {
+        &self.node_type_class_counts
+    }
+    /// Number of nodes per node operands mode, e.g., `"OneParentLHS"`.
+    #[inline(always)]
+    pub fn node_operands_mode_counts(&self) -> &BTreeMap<String, usize> {
+        &self.node_operands_mode_counts
+    }
+    #[inline(always)]
+    pub fn last_backwards_sweep(&self) -> Option<Duration> {
+        self.last_backwards_sweep
+    }
+    #[inline(always)]
+    pub fn last_forward_sweep(&self) -> Option<Duration> {
+        self.last_forward_sweep
+    }
+}
+impl Display for GraphStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "nodes: {}", self.num_nodes)?;
+        writeln!(f, "inputs: {}", self.num_inputs)?;
+        writeln!(f, "size in bytes: {}", self.size_in_bytes)?;
+        writeln!(f, "max depth: {}", self.max_depth)?;
+        writeln!(f, "last backwards sweep: {:?}", self.last_backwards_sweep)?;
+        writeln!(f, "last forward sweep: {:?}", self.last_forward_sweep)?;
+        writeln!(f, "node type classes:")?;
+        let mut node_type_class_counts: Vec<(&String, &usize)> = self.node_type_class_counts.iter().collect();
+        node_type_class_counts.sort_by(|a, b| b.1.cmp(a.1));
+        for (name, count) in node_type_class_counts {
+            writeln!(f, "    {}: {}", name, count)?;
+        }
+        writeln!(f, "node operands modes:")?;
+        for (name, count) in &self.node_operands_mode_counts {
+            writeln!(f, "    {}: {}", name, count)?;
+        }
+        Ok(())
+    }
+}
+
+fn node_type_class_name(node_type_class: &NodeTypeClass) -> String {
+    match node_type_class {
+        NodeTypeClass::Manual { .. } => { "Manual".to_string() }
+        NodeTypeClass::Composite { .. } => { "Composite".to_string() }
+        _ => { format!("{:?}", node_type_class) }
+    }
+}
@@ -0,0 +1,622 @@
+// Interval arithmetic.  An `Interval` is an enclosure `[lo, hi]` of a real number, and every
+// elementary operation returns an interval that contains all results of the operation on points of
+// its inputs.  Arithmetic operations and square roots are correctly rounded, so their exact rounding
+// errors (from `TwoSum` and fused multiply-adds) are used to round their bounds outwards only when
+// they are inexact.  Transcendental functions are rounded outwards by two ulps, which covers the
+// error of the platform's libm (below an ulp in practice).
+//
+// Intervals can be evaluated on the tape of a locked graph with
+// `GlobalComputationGraph::evaluate_interval`, which also computes enclosures of the gradients of
+// the outputs over the whole input box.  Gradient enclosures use the same derivative rules as all
+// other graphs (see `derivative_rules`), except that `Abs`, `Max`, and `Min` enclose both one-sided
+// derivatives at kinks (unless the kink policy is smooth).  Rounding nodes (`Floor`, `Ceil`, etc.)
+// use the derivative given by the rounding policy, i.e., their jumps are not accounted for.
+//
+// Domain errors are handled by intersecting inputs with the domain of the function, e.g.,
+// `sqrt([-1, 4]) = [0, 2]`.  An interval with NaN bounds is empty, i.e., the operation is undefined
+// everywhere on its inputs, and stays empty through all further operations.
+
+use std::cmp::Ordering;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use serde::{Serialize, Deserialize};
+use crate::f64ad::{ComputationGraph, DerivativePolicy, KinkPolicy, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::derivative_rules::{derivatives, DerivativeScalar};
+use crate::f64ad::tape::Operand;
+
+/// Number of ulps that the bounds of transcendental functions are rounded outwards by.
+const TRANSCENDENTAL_ULPS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64
+}
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Self {
+        assert!(lo <= hi, "invalid interval bounds [{}, {}], the lower bound must not be above the upper bound (use `Interval::empty` for empty intervals).", lo, hi);
+        Self { lo, hi }
+    }
+    /// The degenerate interval `[v, v]`.
+    #[inline(always)]
+    pub fn point(v: f64) -> Self {
+        Self { lo: v, hi: v }
+    }
+    /// The interval `[v - radius, v + radius]`, rounded outwards.
+    pub fn centered(v: f64, radius: f64) -> Self {
+        assert!(radius >= 0.0, "interval radius must be non-negative, got {}", radius);
+        Interval { lo: add_rounded(v, -radius).0, hi: add_rounded(v, radius).1 }
+    }
+    /// `[-inf, inf]`.
+    #[inline(always)]
+    pub fn entire() -> Self {
+        Self { lo: f64::NEG_INFINITY, hi: f64::INFINITY }
+    }
+    #[inline(always)]
+    pub fn empty() -> Self {
+        Self { lo: f64::NAN, hi: f64::NAN }
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.lo.is_nan() || self.hi.is_nan()
+    }
+    #[inline(always)]
+    pub fn contains(&self, v: f64) -> bool {
+        self.lo <= v && v <= self.hi
+    }
+    #[inline(always)]
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+    #[inline(always)]
+    pub fn midpoint(&self) -> f64 {
+        0.5 * self.lo + 0.5 * self.hi
+    }
+    /// Largest absolute value in the interval.
+    #[inline(always)]
+    pub fn magnitude(&self) -> f64 {
+        self.lo.abs().max(self.hi.abs())
+    }
+    /// Smallest interval that contains both intervals.
+    pub fn hull(&self, other: &Interval) -> Interval {
+        if self.is_empty() { return *other; }
+        if other.is_empty() { return *self; }
+        Interval { lo: self.lo.min(other.lo), hi: self.hi.max(other.hi) }
+    }
+    /// Intersection of both intervals, empty if they do not overlap.
+    pub fn intersection(&self, other: &Interval) -> Interval {
+        let lo = self.lo.max(other.lo);
+        let hi = self.hi.min(other.hi);
+        if self.is_empty() || other.is_empty() || lo > hi { return Interval::empty(); }
+        Interval { lo, hi }
+    }
+}
+impl Interval {
+    pub fn abs(self) -> Self {
+        if self.lo >= 0.0 { self } else if self.hi <= 0.0 { -self } else { Interval { lo: 0.0, hi: (-self.lo).max(self.hi) } }
+    }
+    pub fn signum(self) -> Self { monotone(self, f64::signum, 0) }
+    pub fn max(self, other: Self) -> Self {
+        if self.is_empty() || other.is_empty() { return Interval::empty(); }
+        Interval { lo: self.lo.max(other.lo), hi: self.hi.max(other.hi) }
+    }
+    pub fn min(self, other: Self) -> Self {
+        if self.is_empty() || other.is_empty() { return Interval::empty(); }
+        Interval { lo: self.lo.min(other.lo), hi: self.hi.min(other.hi) }
+    }
+    /// `atan2(self, other)`, i.e., the angle of the points `(other, self)`.
+    pub fn atan2(self, other: Self) -> Self {
+        if self.is_empty() || other.is_empty() { return Interval::empty(); }
+        // boxes that touch the branch cut along the negative x axis (or contain the origin) cover
+        // angles on both sides of it.
+        if other.lo <= 0.0 && self.lo <= 0.0 && self.hi >= 0.0 { return outward(-PI, PI, 1); }
+        // otherwise, the box does not contain the origin and its extreme angles are at its corners.
+        let corners = [self.lo.atan2(other.lo), self.lo.atan2(other.hi), self.hi.atan2(other.lo), self.hi.atan2(other.hi)];
+        hull_of(&corners, TRANSCENDENTAL_ULPS)
+    }
+    pub fn floor(self) -> Self { monotone(self, f64::floor, 0) }
+    pub fn ceil(self) -> Self { monotone(self, f64::ceil, 0) }
+    pub fn round(self) -> Self { monotone(self, f64::round, 0) }
+    pub fn trunc(self) -> Self { monotone(self, f64::trunc, 0) }
+    pub fn fract(self) -> Self {
+        if self.is_empty() { return self; }
+        // `fract` is `x - trunc(x)`, which is increasing between consecutive integers (and on (-1, 1)).
+        if self.lo.trunc() == self.hi.trunc() { return Interval { lo: self.lo.fract(), hi: self.hi.fract() }; }
+        Interval { lo: if self.lo < 0.0 { -1.0 } else { 0.0 }, hi: if self.hi > 0.0 { 1.0 } else { 0.0 } }
+    }
+    pub fn sin(self) -> Self {
+        if self.is_empty() { return self; }
+        periodic_extrema(self, f64::sin, FRAC_PI_2, -FRAC_PI_2)
+    }
+    pub fn cos(self) -> Self {
+        if self.is_empty() { return self; }
+        periodic_extrema(self, f64::cos, 0.0, PI)
+    }
+    pub fn tan(self) -> Self {
+        if self.is_empty() { return self; }
+        if self.width() >= PI || contains_periodic_point(self, FRAC_PI_2, PI) { return Interval::entire(); }
+        monotone(self, f64::tan, TRANSCENDENTAL_ULPS)
+    }
+    pub fn asin(self) -> Self { monotone(self.intersection(&Interval { lo: -1.0, hi: 1.0 }), f64::asin, TRANSCENDENTAL_ULPS) }
+    pub fn acos(self) -> Self { monotone(self.intersection(&Interval { lo: -1.0, hi: 1.0 }), f64::acos, TRANSCENDENTAL_ULPS) }
+    pub fn atan(self) -> Self { monotone(self, f64::atan, TRANSCENDENTAL_ULPS) }
+    pub fn sinh(self) -> Self { monotone(self, f64::sinh, TRANSCENDENTAL_ULPS) }
+    pub fn cosh(self) -> Self {
+        if self.is_empty() { return self; }
+        let out = if self.lo >= 0.0 || self.hi <= 0.0 { monotone(self, f64::cosh, TRANSCENDENTAL_ULPS) } else { outward(1.0, self.lo.cosh().max(self.hi.cosh()), TRANSCENDENTAL_ULPS) };
+        clamp(out, 1.0, f64::INFINITY)
+    }
+    pub fn tanh(self) -> Self {
+        let out = monotone(self, f64::tanh, TRANSCENDENTAL_ULPS);
+        clamp(out, -1.0, 1.0)
+    }
+    pub fn asinh(self) -> Self { monotone(self, f64::asinh, TRANSCENDENTAL_ULPS) }
+    pub fn acosh(self) -> Self { monotone(self.intersection(&Interval { lo: 1.0, hi: f64::INFINITY }), f64::acosh, TRANSCENDENTAL_ULPS) }
+    pub fn atanh(self) -> Self { monotone(self.intersection(&Interval { lo: -1.0, hi: 1.0 }), f64::atanh, TRANSCENDENTAL_ULPS) }
+    pub fn log(self, base: Self) -> Self { self.ln() / base.ln() }
+    pub fn ln(self) -> Self { monotone(self.intersection(&Interval { lo: 0.0, hi: f64::INFINITY }), f64::ln, TRANSCENDENTAL_ULPS) }
+    pub fn sqrt(self) -> Self {
+        let x = self.intersection(&Interval { lo: 0.0, hi: f64::INFINITY });
+        if x.is_empty() { return x; }
+        Interval { lo: sqrt_rounded(x.lo).0.max(0.0), hi: sqrt_rounded(x.hi).1 }
+    }
+    pub fn exp(self) -> Self {
+        let out = monotone(self, f64::exp, TRANSCENDENTAL_ULPS);
+        clamp(out, 0.0, f64::INFINITY)
+    }
+    /// Negative bases are only supported with a constant integer exponent, as in `f64::powf`.
+    pub fn powf(self, n: Self) -> Self {
+        if self.is_empty() || n.is_empty() { return Interval::empty(); }
+        if n.lo == n.hi && n.lo.fract() == 0.0 { return integer_power(self, n.lo); }
+
+        let x = self.intersection(&Interval { lo: 0.0, hi: f64::INFINITY });
+        if x.is_empty() { return x; }
+        // `x^n = exp(n * ln(x))` and `n * ln(x)` is bilinear in `n` and `ln(x)`, so the extremes are
+        // at the corners.
+        let corners = [x.lo.powf(n.lo), x.lo.powf(n.hi), x.hi.powf(n.lo), x.hi.powf(n.hi)];
+        let out = hull_of(&corners, TRANSCENDENTAL_ULPS);
+        clamp(out, 0.0, f64::INFINITY)
+    }
+}
+
+/// `f` applied to both bounds, for a non-decreasing or non-increasing `f`.
+#[inline(always)]
+fn monotone<F: Fn(f64) -> f64>(x: Interval, f: F, ulps: usize) -> Interval {
+    if x.is_empty() { return x; }
+    let (a, b) = (f(x.lo), f(x.hi));
+    outward(a.min(b), a.max(b), ulps)
+}
+
+/// `x` with its bounds clamped to the range `[lo, hi]` of the function that produced it.
+#[inline(always)]
+fn clamp(x: Interval, lo: f64, hi: f64) -> Interval {
+    if x.is_empty() { return x; }
+    Interval { lo: x.lo.max(lo), hi: x.hi.min(hi) }
+}
+
+#[inline(always)]
+fn outward(lo: f64, hi: f64, ulps: usize) -> Interval {
+    let mut out = Interval { lo, hi };
+    for _ in 0..ulps {
+        out.lo = out.lo.next_down();
+        out.hi = out.hi.next_up();
+    }
+    out
+}
+
+fn hull_of(values: &[f64], ulps: usize) -> Interval {
+    if values.iter().any(|x| x.is_nan()) { return Interval::empty(); }
+    let lo = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let hi = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    outward(lo, hi, ulps)
+}
+
+/// Returns true if `x` contains (up to rounding errors in the reduction) a point `offset + k*period`.
+fn contains_periodic_point(x: Interval, offset: f64, period: f64) -> bool {
+    if !x.lo.is_finite() || !x.hi.is_finite() { return true; }
+    let slack = 4.0 * f64::EPSILON * x.magnitude().max(1.0);
+    let k = ((x.lo - slack - offset) / period).ceil();
+    k * period + offset <= x.hi + slack
+}
+
+/// Enclosure of a 2*pi periodic function with values in [-1, 1] that attains its maximum at
+/// `max_at` and its minimum at `min_at` (modulo 2*pi) and is monotone in between.
+fn periodic_extrema<F: Fn(f64) -> f64>(x: Interval, f: F, max_at: f64, min_at: f64) -> Interval {
+    if !x.width().is_finite() || x.width() >= 2.0 * PI { return Interval { lo: -1.0, hi: 1.0 }; }
+    let mut out = monotone(x, f, TRANSCENDENTAL_ULPS);
+    if contains_periodic_point(x, max_at, 2.0 * PI) { out.hi = 1.0; }
+    if contains_periodic_point(x, min_at, 2.0 * PI) { out.lo = -1.0; }
+    clamp(out, -1.0, 1.0)
+}
+
+fn integer_power(x: Interval, n: f64) -> Interval {
+    if n == 0.0 { return Interval::point(1.0); }
+    let (a, b) = (x.lo.powf(n), x.hi.powf(n));
+    let contains_zero = x.lo <= 0.0 && x.hi >= 0.0;
+    let even = (n / 2.0).fract() == 0.0;
+    match (even, n > 0.0, contains_zero) {
+        (true, true, true) => { Interval { lo: 0.0, hi: outward(0.0, a.max(b), TRANSCENDENTAL_ULPS).hi } }
+        (true, false, true) => { outward(a.min(b), f64::INFINITY, TRANSCENDENTAL_ULPS) }
+        (false, false, true) => { Interval::entire() }
+        _ => { outward(a.min(b), a.max(b), TRANSCENDENTAL_ULPS) }
+    }
+}
+
+/// Correctly rounded result `r` of an operation rounded down and up, given the exact rounding error
+/// `exact - r` (or any number with its sign).
+#[inline(always)]
+fn directed(r: f64, error: f64) -> (f64, f64) {
+    (if error < 0.0 { r.next_down() } else { r }, if error > 0.0 { r.next_up() } else { r })
+}
+
+/// Rounding error of a result that overflowed to infinity, which is only exact if an operand was
+/// infinite.
+#[inline(always)]
+fn overflow_error(r: f64, a: f64, b: f64) -> f64 {
+    if a.is_finite() && b.is_finite() { -r.signum() } else { 0.0 }
+}
+
+/// `a + b` rounded down and up.
+#[inline(always)]
+fn add_rounded(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    if s.is_nan() { return (s, s); }
+    if s.is_infinite() { return directed(s, overflow_error(s, a, b)); }
+    // TwoSum
+    let bb = s - a;
+    directed(s, (a - (s - bb)) + (b - bb))
+}
+
+/// `a * b` rounded down and up, with `0*inf` taken as 0 (the limit for finite operands).
+#[inline(always)]
+fn mul_rounded(a: f64, b: f64) -> (f64, f64) {
+    if a == 0.0 || b == 0.0 { return (0.0, 0.0); }
+    let p = a * b;
+    if p.is_nan() { return (p, p); }
+    if p.is_infinite() { return directed(p, overflow_error(p, a, b)); }
+    // the fused multiply-add is only guaranteed to give the exact error without underflow.
+    if p.abs() < f64::MIN_POSITIVE { return (p.next_down(), p.next_up()); }
+    directed(p, a.mul_add(b, -p))
+}
+
+/// `a / b` rounded down and up, for `b != 0`.
+#[inline(always)]
+fn div_rounded(a: f64, b: f64) -> (f64, f64) {
+    let q = a / b;
+    if q.is_nan() { return (q, q); }
+    if q.is_infinite() { return directed(q, overflow_error(q, a, b)); }
+    if !a.is_finite() || !b.is_finite() { return (q, q); }
+    if q.abs() < f64::MIN_POSITIVE { return if a == 0.0 { (q, q) } else { (q.next_down(), q.next_up()) }; }
+    // the remainder `a - q*b` is exact, and `a/b - q` has the sign of `remainder / b`.
+    let remainder = (-q).mul_add(b, a);
+    directed(q, remainder * b.signum())
+}
+
+/// `sqrt(x)` rounded down and up, for `x >= 0`.
+#[inline(always)]
+fn sqrt_rounded(x: f64) -> (f64, f64) {
+    let s = x.sqrt();
+    if s == 0.0 || !s.is_finite() { return (s, s); }
+    directed(s, -s.mul_add(s, -x))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: Interval) -> Self::Output {
+        if self.is_empty() || rhs.is_empty() { return Interval::empty(); }
+        Interval { lo: add_rounded(self.lo, rhs.lo).0, hi: add_rounded(self.hi, rhs.hi).1 }
+    }
+}
+impl Add<f64> for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: f64) -> Self::Output {
+        self + Interval::point(rhs)
+    }
+}
+impl AddAssign for Interval {
+    fn add_assign(&mut self, rhs: Interval) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: Interval) -> Self::Output {
+        if self.is_empty() || rhs.is_empty() { return Interval::empty(); }
+        Interval { lo: add_rounded(self.lo, -rhs.hi).0, hi: add_rounded(self.hi, -rhs.lo).1 }
+    }
+}
+impl Sub<f64> for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: f64) -> Self::Output {
+        self - Interval::point(rhs)
+    }
+}
+impl SubAssign for Interval {
+    fn sub_assign(&mut self, rhs: Interval) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, rhs: Interval) -> Self::Output {
+        if self.is_empty() || rhs.is_empty() { return Interval::empty(); }
+        let products = [mul_rounded(self.lo, rhs.lo), mul_rounded(self.lo, rhs.hi), mul_rounded(self.hi, rhs.lo), mul_rounded(self.hi, rhs.hi)];
+        Interval { lo: products.iter().fold(f64::INFINITY, |acc, x| acc.min(x.0)), hi: products.iter().fold(f64::NEG_INFINITY, |acc, x| acc.max(x.1)) }
+    }
+}
+impl Mul<f64> for Interval {
+    type Output = Interval;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        self * Interval::point(rhs)
+    }
+}
+impl MulAssign for Interval {
+    fn mul_assign(&mut self, rhs: Interval) {
+        *self = *self * rhs;
+    }
+}
+
+impl Div for Interval {
+    type Output = Interval;
+
+    fn div(self, rhs: Interval) -> Self::Output {
+        if self.is_empty() || rhs.is_empty() { return Interval::empty(); }
+        if rhs.lo == 0.0 && rhs.hi == 0.0 { return Interval::empty(); }
+        if rhs.contains(0.0) { return Interval::entire(); }
+        let quotients = [div_rounded(self.lo, rhs.lo), div_rounded(self.lo, rhs.hi), div_rounded(self.hi, rhs.lo), div_rounded(self.hi, rhs.hi)];
+        // `inf/inf` only occurs with unbounded operands, whose quotients are unbounded as well.
+        if quotients.iter().any(|x| x.0.is_nan()) { return Interval::entire(); }
+        Interval { lo: quotients.iter().fold(f64::INFINITY, |acc, x| acc.min(x.0)), hi: quotients.iter().fold(f64::NEG_INFINITY, |acc, x| acc.max(x.1)) }
+    }
+}
+impl Div<f64> for Interval {
+    type Output = Interval;
+
+    fn div(self, rhs: f64) -> Self::Output {
+        self / Interval::point(rhs)
+    }
+}
+impl DivAssign for Interval {
+    fn div_assign(&mut self, rhs: Interval) {
+        *self = *self / rhs;
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Self::Output {
+        Interval { lo: -self.hi, hi: -self.lo }
+    }
+}
+
+/// Intervals are only ordered if one lies entirely below the other.
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other { Some(Ordering::Equal) }
+        else if self.hi < other.lo { Some(Ordering::Less) }
+        else if self.lo > other.hi { Some(Ordering::Greater) }
+        else { None }
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("[{}, {}]", self.lo, self.hi))
+    }
+}
+
+impl From<f64> for Interval {
+    fn from(a: f64) -> Self {
+        Interval::point(a)
+    }
+}
+
+impl DerivativeScalar for Interval {
+    #[inline(always)]
+    fn constant(v: f64) -> Self { Interval::point(v) }
+    #[inline(always)]
+    fn to_f64_value(self) -> f64 { self.midpoint() }
+    #[inline(always)]
+    fn sin(self) -> Self { Interval::sin(self) }
+    #[inline(always)]
+    fn cos(self) -> Self { Interval::cos(self) }
+    #[inline(always)]
+    fn sinh(self) -> Self { Interval::sinh(self) }
+    #[inline(always)]
+    fn cosh(self) -> Self { Interval::cosh(self) }
+    #[inline(always)]
+    fn tanh(self) -> Self { Interval::tanh(self) }
+    #[inline(always)]
+    fn sqrt(self) -> Self { Interval::sqrt(self) }
+    #[inline(always)]
+    fn exp(self) -> Self { Interval::exp(self) }
+    #[inline(always)]
+    fn ln(self) -> Self { Interval::ln(self) }
+    #[inline(always)]
+    fn powf(self, n: Self) -> Self { Interval::powf(self, n) }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Enclosure of the value of a node of class `node_type_class` given enclosures of its parents.
+pub fn interval_value(node_type_class: NodeTypeClass, lhs: Interval, rhs: Option<Interval>) -> Interval {
+    match node_type_class {
+        NodeTypeClass::InputVariable => { panic!("input variable cannot compute value.") }
+        NodeTypeClass::Add => { lhs + rhs.unwrap() }
+        NodeTypeClass::Mul => { lhs * rhs.unwrap() }
+        NodeTypeClass::Sub => { lhs - rhs.unwrap() }
+        NodeTypeClass::Div => { lhs / rhs.unwrap() }
+        NodeTypeClass::Neg => { -lhs }
+        NodeTypeClass::Abs => { lhs.abs() }
+        NodeTypeClass::Signum => { lhs.signum() }
+        NodeTypeClass::Max => { lhs.max(rhs.unwrap()) }
+        NodeTypeClass::Min => { lhs.min(rhs.unwrap()) }
+        NodeTypeClass::Atan2 => { lhs.atan2(rhs.unwrap()) }
+        NodeTypeClass::Floor => { lhs.floor() }
+        NodeTypeClass::Ceil => { lhs.ceil() }
+        NodeTypeClass::Round => { lhs.round() }
+        NodeTypeClass::Trunc => { lhs.trunc() }
+        NodeTypeClass::Fract => { lhs.fract() }
+        NodeTypeClass::Sin => { lhs.sin() }
+        NodeTypeClass::Cos => { lhs.cos() }
+        NodeTypeClass::Tan => { lhs.tan() }
+        NodeTypeClass::Asin => { lhs.asin() }
+        NodeTypeClass::Acos => { lhs.acos() }
+        NodeTypeClass::Atan => { lhs.atan() }
+        NodeTypeClass::Sinh => { lhs.sinh() }
+        NodeTypeClass::Cosh => { lhs.cosh() }
+        NodeTypeClass::Tanh => { lhs.tanh() }
+        NodeTypeClass::Asinh => { lhs.asinh() }
+        NodeTypeClass::Acosh => { lhs.acosh() }
+        NodeTypeClass::Atanh => { lhs.atanh() }
+        NodeTypeClass::Log => { lhs.log(rhs.unwrap()) }
+        NodeTypeClass::Ln => { lhs.ln() }
+        NodeTypeClass::Sqrt => { lhs.sqrt() }
+        NodeTypeClass::Exp => { lhs.exp() }
+        NodeTypeClass::Powf => { lhs.powf(rhs.unwrap()) }
+        NodeTypeClass::Manual { .. } | NodeTypeClass::Composite { .. } => { panic!("cannot compute interval value of {:?} nodes.", node_type_class) }
+    }
+}
+
+/// Slot aligned enclosures of the local partial derivatives of a (non input, non composite) node.
+pub fn interval_local_partials(node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, lhs: Interval, rhs: Option<Interval>, derivative_policy: &DerivativePolicy) -> [Interval; 2] {
+    let zero = Interval::point(0.0);
+    let one = Interval::point(1.0);
+    let unit = Interval { lo: 0.0, hi: 1.0 };
+    let kinks_are_smooth = matches!(derivative_policy.kinks, KinkPolicy::Smooth { .. });
+    match (node_type_class, node_operands_mode) {
+        (_, NodeOperandsMode::NoParents) => { [zero; 2] }
+        (NodeTypeClass::Abs, _) if !kinks_are_smooth => {
+            let d = if lhs.lo > 0.0 { one } else if lhs.hi < 0.0 { -one } else { Interval { lo: -1.0, hi: 1.0 } };
+            [d, zero]
+        }
+        (NodeTypeClass::Max, _) | (NodeTypeClass::Min, _) if !kinks_are_smooth => {
+            let rhs = rhs.unwrap();
+            let (lhs_wins, rhs_wins) = if node_type_class == NodeTypeClass::Max { (lhs.lo > rhs.hi, lhs.hi < rhs.lo) } else { (lhs.hi < rhs.lo, lhs.lo > rhs.hi) };
+            let (d_lhs, d_rhs) = if lhs_wins { (one, zero) } else if rhs_wins { (zero, one) } else { (unit, unit) };
+            match node_operands_mode {
+                NodeOperandsMode::TwoParents => { [d_lhs, d_rhs] }
+                NodeOperandsMode::OneParentLHS => { [d_lhs, zero] }
+                _ => { [zero, d_rhs] }
+            }
+        }
+        _ => {
+            let derivatives = derivatives(lhs, rhs, node_type_class, node_operands_mode, derivative_policy);
+            match node_operands_mode {
+                NodeOperandsMode::TwoParents => { [derivatives[0], derivatives[1]] }
+                NodeOperandsMode::OneParentLHS => { [derivatives[0], zero] }
+                NodeOperandsMode::OneParentRHS => { [zero, derivatives[0]] }
+                NodeOperandsMode::NoParents => { unreachable!() }
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Enclosures of the values (and, if requested, gradients) of the outputs of a locked graph over a
+/// box of inputs.
+#[derive(Clone, Debug)]
+pub struct IntervalOutput {
+    values: Vec<Interval>,
+    /// `gradients[output][input]`
+    gradients: Option<Vec<Vec<Interval>>>
+}
+impl IntervalOutput {
+    #[inline(always)]
+    pub fn value(&self, output: usize) -> Interval {
+        self.values[output]
+    }
+    #[inline(always)]
+    pub fn values(&self) -> &Vec<Interval> {
+        &self.values
+    }
+    /// Enclosure of the gradient of `output` with respect to the inputs over the whole box.
+    #[inline(always)]
+    pub fn gradient(&self, output: usize) -> &[Interval] {
+        let gradients = self.gradients.as_ref().expect("gradients were not computed in this interval evaluation.");
+        &gradients[output]
+    }
+    /// Upper bound on the Lipschitz constant of `output` over the input box, with respect to the
+    /// euclidean norm on the inputs.  Only valid if `output` is continuous on the box, i.e., the
+    /// computation does not contain rounding nodes with a jump in the box.
+    pub fn lipschitz_bound(&self, output: usize) -> f64 {
+        let squared_norm = self.gradient(output).iter().fold(Interval::point(0.0), |acc, x| { let m = Interval::point(x.magnitude()); acc + m * m });
+        if squared_norm.is_empty() { return f64::INFINITY; }
+        squared_norm.hi.sqrt().next_up()
+    }
+}
+
+/// `inputs` holds one enclosure per input, in the order the inputs were spawned.
+pub(crate) fn evaluate_interval(computation_graph: &ComputationGraph, outputs: Vec<usize>, inputs: &[Interval], gradients: bool) -> IntervalOutput {
+    let derivative_policy = computation_graph.derivative_policy();
+    let nodes: Vec<(NodeTypeClass, NodeOperandsMode, [Option<Operand>; 2])> = computation_graph.with_tape(|tape| {
+        (0..tape.len()).map(|node_idx| (tape.node_type_class(node_idx), tape.node_operands_mode(node_idx), tape.operands(node_idx))).collect()
+    });
+    let num_inputs = nodes.iter().filter(|x| x.0 == NodeTypeClass::InputVariable).count();
+    assert_eq!(inputs.len(), num_inputs, "number of input intervals ({}) does not match the number of inputs ({}).", inputs.len(), num_inputs);
+
+    let operand_value = |values: &[Interval], operand: Option<Operand>| -> Option<Interval> {
+        operand.map(|x| {
+            match x {
+                Operand::Variable(parent) => { values[parent] }
+                Operand::Constant(constant) => { Interval::point(constant) }
+            }
+        })
+    };
+
+    let mut values = Vec::with_capacity(nodes.len());
+    let mut input_idxs = vec![];
+    for (node_idx, (node_type_class, _, operands)) in nodes.iter().enumerate() {
+        if *node_type_class == NodeTypeClass::InputVariable {
+            values.push(inputs[input_idxs.len()]);
+            input_idxs.push(node_idx);
+            continue;
+        }
+        let lhs = operand_value(&values, operands[0]).unwrap();
+        // `x*x` is evaluated as a square, which (unlike a product of two independent intervals) is
+        // never negative.
+        let value = if *node_type_class == NodeTypeClass::Mul && operands[0] == operands[1] { lhs.powf(Interval::point(2.0)) } else { interval_value(*node_type_class, lhs, operand_value(&values, operands[1])) };
+        values.push(value);
+    }
+
+    let gradients = if gradients {
+        let mut out = vec![];
+        for output in &outputs {
+            let mut adjoints = vec![Interval::point(0.0); nodes.len()];
+            adjoints[*output] = Interval::point(1.0);
+            for node_idx in (0..nodes.len()).rev() {
+                let (node_type_class, node_operands_mode, operands) = nodes[node_idx];
+                if node_type_class == NodeTypeClass::InputVariable || node_operands_mode == NodeOperandsMode::NoParents { continue; }
+                let adjoint = adjoints[node_idx];
+                if adjoint == Interval::point(0.0) { continue; }
+
+                let partials = interval_local_partials(node_type_class, node_operands_mode, operand_value(&values, operands[0]).unwrap(), operand_value(&values, operands[1]), &derivative_policy);
+                for (operand, partial) in operands.iter().zip(partials) {
+                    if let Some(Operand::Variable(parent)) = operand { adjoints[*parent] += adjoint * partial; }
+                }
+            }
+            out.push(input_idxs.iter().map(|x| adjoints[*x]).collect());
+        }
+        Some(out)
+    } else {
+        None
+    };
+
+    IntervalOutput {
+        values: outputs.iter().map(|x| values[*x]).collect(),
+        gradients
+    }
+}
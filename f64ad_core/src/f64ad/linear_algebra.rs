@@ -0,0 +1,598 @@
+// Matrix-level linear algebra.  Running nalgebra's decompositions directly on `f64ad` matrices
+// records every scalar multiply-add of the factorization, which makes for enormous tapes (and, for
+// iterative algorithms like eigendecompositions, derivatives of the iteration rather than of the
+// result).  The functions here instead run on plain `f64` matrices and record a single composite
+// operation whose derivatives are the known analytic adjoints of the whole matrix operation.
+//
+// Matrices are passed to composite operations in column-major order, the storage order of nalgebra.
+// As with checkpoint regions, these operations only support first order derivatives, so they are
+// only recorded on `ComputationGraph1` graphs.  On `ComputationGraphF` graphs, everything but the
+// (iterative) eigendecomposition and SVD falls back to nalgebra's scalar algorithms on `f64ad`, so
+// higher order derivatives work.  Differentiating through the iterations of the eigendecomposition
+// and SVD would not give the derivatives of their results, so they panic on every graph other than
+// `ComputationGraph1`.  `ComputationGraphT` and `ComputationGraphL` graphs support neither composite
+// operations nor the pivoting of the scalar algorithms, so only `f64ad_matmul` falls back there and
+// everything else panics.  If all inputs are constants, the result is just computed on `f64` values.
+//
+// Symmetric inputs (Cholesky and symmetric eigendecomposition) use `(A + Aᵀ)/2`, so derivatives
+// are split evenly between `A[(i, j)]` and `A[(j, i)]`.
+
+use std::rc::Rc;
+use nalgebra::{DefaultAllocator, Dim, DimMin, DimMinimum, DMatrix, DVector, Matrix, OMatrix, OVector, Storage, SVD, SymmetricEigen};
+use nalgebra::allocator::Allocator;
+use crate::f64ad::{ComputationGraphType, f64ad, F64adType};
+use crate::f64ad::composite_operations::{CompositeOperation, record_composite, supports_composites};
+
+/// Relative gap below which two eigenvalues (or singular values) are considered equal.  Eigenvector
+/// derivatives are not defined for repeated eigenvalues, so the coupling between such pairs is
+/// dropped rather than divided by a vanishing gap.
+const DEGENERACY_TOLERANCE: f64 = 1e-9;
+
+/// `a * b`.
+pub fn f64ad_matmul<R1: Dim, C1: Dim, R2: Dim, C2: Dim, S1: Storage<f64ad, R1, C1>, S2: Storage<f64ad, R2, C2>>(a: &Matrix<f64ad, R1, C1, S1>, b: &Matrix<f64ad, R2, C2, S2>) -> OMatrix<f64ad, R1, C2> where DefaultAllocator: Allocator<f64ad, R1, C2> {
+    assert_eq!(a.ncols(), b.nrows(), "matrix dimensions do not match for multiplication.");
+    let inputs: Vec<f64ad> = a.iter().chain(b.iter()).copied().collect();
+    if !supports_composites(&inputs) {
+        return OMatrix::from_fn_generic(a.shape_generic().0, b.shape_generic().1, |i, j| (0..a.ncols()).map(|l| a[(i, l)] * b[(l, j)]).sum());
+    }
+    let outputs = record_composite(&inputs, Rc::new(MatMul { m: a.nrows(), k: a.ncols(), n: b.ncols() }));
+    OMatrix::from_iterator_generic(a.shape_generic().0, b.shape_generic().1, outputs)
+}
+
+/// `x` such that `a * x = b`.  Panics if `a` is singular.
+pub fn f64ad_solve<D: Dim, C: Dim, S1: Storage<f64ad, D, D>, S2: Storage<f64ad, D, C>>(a: &Matrix<f64ad, D, D, S1>, b: &Matrix<f64ad, D, C, S2>) -> OMatrix<f64ad, D, C> where DefaultAllocator: Allocator<f64ad, D, C> {
+    assert!(a.is_square(), "can only solve with a square matrix.");
+    assert_eq!(a.nrows(), b.nrows(), "matrix dimensions do not match for solve.");
+    let inputs: Vec<f64ad> = a.iter().chain(b.iter()).copied().collect();
+    assert_not_traced(&inputs, "f64ad_solve");
+    if !supports_composites(&inputs) {
+        let x = solve_dynamic(&to_dmatrix(a), &to_dmatrix(b));
+        return OMatrix::from_iterator_generic(b.shape_generic().0, b.shape_generic().1, x.iter().copied());
+    }
+    let outputs = record_composite(&inputs, Rc::new(Solve { n: a.nrows(), k: b.ncols() }));
+    OMatrix::from_iterator_generic(b.shape_generic().0, b.shape_generic().1, outputs)
+}
+
+/// `a⁻¹`.  Panics if `a` is singular.
+pub fn f64ad_inverse<D: Dim, S: Storage<f64ad, D, D>>(a: &Matrix<f64ad, D, D, S>) -> OMatrix<f64ad, D, D> where DefaultAllocator: Allocator<f64ad, D, D> {
+    assert!(a.is_square(), "can only invert a square matrix.");
+    let inputs: Vec<f64ad> = a.iter().copied().collect();
+    assert_not_traced(&inputs, "f64ad_inverse");
+    if !supports_composites(&inputs) {
+        let inverse = inverse_dynamic(&to_dmatrix(a));
+        return OMatrix::from_iterator_generic(a.shape_generic().0, a.shape_generic().1, inverse.iter().copied());
+    }
+    let outputs = record_composite(&inputs, Rc::new(Inverse { n: a.nrows() }));
+    OMatrix::from_iterator_generic(a.shape_generic().0, a.shape_generic().1, outputs)
+}
+
+/// `det(a)`.  The derivative is the transposed adjugate of `a`, computed from an SVD, so it stays
+/// well defined when `a` is singular.
+pub fn f64ad_determinant<D: Dim, S: Storage<f64ad, D, D>>(a: &Matrix<f64ad, D, D, S>) -> f64ad {
+    assert!(a.is_square(), "can only take the determinant of a square matrix.");
+    let inputs: Vec<f64ad> = a.iter().copied().collect();
+    assert_not_traced(&inputs, "f64ad_determinant");
+    if !supports_composites(&inputs) { return to_dmatrix(a).determinant(); }
+    record_composite(&inputs, Rc::new(Determinant { n: a.nrows() }))[0]
+}
+
+/// `ln |det(a)|`.  Panics if `a` is singular.
+pub fn f64ad_log_determinant<D: Dim, S: Storage<f64ad, D, D>>(a: &Matrix<f64ad, D, D, S>) -> f64ad {
+    assert!(a.is_square(), "can only take the log determinant of a square matrix.");
+    let inputs: Vec<f64ad> = a.iter().copied().collect();
+    assert_not_traced(&inputs, "f64ad_log_determinant");
+    if !supports_composites(&inputs) {
+        let determinant = to_dmatrix(a).determinant();
+        assert!(determinant.value() != 0.0, "cannot take the log determinant of a singular matrix.");
+        return determinant.abs().ln();
+    }
+    record_composite(&inputs, Rc::new(LogDeterminant { n: a.nrows() }))[0]
+}
+
+/// The lower triangular `l` such that `l * lᵀ = a`.  Panics if `a` is not positive definite.
+pub fn f64ad_cholesky<D: Dim, S: Storage<f64ad, D, D>>(a: &Matrix<f64ad, D, D, S>) -> OMatrix<f64ad, D, D> where DefaultAllocator: Allocator<f64ad, D, D> {
+    assert!(a.is_square(), "can only take the cholesky decomposition of a square matrix.");
+    let inputs: Vec<f64ad> = a.iter().copied().collect();
+    assert_not_traced(&inputs, "f64ad_cholesky");
+    if !supports_composites(&inputs) {
+        let l = cholesky_dynamic(&to_dmatrix(a));
+        return OMatrix::from_iterator_generic(a.shape_generic().0, a.shape_generic().1, l.iter().copied());
+    }
+    let outputs = record_composite(&inputs, Rc::new(Cholesky { n: a.nrows() }));
+    OMatrix::from_iterator_generic(a.shape_generic().0, a.shape_generic().1, outputs)
+}
+
+/// `(eigenvalues, eigenvectors)` of the symmetric matrix `a`.  Eigenvalues are sorted in ascending
+/// order, eigenvector `i` is column `i`, and each eigenvector's largest component is positive.
+/// Panics on graphs other than `ComputationGraph1`.
+pub fn f64ad_symmetric_eigen<D: Dim, S: Storage<f64ad, D, D>>(a: &Matrix<f64ad, D, D, S>) -> (OVector<f64ad, D>, OMatrix<f64ad, D, D>) where DefaultAllocator: Allocator<f64ad, D> + Allocator<f64ad, D, D> {
+    assert!(a.is_square(), "can only take the eigendecomposition of a square matrix.");
+    let n = a.nrows();
+    let inputs: Vec<f64ad> = a.iter().copied().collect();
+    assert_first_order(&inputs, "f64ad_symmetric_eigen");
+    let outputs = record_composite(&inputs, Rc::new(SymmetricEigendecomposition { n }));
+    let (d, _) = a.shape_generic();
+    (OVector::from_iterator_generic(d, nalgebra::Const::<1>, outputs[..n].iter().copied()), OMatrix::from_iterator_generic(d, d, outputs[n..].iter().copied()))
+}
+
+/// `(u, singular_values, v_t)` of a thin singular value decomposition.
+pub type F64adSVD<R, C> = (OMatrix<f64ad, R, DimMinimum<R, C>>, OVector<f64ad, DimMinimum<R, C>>, OMatrix<f64ad, DimMinimum<R, C>, C>);
+
+/// `(u, singular_values, v_t)` of the thin singular value decomposition `a = u * diag(singular_values) * v_t`.
+/// Singular values are sorted in descending order.  Panics on graphs other than `ComputationGraph1`.
+pub fn f64ad_svd<R: DimMin<C>, C: Dim, S: Storage<f64ad, R, C>>(a: &Matrix<f64ad, R, C, S>) -> F64adSVD<R, C> where DefaultAllocator: Allocator<f64ad, R, DimMinimum<R, C>> + Allocator<f64ad, DimMinimum<R, C>> + Allocator<f64ad, DimMinimum<R, C>, C> {
+    let (m, n) = a.shape();
+    let k = m.min(n);
+    let inputs: Vec<f64ad> = a.iter().copied().collect();
+    assert_first_order(&inputs, "f64ad_svd");
+    let outputs = record_composite(&inputs, Rc::new(SingularValueDecomposition { m, n }));
+    let (r, c) = a.shape_generic();
+    let kd = r.min(c);
+    (OMatrix::from_iterator_generic(r, kd, outputs[..m * k].iter().copied()),
+     OVector::from_iterator_generic(kd, nalgebra::Const::<1>, outputs[m * k..m * k + k].iter().copied()),
+     OMatrix::from_iterator_generic(kd, c, outputs[m * k + k..].iter().copied()))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+struct MatMul {
+    m: usize,
+    k: usize,
+    n: usize
+}
+impl CompositeOperation for MatMul {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        let (a, b) = (matrix_at(input_values, 0, self.m, self.k), matrix_at(input_values, self.m * self.k, self.k, self.n));
+        (a * b).as_slice().to_vec()
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        let (a, b) = (matrix_at(input_values, 0, self.m, self.k), matrix_at(input_values, self.m * self.k, self.k, self.n));
+        let c_bar = matrix_at(output_adjoints, 0, self.m, self.n);
+        concat(&[&c_bar * b.transpose(), a.transpose() * &c_bar])
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        let (a, b) = (matrix_at(input_values, 0, self.m, self.k), matrix_at(input_values, self.m * self.k, self.k, self.n));
+        let (a_dot, b_dot) = (matrix_at(input_tangents, 0, self.m, self.k), matrix_at(input_tangents, self.m * self.k, self.k, self.n));
+        (a_dot * b + a * b_dot).as_slice().to_vec()
+    }
+}
+
+struct Solve {
+    n: usize,
+    k: usize
+}
+impl Solve {
+    fn solve(&self, input_values: &[f64]) -> (DMatrix<f64>, DMatrix<f64>) {
+        let a = matrix_at(input_values, 0, self.n, self.n);
+        let x = a.clone().lu().solve(&matrix_at(input_values, self.n * self.n, self.n, self.k)).expect("matrix is singular.");
+        (a, x)
+    }
+}
+impl CompositeOperation for Solve {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        self.solve(input_values).1.as_slice().to_vec()
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        let (a, x) = self.solve(input_values);
+        let b_bar = a.transpose().lu().solve(&matrix_at(output_adjoints, 0, self.n, self.k)).expect("matrix is singular.");
+        concat(&[-&b_bar * x.transpose(), b_bar])
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        let (a, x) = self.solve(input_values);
+        let (a_dot, b_dot) = (matrix_at(input_tangents, 0, self.n, self.n), matrix_at(input_tangents, self.n * self.n, self.n, self.k));
+        a.lu().solve(&(b_dot - a_dot * x)).expect("matrix is singular.").as_slice().to_vec()
+    }
+}
+
+struct Inverse {
+    n: usize
+}
+impl Inverse {
+    fn inverse(&self, input_values: &[f64]) -> DMatrix<f64> {
+        matrix_at(input_values, 0, self.n, self.n).try_inverse().expect("matrix is singular.")
+    }
+}
+impl CompositeOperation for Inverse {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        self.inverse(input_values).as_slice().to_vec()
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        let y_t = self.inverse(input_values).transpose();
+        (-&y_t * matrix_at(output_adjoints, 0, self.n, self.n) * &y_t).as_slice().to_vec()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        let y = self.inverse(input_values);
+        (-&y * matrix_at(input_tangents, 0, self.n, self.n) * &y).as_slice().to_vec()
+    }
+}
+
+struct Determinant {
+    n: usize
+}
+impl Determinant {
+    /// `adj(A) = det(U) det(Vᵀ) V adj(Σ) Uᵀ`, where `adj(Σ)` holds the products of all other singular values.
+    fn adjugate(&self, input_values: &[f64]) -> DMatrix<f64> {
+        let svd = SVD::new(matrix_at(input_values, 0, self.n, self.n), true, true);
+        let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        let sigma = &svd.singular_values;
+        let adj_sigma = DVector::from_fn(self.n, |i, _| (0..self.n).filter(|j| *j != i).map(|j| sigma[j]).product::<f64>());
+        (v_t.transpose() * DMatrix::from_diagonal(&adj_sigma) * u.transpose()) * (u.determinant() * v_t.determinant())
+    }
+}
+impl CompositeOperation for Determinant {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        vec![matrix_at(input_values, 0, self.n, self.n).determinant()]
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        (self.adjugate(input_values).transpose() * output_adjoints[0]).as_slice().to_vec()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        vec![(self.adjugate(input_values) * matrix_at(input_tangents, 0, self.n, self.n)).trace()]
+    }
+}
+
+struct LogDeterminant {
+    n: usize
+}
+impl LogDeterminant {
+    fn inverse(&self, input_values: &[f64]) -> DMatrix<f64> {
+        matrix_at(input_values, 0, self.n, self.n).try_inverse().expect("matrix is singular.")
+    }
+}
+impl CompositeOperation for LogDeterminant {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        let lu = matrix_at(input_values, 0, self.n, self.n).lu();
+        vec![lu.u().diagonal().iter().map(|x| x.abs().ln()).sum()]
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        (self.inverse(input_values).transpose() * output_adjoints[0]).as_slice().to_vec()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        vec![(self.inverse(input_values) * matrix_at(input_tangents, 0, self.n, self.n)).trace()]
+    }
+}
+
+struct Cholesky {
+    n: usize
+}
+impl Cholesky {
+    fn l(&self, input_values: &[f64]) -> DMatrix<f64> {
+        symmetric_part(&matrix_at(input_values, 0, self.n, self.n)).cholesky().expect("matrix is not positive definite.").l()
+    }
+}
+impl CompositeOperation for Cholesky {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        self.l(input_values).as_slice().to_vec()
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        // `Ā = sym(L⁻ᵀ Φ(Lᵀ L̄) L⁻¹)`, where the strictly upper triangle of `L` is constant.
+        let l = self.l(input_values);
+        let l_bar = matrix_at(output_adjoints, 0, self.n, self.n).lower_triangle();
+        let q = l.tr_solve_lower_triangular(&phi(&(l.transpose() * l_bar))).expect("error");
+        let s_bar = l.tr_solve_lower_triangular(&q.transpose()).expect("error").transpose();
+        symmetric_part(&s_bar).as_slice().to_vec()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        // `L̇ = L Φ(L⁻¹ sym(Ȧ) L⁻ᵀ)`.
+        let l = self.l(input_values);
+        let w = l.solve_lower_triangular(&symmetric_part(&matrix_at(input_tangents, 0, self.n, self.n))).expect("error");
+        let m = l.solve_lower_triangular(&w.transpose()).expect("error");
+        (&l * phi(&m)).as_slice().to_vec()
+    }
+}
+
+struct SymmetricEigendecomposition {
+    n: usize
+}
+impl SymmetricEigendecomposition {
+    fn eigen(&self, input_values: &[f64]) -> (DVector<f64>, DMatrix<f64>) {
+        let eigen = SymmetricEigen::new(symmetric_part(&matrix_at(input_values, 0, self.n, self.n)));
+        let mut order: Vec<usize> = (0..self.n).collect();
+        order.sort_by(|i, j| eigen.eigenvalues[*i].partial_cmp(&eigen.eigenvalues[*j]).expect("eigenvalue is NaN."));
+        let eigenvalues = DVector::from_fn(self.n, |i, _| eigen.eigenvalues[order[i]]);
+        let mut eigenvectors = DMatrix::from_fn(self.n, self.n, |i, j| eigen.eigenvectors[(i, order[j])]);
+        for mut column in eigenvectors.column_iter_mut() {
+            if column[column.iamax()] < 0.0 { column.neg_mut(); }
+        }
+        (eigenvalues, eigenvectors)
+    }
+    /// `F[(i, j)] = 1 / (λ_j - λ_i)` for distinct eigenvalues, 0 otherwise.
+    fn f(&self, eigenvalues: &DVector<f64>) -> DMatrix<f64> {
+        let tolerance = DEGENERACY_TOLERANCE * eigenvalues.amax().max(1.0);
+        DMatrix::from_fn(self.n, self.n, |i, j| {
+            let gap = eigenvalues[j] - eigenvalues[i];
+            if i == j || gap.abs() <= tolerance { 0.0 } else { 1.0 / gap }
+        })
+    }
+}
+impl CompositeOperation for SymmetricEigendecomposition {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        let (eigenvalues, eigenvectors) = self.eigen(input_values);
+        eigenvalues.iter().chain(eigenvectors.iter()).copied().collect()
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        // `Ā = sym(V (diag(λ̄) + F ∘ (Vᵀ V̄)) Vᵀ)`.
+        let (eigenvalues, v) = self.eigen(input_values);
+        let eigenvalues_bar = DVector::from_column_slice(&output_adjoints[..self.n]);
+        let v_bar = matrix_at(output_adjoints, self.n, self.n, self.n);
+        let inner = DMatrix::from_diagonal(&eigenvalues_bar) + self.f(&eigenvalues).component_mul(&(v.transpose() * v_bar));
+        symmetric_part(&(&v * inner * v.transpose())).as_slice().to_vec()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        // `λ̇ = diag(K)` and `V̇ = V (F ∘ K)`, where `K = Vᵀ sym(Ȧ) V`.
+        let (eigenvalues, v) = self.eigen(input_values);
+        let k = v.transpose() * symmetric_part(&matrix_at(input_tangents, 0, self.n, self.n)) * &v;
+        let v_dot = &v * self.f(&eigenvalues).component_mul(&k);
+        k.diagonal().iter().chain(v_dot.iter()).copied().collect()
+    }
+}
+
+struct SingularValueDecomposition {
+    m: usize,
+    n: usize
+}
+impl SingularValueDecomposition {
+    fn svd(&self, input_values: &[f64]) -> (DMatrix<f64>, DVector<f64>, DMatrix<f64>) {
+        let svd = SVD::new(matrix_at(input_values, 0, self.m, self.n), true, true);
+        (svd.u.unwrap(), svd.singular_values, svd.v_t.unwrap())
+    }
+    /// `F[(i, j)] = 1 / (σ_j² - σ_i²)` for distinct singular values, 0 otherwise, and the
+    /// pseudo-inverse of `diag(σ)`.
+    fn f_and_sigma_inverse(&self, sigma: &DVector<f64>) -> (DMatrix<f64>, DMatrix<f64>) {
+        let k = sigma.len();
+        let tolerance = DEGENERACY_TOLERANCE * sigma.amax().max(1.0);
+        let f = DMatrix::from_fn(k, k, |i, j| {
+            if i == j || (sigma[j] - sigma[i]).abs() <= tolerance { 0.0 } else { 1.0 / (sigma[j] * sigma[j] - sigma[i] * sigma[i]) }
+        });
+        let sigma_inverse = DMatrix::from_diagonal(&sigma.map(|x| if x <= tolerance { 0.0 } else { 1.0 / x }));
+        (f, sigma_inverse)
+    }
+}
+impl CompositeOperation for SingularValueDecomposition {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        let (u, sigma, v_t) = self.svd(input_values);
+        u.iter().chain(sigma.iter()).chain(v_t.iter()).copied().collect()
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        // Townsend (2016), "Differentiating the Singular Value Decomposition":
+        // `Ā = U [(F ∘ (UᵀŪ - ŪᵀU)) Σ + diag(σ̄) + Σ (F ∘ (VᵀV̄ - V̄ᵀV))] Vᵀ
+        //      + (I - UUᵀ) Ū Σ⁻¹ Vᵀ + U Σ⁻¹ V̄ᵀ (I - VVᵀ)`.
+        let (m, n) = (self.m, self.n);
+        let k = m.min(n);
+        let (u, sigma, v_t) = self.svd(input_values);
+        let v = v_t.transpose();
+        let (f, sigma_inverse) = self.f_and_sigma_inverse(&sigma);
+        let s = DMatrix::from_diagonal(&sigma);
+        let u_bar = matrix_at(output_adjoints, 0, m, k);
+        let sigma_bar = DVector::from_column_slice(&output_adjoints[m * k..m * k + k]);
+        let v_bar = matrix_at(output_adjoints, m * k + k, k, n).transpose();
+
+        let u_t_u_bar = u.transpose() * &u_bar;
+        let v_t_v_bar = &v_t * &v_bar;
+        let inner = f.component_mul(&(&u_t_u_bar - u_t_u_bar.transpose())) * &s + DMatrix::from_diagonal(&sigma_bar) + &s * f.component_mul(&(&v_t_v_bar - v_t_v_bar.transpose()));
+        let mut a_bar = &u * inner * &v_t;
+        a_bar += (DMatrix::identity(m, m) - &u * u.transpose()) * u_bar * &sigma_inverse * &v_t;
+        a_bar += &u * sigma_inverse * v_bar.transpose() * (DMatrix::identity(n, n) - &v * &v_t);
+        a_bar.as_slice().to_vec()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        // With `dP = Uᵀ Ȧ V`: `σ̇ = diag(dP)`,
+        // `U̇ = U (F ∘ (dP Σ + Σ dPᵀ)) + (I - UUᵀ) Ȧ V Σ⁻¹`, and
+        // `V̇ = V (F ∘ (Σ dP + dPᵀ Σ)) + (I - VVᵀ) Ȧᵀ U Σ⁻¹`.
+        let (m, n) = (self.m, self.n);
+        let (u, sigma, v_t) = self.svd(input_values);
+        let v = v_t.transpose();
+        let (f, sigma_inverse) = self.f_and_sigma_inverse(&sigma);
+        let s = DMatrix::from_diagonal(&sigma);
+        let a_dot = matrix_at(input_tangents, 0, m, n);
+
+        let dp = u.transpose() * &a_dot * &v;
+        let u_dot = &u * f.component_mul(&(&dp * &s + &s * dp.transpose())) + (DMatrix::identity(m, m) - &u * u.transpose()) * &a_dot * &v * &sigma_inverse;
+        let v_dot = &v * f.component_mul(&(&s * &dp + dp.transpose() * &s)) + (DMatrix::identity(n, n) - &v * &v_t) * a_dot.transpose() * &u * &sigma_inverse;
+        u_dot.iter().chain(dp.diagonal().iter()).chain(v_dot.transpose().iter()).copied().collect()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn to_dmatrix<R: Dim, C: Dim, S: Storage<f64ad, R, C>>(a: &Matrix<f64ad, R, C, S>) -> DMatrix<f64ad> {
+    DMatrix::from_iterator(a.nrows(), a.ncols(), a.iter().copied())
+}
+
+// The fallbacks run on `DMatrix` outside of the generic functions, whose allocator bounds would
+// otherwise be picked over nalgebra's impls for the decompositions.
+
+fn solve_dynamic(a: &DMatrix<f64ad>, b: &DMatrix<f64ad>) -> DMatrix<f64ad> {
+    a.clone().lu().solve(b).expect("cannot solve with a singular matrix.")
+}
+
+fn inverse_dynamic(a: &DMatrix<f64ad>) -> DMatrix<f64ad> {
+    a.clone().lu().try_inverse().expect("cannot invert a singular matrix.")
+}
+
+fn cholesky_dynamic(a: &DMatrix<f64ad>) -> DMatrix<f64ad> {
+    let symmetric = (a + a.transpose()) * f64ad::f64(0.5);
+    symmetric.cholesky().expect("cannot take the cholesky decomposition of a matrix that is not positive definite.").l()
+}
+
+/// Panics with a message naming `function` if `inputs` are on a tracer or locked graph.
+fn assert_not_traced(inputs: &[f64ad], function: &str) {
+    if let Some(x) = inputs.iter().find(|x| x.map_to_type() != F64adType::F64) {
+        let computation_graph_type = x.computation_graph().map_to_type();
+        assert!(computation_graph_type != ComputationGraphType::ComputationGraphT && computation_graph_type != ComputationGraphType::ComputationGraphL, "{} is not supported on {:?} graphs, which can neither record composite operations nor branch on values.", function, computation_graph_type);
+    }
+}
+
+/// Panics with a message naming `function` unless `inputs` can be recorded as a composite
+/// operation, i.e., they are all constants or on a `ComputationGraph1`.
+fn assert_first_order(inputs: &[f64ad], function: &str) {
+    if !supports_composites(inputs) {
+        let computation_graph_type = inputs.iter().find(|x| x.map_to_type() != F64adType::F64).unwrap().computation_graph().map_to_type();
+        panic!("{} is only supported on ComputationGraph1 graphs, since only its first order derivatives are available, not on {:?} graphs.", function, computation_graph_type);
+    }
+}
+
+fn matrix_at(values: &[f64], offset: usize, nrows: usize, ncols: usize) -> DMatrix<f64> {
+    DMatrix::from_column_slice(nrows, ncols, &values[offset..offset + nrows * ncols])
+}
+
+fn concat(matrices: &[DMatrix<f64>]) -> Vec<f64> {
+    matrices.iter().flat_map(|x| x.iter().copied()).collect()
+}
+
+fn symmetric_part(a: &DMatrix<f64>) -> DMatrix<f64> {
+    (a + a.transpose()) * 0.5
+}
+
+/// The lower triangle of `a` with its diagonal halved.
+fn phi(a: &DMatrix<f64>) -> DMatrix<f64> {
+    let mut out = a.lower_triangle();
+    out.set_diagonal(&(a.diagonal() * 0.5));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+    use crate::f64ad::{f64ad, GlobalComputationGraph, GlobalComputationGraphs};
+    use super::*;
+
+    const A: [f64; 9] = [2.0, 0.3, -0.4, 0.3, 1.5, 0.2, -0.4, 0.2, 1.8];
+
+    /// A weighted sum of the outputs of one of the functions, so its gradient depends on every output.
+    fn loss(name: &str, a: &DMatrix<f64ad>) -> f64ad {
+        let b = DMatrix::from_fn(3, 2, |i, j| a[(i, j)] * 0.5 + (i + j) as f64);
+        let outputs: Vec<f64ad> = match name {
+            "matmul" => { f64ad_matmul(a, &b).iter().copied().collect() }
+            "solve" => { f64ad_solve(a, &b).iter().copied().collect() }
+            "inverse" => { f64ad_inverse(a).iter().copied().collect() }
+            "determinant" => { vec![f64ad_determinant(a)] }
+            "log_determinant" => { vec![f64ad_log_determinant(a)] }
+            "cholesky" => { f64ad_cholesky(a).iter().copied().collect() }
+            _ => { unreachable!() }
+        };
+        outputs.iter().enumerate().map(|(i, x)| *x * (1.0 + 0.1 * i as f64)).sum()
+    }
+
+    fn gradient(computation_graph: &GlobalComputationGraph, name: &str) -> Vec<f64> {
+        computation_graph.reset();
+        let a = DMatrix::from_iterator(3, 3, A.iter().map(|x| computation_graph.spawn_variable(*x)));
+        let grad = loss(name, &a).backwards_mode_grad(false);
+        a.iter().map(|x| grad.wrt(x).value()).collect()
+    }
+
+    /// The fallbacks on `ComputationGraphF` graphs give the same gradients as the composite
+    /// operations on `ComputationGraph1` graphs.
+    #[test]
+    fn fallbacks_match_composites() {
+        for name in ["matmul", "solve", "inverse", "determinant", "log_determinant", "cholesky"] {
+            let expected = gradient(&GlobalComputationGraphs::get_first_order(Some("linear_algebra_tests"), None), name);
+            let actual = gradient(&GlobalComputationGraphs::get(Some("linear_algebra_tests"), None), name);
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                assert!((e - a).abs() <= 1e-12 * (1.0 + e.abs()), "gradient of {} is {}, but the composite gives {}.", name, a, e);
+            }
+        }
+    }
+
+    #[test]
+    fn matmul_on_locked_graph() {
+        let expected = gradient(&GlobalComputationGraphs::get_first_order(Some("linear_algebra_tests_matmul"), None), "matmul");
+        let tracer = GlobalComputationGraphs::get_tracer(Some("linear_algebra_tests_matmul"), None);
+        let a = DMatrix::from_iterator(3, 3, A.iter().map(|x| tracer.spawn_variable(*x)));
+        tracer.set_outputs(&[loss("matmul", &a)]);
+        tracer.lock(Some("linear_algebra_tests_matmul"), None);
+        let locked = GlobalComputationGraphs::get_locked(Some("linear_algebra_tests_matmul"), None);
+        let grad = locked.evaluate(&A)[0].backwards_mode_grad(false);
+        for (e, x) in expected.iter().zip(locked.inputs().iter()) {
+            assert!((e - grad.wrt(x).value()).abs() <= 1e-12 * (1.0 + e.abs()));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "f64ad_inverse is not supported on ComputationGraphT graphs")]
+    fn inverse_on_tracer_graph() {
+        let tracer = GlobalComputationGraphs::get_tracer(Some("linear_algebra_tests_inverse"), None);
+        let a = DMatrix::from_iterator(3, 3, A.iter().map(|x| tracer.spawn_variable(*x)));
+        f64ad_inverse(&a);
+    }
+
+    /// Checks the gradient of a weighted sum of the outputs of `f` at `a`, from backwards and forward
+    /// mode sweeps on a first order graph, against central differences.
+    fn assert_gradient_matches_finite_differences(name: &str, f: &dyn Fn(&DMatrix<f64ad>) -> Vec<f64ad>, a: &DMatrix<f64>, h: f64, tolerance: f64) {
+        let weighted_sum = |outputs: Vec<f64ad>| -> f64ad { outputs.iter().enumerate().map(|(i, x)| *x * (1.0 + 0.1 * i as f64)).sum() };
+        let value = |a: &DMatrix<f64>| weighted_sum(f(&a.map(f64ad::f64))).value();
+
+        let computation_graph = GlobalComputationGraphs::get_first_order(Some("linear_algebra_tests_finite_differences"), None);
+        computation_graph.reset();
+        let x = a.map(|v| computation_graph.spawn_variable(v));
+        let out = weighted_sum(f(&x));
+        let grad = out.backwards_mode_grad(false);
+        for i in 0..a.len() {
+            let (mut plus, mut minus) = (a.clone(), a.clone());
+            plus[i] += h;
+            minus[i] -= h;
+            let expected = (value(&plus) - value(&minus)) / (2.0 * h);
+            for (mode, actual) in [("backwards", grad.wrt(&x[i]).value()), ("forward", x[i].forward_mode_grad(false).wrt(&out).value())] {
+                assert!((actual - expected).abs() <= tolerance * (1.0 + expected.abs()), "{} mode derivative of {} with respect to entry {} is {}, but finite differences give {}.", mode, name, i, actual, expected);
+            }
+        }
+    }
+
+    fn eigen(a: &DMatrix<f64ad>) -> Vec<f64ad> {
+        let (eigenvalues, eigenvectors) = f64ad_symmetric_eigen(a);
+        eigenvalues.iter().chain(eigenvectors.iter()).copied().collect()
+    }
+
+    fn svd(a: &DMatrix<f64ad>) -> Vec<f64ad> {
+        let (u, singular_values, v_t) = f64ad_svd(a);
+        u.iter().chain(singular_values.iter()).chain(v_t.iter()).copied().collect()
+    }
+
+    #[test]
+    fn symmetric_eigen_matches_finite_differences() {
+        assert_gradient_matches_finite_differences("f64ad_symmetric_eigen", &eigen, &DMatrix::from_column_slice(3, 3, &A), 1e-6, 1e-6);
+        // only the symmetric part is used.
+        let asymmetric = DMatrix::from_row_slice(3, 3, &[2.0, 0.1, -0.4, 0.5, 1.5, 0.6, -0.2, -0.2, 1.8]);
+        assert_gradient_matches_finite_differences("f64ad_symmetric_eigen", &eigen, &asymmetric, 1e-6, 1e-6);
+
+        // eigenvalues 1 and 1.001 are close but above `DEGENERACY_TOLERANCE`, so eigenvector
+        // derivatives are large (~1 / gap) but still exact.
+        let q = nalgebra::Rotation3::from_euler_angles(0.3, -0.5, 0.7);
+        let q = DMatrix::from_column_slice(3, 3, q.matrix().as_slice());
+        let near_degenerate = &q * DMatrix::from_diagonal(&DVector::from_column_slice(&[1.0, 1.001, 2.5])) * q.transpose();
+        assert_gradient_matches_finite_differences("f64ad_symmetric_eigen", &eigen, &near_degenerate, 1e-7, 1e-5);
+    }
+
+    #[test]
+    fn svd_matches_finite_differences() {
+        let tall = DMatrix::from_row_slice(3, 2, &[1.2, -0.3, 0.4, 0.9, -0.7, 0.5]);
+        let wide = DMatrix::from_row_slice(2, 3, &[0.8, -0.6, 0.3, 0.2, 1.1, -0.9]);
+        for a in [DMatrix::from_column_slice(3, 3, &A), tall, wide] {
+            assert_gradient_matches_finite_differences("f64ad_svd", &svd, &a, 1e-6, 1e-6);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "f64ad_symmetric_eigen is only supported on ComputationGraph1 graphs")]
+    fn symmetric_eigen_on_computation_graph_f() {
+        let computation_graph = GlobalComputationGraphs::get(Some("linear_algebra_tests_eigen"), None);
+        let a = DMatrix::from_iterator(3, 3, A.iter().map(|x| computation_graph.spawn_variable(*x)));
+        f64ad_symmetric_eigen(&a);
+    }
+}
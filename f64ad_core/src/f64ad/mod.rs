@@ -3,17 +3,31 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign};
+use std::rc::Rc;
 use std::sync::Mutex;
-use tinyvec::{tiny_vec, TinyVec};
+use std::time::Instant;
+use tinyvec::TinyVec;
 use once_cell::sync::OnceCell;
-use nalgebra::ComplexField;
-use rand::{Rng, thread_rng};
+use nalgebra::Complex;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use serde::de::{Error, Visitor};
 use crate::f64ad::f64ad_var_1_mod::*;
 use crate::f64ad::f64ad_var_f_mod::{ComputationGraphF, f64ad_var_f};
-use crate::f64ad::f64ad_var_l_mod::{ComputationGraphL, f64ad_var_l, F64ADNodeL};
+use crate::f64ad::f64ad_var_l_mod::{ComputationGraphL, f64ad_var_l};
 use crate::f64ad::f64ad_var_t_mod::{ComputationGraphT, f64ad_var_t};
+use crate::f64ad::composite_operations::{composite_backwards_mode_step, composite_forward_mode_step, CompositeOperation, CompositeRecord};
+use crate::f64ad::nested_levels::{lift_to_common_level, NestedLevel, resolve_node_idx, to_outer_level};
+use crate::f64ad::tape::{Tape, TapeMark};
+use crate::f64ad::dot_export::to_dot;
+use crate::f64ad::saved_graphs::{LoadError, SavedComputationGraph};
+use crate::f64ad::optimization::{optimize, OptimizationPasses};
+use crate::f64ad::graph_stats::{GraphStats, SweepTimings};
+use crate::f64ad::expression_printing::{ExpressionFormat, to_expression_string};
+use crate::f64ad::batch_evaluation::{BatchOutput, evaluate_batch};
+use crate::f64ad::c64ad_mod::c64ad;
+use crate::f64ad::derivative_rules::derivatives;
+use crate::f64ad::interval_mod::{evaluate_interval, Interval, IntervalOutput};
+use crate::f64ad::non_finite_tracking::{caller_location, check_new_node, check_sweep_step, NonFiniteRecord, NonFiniteStage, NonFiniteTracker};
 
 pub mod trait_impls;
 pub mod f64ad_var_1_mod;
@@ -21,6 +35,26 @@ pub mod f64ad_var_f_mod;
 pub mod f64ad_var_l_mod;
 pub mod f64ad_var_t_mod;
 pub mod manual_derivative_functions;
+pub mod composite_operations;
+pub mod checkpointing;
+pub mod non_finite_tracking;
+pub mod nested_levels;
+pub mod tape;
+pub mod dot_export;
+pub mod saved_graphs;
+pub mod codegen;
+pub mod optimization;
+pub mod graph_stats;
+pub mod expression_printing;
+pub mod expression_parser;
+pub mod batch_evaluation;
+pub mod c64ad_mod;
+pub mod derivative_rules;
+pub mod interval_mod;
+pub mod f64adx4_mod;
+pub mod linear_algebra;
+pub mod geometry;
+pub mod reductions;
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, Debug)]
@@ -57,6 +91,18 @@ impl f64ad {
             f64ad::f64ad_var_l(v) => { v.node_idx() }
         }
     }
+    /// Renders the expression that produced this value as infix text or LaTeX.  Derivatives computed
+    /// with `add_to_computation_graph = true` can be printed the same way.  See
+    /// `expression_printing` for the details.
+    pub fn to_expression_string(&self, format: ExpressionFormat, share_subexpressions: bool) -> String {
+        to_expression_string(self, format, share_subexpressions)
+    }
+    /// Returns the value of this `f64ad` as a constant, i.e., no derivatives will flow back through
+    /// the result (stop-gradient).
+    #[inline(always)]
+    pub fn detach(&self) -> f64ad {
+        f64ad::f64(self.value())
+    }
     #[inline(always)]
     pub fn map_to_type(&self) -> F64adType {
         match self {
@@ -68,6 +114,7 @@ impl f64ad {
         }
     }
     pub fn forward_mode_grad(&self, add_to_computation_graph: bool) -> ForwardModeGradOutput {
+        self.assert_valid();
         return match self {
             f64ad::f64(_) => { panic!("cannot compute gradient on f64.") }
             f64ad::f64ad_var_1(_) => {
@@ -85,6 +132,7 @@ impl f64ad {
         };
     }
     pub fn backwards_mode_grad(&self, add_to_computation_graph: bool) -> BackwardsModeGradOutput {
+        self.assert_valid();
         return match self {
             f64ad::f64(_) => { panic!("cannot compute gradient on f64.") }
             f64ad::f64ad_var_1(_) => {
@@ -101,6 +149,22 @@ impl f64ad {
             }
         };
     }
+    /// The generation of the tape this variable was created in.
+    pub(crate) fn generation(&self) -> u32 {
+        match self {
+            f64ad::f64(_) => { panic!("no generation on f64.") }
+            f64ad::f64ad_var_1(v) => { v.generation() }
+            f64ad::f64ad_var_f(v) => { v.generation() }
+            f64ad::f64ad_var_t(v) => { v.generation() }
+            f64ad::f64ad_var_l(v) => { v.generation() }
+        }
+    }
+    /// Panics if this variable was invalidated by a reset or rewind of its computation graph, i.e.,
+    /// its node may since have been reused by another variable.
+    pub(crate) fn assert_valid(&self) {
+        if self.map_to_type() == F64adType::F64 { return; }
+        self.computation_graph().assert_variable_valid(self.node_idx(), self.generation());
+    }
     #[inline(always)]
     pub fn computation_graph(&self) -> &'static ComputationGraph {
         match self {
@@ -183,14 +247,12 @@ impl ComputationGraph {
         }
     }
     #[inline(always)]
+    #[cfg_attr(feature = "track_caller", track_caller)]
     pub(crate) fn add_node(&'static self, value: f64, node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, parent_0: Option<f64ad>, parent_1: Option<f64ad>) -> f64ad {
-        return match self {
+        if self.paused() { return f64ad::f64(value); }
+        let out = match self {
             ComputationGraph::ComputationGraph1(c) => {
-                if c.borrow().paused() {
-                    f64ad::f64(value)
-                } else {
-                    c.borrow().add_node(value, node_type_class, node_operands_mode, parent_0, parent_1, self)
-                }
+                c.borrow().add_node(value, node_type_class, node_operands_mode, parent_0, parent_1, self)
             }
             ComputationGraph::ComputationGraphF(c) => {
                 c.borrow().add_node(value, node_type_class, node_operands_mode, parent_0, parent_1, self)
@@ -202,9 +264,12 @@ impl ComputationGraph {
                 c.borrow().add_node(value, node_type_class, node_operands_mode, parent_0, parent_1, self)
             }
         };
+        if self.debug_mode() { check_new_node(self, out, value, node_type_class, node_operands_mode, parent_0, parent_1, caller_location()); }
+        out
     }
+    #[cfg_attr(feature = "track_caller", track_caller)]
     pub(crate) fn spawn_variable(&'static self, value: f64) -> f64ad {
-        match self {
+        let out = match self {
             ComputationGraph::ComputationGraph1(c) => {
                 c.borrow().add_node(value, NodeTypeClass::InputVariable, NodeOperandsMode::NoParents, None, None, self)
             }
@@ -217,51 +282,95 @@ impl ComputationGraph {
             ComputationGraph::ComputationGraphL(c) => {
                 c.borrow().add_node(value, NodeTypeClass::InputVariable, NodeOperandsMode::NoParents, None, None, self)
             }
-        }
+        };
+        if self.debug_mode() { check_new_node(self, out, value, NodeTypeClass::InputVariable, NodeOperandsMode::NoParents, None, None, caller_location()); }
+        out
     }
-    #[inline(always)]
-    pub(crate) fn get_node_value(&self, node_idx: usize) -> f64 {
+    pub(crate) fn add_composite(&'static self, inputs: &[f64ad], operation: Rc<dyn CompositeOperation>) -> Vec<f64ad> {
+        let input_values: Vec<f64> = inputs.iter().map(|x| x.value()).collect();
+        let output_values = operation.compute_outputs(&input_values);
+        if self.paused() { return output_values.iter().map(|x| f64ad::f64(*x)).collect(); }
         match self {
             ComputationGraph::ComputationGraph1(c) => {
-                c.borrow().computation_graph().borrow().item(node_idx).value()
+                c.borrow().add_composite(inputs, input_values, output_values, operation, self)
             }
             ComputationGraph::ComputationGraphF(c) => {
-                c.borrow().computation_graph().borrow().item(node_idx).value()
-            }
-            ComputationGraph::ComputationGraphT(c) => {
-                c.borrow().computation_graph().borrow()[node_idx].value()
-            }
-            ComputationGraph::ComputationGraphL(c) => {
-                c.borrow().locked_nodes().borrow()[node_idx].value()
+                c.borrow().add_composite(inputs, input_values, output_values, operation, self)
             }
+            _ => { panic!("composite operations are only supported on ComputationGraph1 and ComputationGraphF.") }
+        }
+    }
+    pub(crate) fn get_composite(&self, composite_idx: usize) -> CompositeRecord {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow().composite(composite_idx) }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow().composite(composite_idx) }
+            _ => { unreachable!() }
+        }
+    }
+    pub(crate) fn with_tape<R, F: FnOnce(&Tape) -> R>(&self, f: F) -> R {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { f(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphF(c) => { f(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphT(c) => { f(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphL(c) => { f(&c.borrow().tape().borrow()) }
         }
     }
     #[inline(always)]
-    /// Returns the node parents, node type class, and node operands mode
-    pub(crate) fn get_node_bundle(&self, node_idx: usize) -> ([Option<f64ad>; 2], NodeTypeClass, NodeOperandsMode) {
-        return match self {
+    pub(crate) fn get_node_value(&self, node_idx: usize) -> f64 {
+        match self {
             ComputationGraph::ComputationGraph1(c) => {
-                let binding0 = c.borrow();
-                let binding1 = binding0.computation_graph().borrow();
-                let node = binding1.item(node_idx);
-                ([node.parent_0().clone(), node.parent_1().clone()], node.node_type_class(), node.node_operands_mode())
+                c.borrow().tape().borrow().value(node_idx)
             }
             ComputationGraph::ComputationGraphF(c) => {
-                let binding0 = c.borrow();
-                let binding1 = binding0.computation_graph().borrow();
-                let node = binding1.item(node_idx);
-                ([node.parent_0().clone(), node.parent_1().clone()], node.node_type_class(), node.node_operands_mode())
+                c.borrow().tape().borrow().value(node_idx)
             }
-            ComputationGraph::ComputationGraphT(_) => {
-                unreachable!()
+            ComputationGraph::ComputationGraphT(c) => {
+                c.borrow().tape().borrow().value(node_idx)
             }
             ComputationGraph::ComputationGraphL(c) => {
-                let binding0 = c.borrow();
-                let binding1 = binding0.locked_nodes().borrow();
-                let node = &binding1[node_idx];
-                ([node.parent_0().clone(), node.parent_1().clone()], node.node_type_class(), node.node_operands_mode())
+                c.borrow().tape().borrow().value(node_idx)
             }
+        }
+    }
+    /// Panics if the variable at `node_idx` that was created in `generation` was invalidated by a
+    /// reset or rewind.
+    pub(crate) fn assert_variable_valid(&self, node_idx: usize, generation: u32) {
+        self.with_tape(|tape| assert!(tape.is_valid(node_idx, generation), "variable no longer exists, its computation graph was reset or rewound since it was created."));
+    }
+    /// Same as `get_node_value`, but panics if the variable was invalidated by a reset or rewind.
+    #[inline(always)]
+    pub(crate) fn get_variable_value(&self, node_idx: usize, generation: u32) -> f64 {
+        let get = |tape: &Tape| {
+            assert!(tape.is_valid(node_idx, generation), "variable no longer exists, its computation graph was reset or rewound since it was created.");
+            tape.value(node_idx)
         };
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { get(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphF(c) => { get(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphT(c) => { get(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphL(c) => { get(&c.borrow().tape().borrow()) }
+        }
+    }
+    /// Returns the variable at `node_idx` on this graph.
+    #[inline(always)]
+    pub(crate) fn variable(&'static self, node_idx: usize) -> f64ad {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { f64ad::f64ad_var_1(f64ad_var_1::new(node_idx, c.borrow().tape().borrow().generation(), self)) }
+            ComputationGraph::ComputationGraphF(c) => { f64ad::f64ad_var_f(f64ad_var_f::new(node_idx, c.borrow().tape().borrow().generation(), self)) }
+            ComputationGraph::ComputationGraphT(c) => { f64ad::f64ad_var_t(f64ad_var_t::new(node_idx, c.borrow().tape().borrow().generation(), self)) }
+            ComputationGraph::ComputationGraphL(c) => { f64ad::f64ad_var_l(f64ad_var_l::new(node_idx, c.borrow().tape().borrow().generation(), self)) }
+        }
+    }
+    #[inline(always)]
+    /// Returns the node parents, node type class, and node operands mode
+    pub(crate) fn get_node_bundle(&'static self, node_idx: usize) -> ([Option<f64ad>; 2], NodeTypeClass, NodeOperandsMode) {
+        let bundle = |tape: &Tape| (tape.parents(node_idx, self), tape.node_type_class(node_idx), tape.node_operands_mode(node_idx));
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { bundle(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphF(c) => { bundle(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphT(c) => { bundle(&c.borrow().tape().borrow()) }
+            ComputationGraph::ComputationGraphL(c) => { bundle(&c.borrow().tape().borrow()) }
+        }
     }
     #[inline(always)]
     pub fn num_nodes(&self) -> usize {
@@ -272,6 +381,15 @@ impl ComputationGraph {
             ComputationGraph::ComputationGraphL(c) => { c.borrow().num_nodes() }
         }
     }
+    /// Number of bytes used by the nodes on this graph.
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow().size_in_bytes() }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow().tape().borrow().size_in_bytes() }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow().tape().borrow().size_in_bytes() }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow().tape().borrow().size_in_bytes() }
+        }
+    }
     #[inline(always)]
     pub(crate) fn map_to_type(&self) -> ComputationGraphType {
         match self {
@@ -282,6 +400,63 @@ impl ComputationGraph {
         }
     }
     #[inline(always)]
+    fn with_non_finite_tracker<R, F: FnOnce(&NonFiniteTracker) -> R>(&self, f: F) -> R {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { f(&c.borrow().non_finite_tracker) }
+            ComputationGraph::ComputationGraphF(c) => { f(&c.borrow().non_finite_tracker) }
+            ComputationGraph::ComputationGraphT(c) => { f(&c.borrow().non_finite_tracker) }
+            ComputationGraph::ComputationGraphL(c) => { f(&c.borrow().non_finite_tracker) }
+        }
+    }
+    #[inline(always)]
+    pub(crate) fn with_sweep_timings<R, F: FnOnce(&SweepTimings) -> R>(&self, f: F) -> R {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { f(&c.borrow().sweep_timings) }
+            ComputationGraph::ComputationGraphF(c) => { f(&c.borrow().sweep_timings) }
+            ComputationGraph::ComputationGraphT(c) => { f(&c.borrow().sweep_timings) }
+            ComputationGraph::ComputationGraphL(c) => { f(&c.borrow().sweep_timings) }
+        }
+    }
+    #[inline(always)]
+    pub(crate) fn debug_mode(&self) -> bool {
+        self.with_non_finite_tracker(|x| x.enabled)
+    }
+    pub(crate) fn set_debug_mode(&self, enabled: bool) {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow_mut().non_finite_tracker.enabled = enabled; }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow_mut().non_finite_tracker.enabled = enabled; }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow_mut().non_finite_tracker.enabled = enabled; }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow_mut().non_finite_tracker.enabled = enabled; }
+        }
+    }
+    pub(crate) fn has_non_finite_record(&self) -> bool {
+        self.with_non_finite_tracker(|x| x.has_record())
+    }
+    pub(crate) fn record_non_finite(&self, record: NonFiniteRecord) {
+        self.with_non_finite_tracker(|x| x.record(record))
+    }
+    pub(crate) fn first_non_finite_node(&self) -> Option<NonFiniteRecord> {
+        self.with_non_finite_tracker(|x| x.first())
+    }
+    #[inline(always)]
+    pub(crate) fn derivative_policy(&self) -> DerivativePolicy {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow().derivative_policy() }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow().derivative_policy() }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow().derivative_policy() }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow().derivative_policy() }
+        }
+    }
+    pub(crate) fn set_derivative_policy(&self, derivative_policy: DerivativePolicy) {
+        derivative_policy.assert_valid();
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow_mut().derivative_policy = derivative_policy; }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow_mut().derivative_policy = derivative_policy; }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow_mut().derivative_policy = derivative_policy; }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow_mut().derivative_policy = derivative_policy; }
+        }
+    }
+    #[inline(always)]
     pub (crate) fn computation_graph_id(&self) -> usize {
         match self {
             ComputationGraph::ComputationGraph1(c) => { c.borrow().computation_graph_id() }
@@ -293,29 +468,110 @@ impl ComputationGraph {
     pub (crate) fn reset(&self) {
         match self {
             ComputationGraph::ComputationGraph1(c) => { c.borrow_mut().soft_reset(); }
-            ComputationGraph::ComputationGraphF(c) => { c.borrow_mut().soft_reset(); }
+            ComputationGraph::ComputationGraphF(c) => {
+                c.borrow_mut().soft_reset();
+                // variables of the inner level may refer to nodes of this graph, so it is reset as well.
+                let inner_level = c.borrow().inner_level;
+                if let Some(inner_level) = inner_level { inner_level.reset(); }
+            }
             ComputationGraph::ComputationGraphT(c) => { c.borrow_mut().reset()  }
             ComputationGraph::ComputationGraphL(c) => { c.borrow_mut().reset() }
         }
     }
+    pub(crate) fn mark(&self) -> TapeMark {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow().mark() }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow().mark() }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow().mark() }
+            ComputationGraph::ComputationGraphL(_) => { panic!("cannot mark a locked computation graph.") }
+        }
+    }
+    pub(crate) fn rewind_to(&self, mark: &TapeMark) {
+        // every rewind bumps the tape generation; once there are no generations left, the graph is
+        // reset instead, which starts the generations over.
+        if !self.with_tape(|tape| tape.can_rewind()) {
+            self.reset();
+            return;
+        }
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow().rewind_to(mark); }
+            ComputationGraph::ComputationGraphF(c) => {
+                c.borrow().rewind_to(mark);
+                // variables of the inner level may refer to removed nodes of this graph.
+                let inner_level = c.borrow().inner_level;
+                if let Some(inner_level) = inner_level { inner_level.reset(); }
+            }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow().rewind_to(mark); }
+            ComputationGraph::ComputationGraphL(_) => { panic!("cannot rewind a locked computation graph.") }
+        }
+    }
+    /// Returns the inner level of this graph, creating it on first use.  Only supported on
+    /// `ComputationGraphF`.
+    pub (crate) fn inner_level(&'static self) -> &'static ComputationGraph {
+        match self {
+            ComputationGraph::ComputationGraphF(c) => {
+                let inner_level = c.borrow().inner_level;
+                match inner_level {
+                    Some(inner_level) => { inner_level }
+                    None => {
+                        let mut computation_graph = ComputationGraphF::new();
+                        computation_graph.derivative_policy = c.borrow().derivative_policy;
+                        computation_graph.nested_level = Some(NestedLevel::new(self));
+                        let inner_level: &'static ComputationGraph = Box::leak(Box::new(ComputationGraph::ComputationGraphF(RefCell::new(computation_graph))));
+                        c.borrow_mut().inner_level = Some(inner_level);
+                        inner_level
+                    }
+                }
+            }
+            _ => { panic!("nested levels are only supported on ComputationGraphF.") }
+        }
+    }
+    #[inline(always)]
+    pub (crate) fn outer_level(&self) -> Option<&'static ComputationGraph> {
+        match self {
+            ComputationGraph::ComputationGraphF(c) => { c.borrow().nested_level.as_ref().map(|x| x.outer) }
+            _ => { None }
+        }
+    }
+    pub (crate) fn nesting_depth(&self) -> usize {
+        match self.outer_level() {
+            None => { 0 }
+            Some(outer) => { outer.nesting_depth() + 1 }
+        }
+    }
+    pub (crate) fn with_nested_level<R, F: FnOnce(&NestedLevel) -> R>(&self, f: F) -> R {
+        match self {
+            ComputationGraph::ComputationGraphF(c) => { f(c.borrow().nested_level.as_ref().expect("computation graph is not nested.")) }
+            _ => { panic!("computation graph is not nested.") }
+        }
+    }
+    #[inline(always)]
+    pub (crate) fn paused(&self) -> bool {
+        match self {
+            ComputationGraph::ComputationGraph1(c) => { c.borrow().paused() }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow().paused() }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow().paused() }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow().paused() }
+        }
+    }
+    /// Pauses are nested, i.e., the graph stays paused until `unpause` has been called once for every
+    /// call to `pause`.
     #[inline(always)]
-    #[allow(dead_code)]
     pub (crate) fn pause(&self) {
         match self {
-            ComputationGraph::ComputationGraph1(c) => {
-                c.borrow_mut().paused = true;
-            }
-            _ => { panic!("can only pause a ComputationGraph1") }
+            ComputationGraph::ComputationGraph1(c) => { c.borrow_mut().pause_depth += 1; }
+            ComputationGraph::ComputationGraphF(c) => { c.borrow_mut().pause_depth += 1; }
+            ComputationGraph::ComputationGraphT(c) => { c.borrow_mut().pause_depth += 1; }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow_mut().pause_depth += 1; }
         }
     }
     #[inline(always)]
-    #[allow(dead_code)]
     pub (crate) fn unpause(&self) {
         match self {
-            ComputationGraph::ComputationGraph1(c) => {
-                c.borrow_mut().paused = false;
-            }
-            _ => { panic!("can only pause a ComputationGraph1") }
+            ComputationGraph::ComputationGraph1(c) => { let mut b = c.borrow_mut(); b.pause_depth = b.pause_depth.saturating_sub(1); }
+            ComputationGraph::ComputationGraphF(c) => { let mut b = c.borrow_mut(); b.pause_depth = b.pause_depth.saturating_sub(1); }
+            ComputationGraph::ComputationGraphT(c) => { let mut b = c.borrow_mut(); b.pause_depth = b.pause_depth.saturating_sub(1); }
+            ComputationGraph::ComputationGraphL(c) => { let mut b = c.borrow_mut(); b.pause_depth = b.pause_depth.saturating_sub(1); }
         }
     }
 }
@@ -331,14 +587,33 @@ pub enum ComputationGraphType {
     ComputationGraphL
 }
 
+/// Returned by `GlobalComputationGraph::no_grad`.  Unpauses the graph when dropped.
+#[must_use = "the graph is unpaused as soon as the guard is dropped"]
+pub struct NoGradGuard {
+    computation_graph: &'static ComputationGraph
+}
+impl Drop for NoGradGuard {
+    fn drop(&mut self) {
+        self.computation_graph.unpause();
+    }
+}
+
 #[derive(Clone)]
 pub struct GlobalComputationGraph(*const ComputationGraph);
 impl GlobalComputationGraph {
     #[inline(always)]
+    #[cfg_attr(feature = "track_caller", track_caller)]
     pub fn spawn_variable(&self, value: f64) -> f64ad {
         return unsafe { (*self.0).spawn_variable(value) };
     }
+    /// Spawns the real and imaginary parts of `value` as two variables.
+    #[inline(always)]
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn spawn_complex_variable(&self, value: Complex<f64>) -> c64ad {
+        c64ad::new(self.spawn_variable(value.re), self.spawn_variable(value.im))
+    }
     #[inline(always)]
+    #[cfg_attr(feature = "track_caller", track_caller)]
     pub fn add_node(&self, value: f64, node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, parent_0: Option<f64ad>, parent_1: Option<f64ad>) -> f64ad {
         return unsafe { (*self.0).add_node(value, node_type_class, node_operands_mode, parent_0, parent_1) };
     }
@@ -362,101 +637,214 @@ impl GlobalComputationGraph {
         let c = self.computation_graph();
         match c {
             ComputationGraph::ComputationGraphT(c) => {
-                let binding0 = c.borrow();
-                let binding1 = binding0.computation_graph().borrow();
-
-                let mut rng = thread_rng();
-                let id: usize = rng.gen();
-
-                let locked_computation_graph = ComputationGraphL {
-                    computation_graph_id: id,
-                    locked_nodes: RefCell::new(vec![]),
-                    count: RefCell::new(0)
+                // the borrow of the tracer must end before the insert, which can move the graphs in
+                // the global map.
+                let locked_computation_graph = {
+                    let binding0 = c.borrow();
+                    let tape = binding0.tape().borrow().clone();
+                    ComputationGraphL::new(binding0.derivative_policy(), tape, binding0.outputs())
                 };
-
-                for node in binding1.iter() {
-                    locked_computation_graph.locked_nodes.borrow_mut().push(F64ADNodeL {
-                        node_idx: node.node_idx(),
-                        node_type_class: node.node_type_class(),
-                        node_operands_mode: node.node_operands_mode(),
-                        value: node.value(),
-                        parent_0: node.parent_0().clone(),
-                        parent_1: node.parent_1().clone()
-                    });
-                }
-
-                let computation_graph = ComputationGraph::ComputationGraphL(RefCell::new(locked_computation_graph));
-
-                let hashmap = unsafe { _GLOBAL_COMPUTATION_GRAPHS.get_or_init(|| Mutex::new(HashMap::new())) };
-
-                let name = match name {
-            None => { "".to_string() }
-            Some(name) => { name.to_string() }
-        };
-                let idx = match idx {
-            None => { 0 }
-            Some(idx) => { idx }
-        };
-
-                let mut binding = hashmap.lock().unwrap();
-
-                binding.insert((name, idx, ComputationGraphType::ComputationGraphL), computation_graph);
+                GlobalComputationGraphs::insert_locked(name, idx, locked_computation_graph);
             }
             _ => { panic!("can only lock a tracer graph") }
         }
     }
-    pub fn reset(&self) {
-        return unsafe { (*self.0).reset() };
+    /// Like `lock`, but first runs the given optimization passes on the traced graph, relative to
+    /// the outputs marked with `set_outputs`.  The resulting locked graph should be used with
+    /// `evaluate`, since it no longer matches the traced code node for node.
+    pub fn lock_optimized(&self, name: Option<&str>, idx: Option<usize>, optimization_passes: &OptimizationPasses) {
+        let c = self.computation_graph();
+        match c {
+            ComputationGraph::ComputationGraphT(c) => {
+                let locked_computation_graph = {
+                    let binding0 = c.borrow();
+                    let outputs = binding0.outputs();
+                    assert!(!outputs.is_empty(), "the outputs of the graph must be marked with `set_outputs` before it is optimized.");
+                    let (tape, outputs) = optimize(&binding0.tape().borrow(), &outputs, optimization_passes);
+                    ComputationGraphL::new(binding0.derivative_policy(), tape, outputs)
+                };
+                GlobalComputationGraphs::insert_locked(name, idx, locked_computation_graph);
+            }
+            _ => { panic!("can only lock a tracer graph") }
+        }
     }
-}
-
-pub struct GenericComputationGraph<T> {
-    nodes: Vec<T>,
-    curr_idx: usize,
-    curr_len: usize,
-}
-impl<T> GenericComputationGraph<T> {
-    pub fn new() -> Self {
-        Self {
-            nodes: Vec::with_capacity(1_000_000),
-            curr_idx: 0,
-            curr_len: 0,
+    /// Marks the outputs of the computation traced on this graph, so they are available after the
+    /// graph is locked, saved, or loaded.  Only supported on tracer graphs.
+    pub fn set_outputs(&self, outputs: &[f64ad]) {
+        match self.computation_graph() {
+            ComputationGraph::ComputationGraphT(c) => {
+                let outputs = outputs.iter().map(|x| {
+                    assert_eq!(x.computation_graph().computation_graph_id(), self.computation_graph_id(), "outputs must be variables of this computation graph.");
+                    x.node_idx()
+                }).collect();
+                c.borrow().set_outputs(outputs);
+            }
+            _ => { panic!("outputs can only be set on a tracer graph.") }
         }
     }
-    #[inline(always)]
-    pub fn push(&mut self, item: T) {
-        if self.curr_idx == self.curr_len {
-            self.nodes.push(item);
-            self.curr_len += 1;
-        } else {
-            self.nodes[self.curr_idx] = item;
+    /// The input variables of this graph, in the order they were spawned.
+    pub fn inputs(&self) -> Vec<f64ad> {
+        let computation_graph = self.computation_graph();
+        let input_node_idxs: Vec<usize> = computation_graph.with_tape(|tape| (0..tape.len()).filter(|x| tape.node_type_class(*x) == NodeTypeClass::InputVariable).collect());
+        input_node_idxs.iter().map(|x| computation_graph.variable(*x)).collect()
+    }
+    /// The outputs marked with `set_outputs`.  Only supported on tracer and locked graphs.
+    pub fn outputs(&self) -> Vec<f64ad> {
+        let computation_graph = self.computation_graph();
+        let output_node_idxs = match computation_graph {
+            ComputationGraph::ComputationGraphT(c) => { c.borrow().outputs() }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow().outputs().clone() }
+            _ => { panic!("outputs are only available on tracer and locked graphs.") }
+        };
+        output_node_idxs.iter().map(|x| computation_graph.variable(*x)).collect()
+    }
+    /// Recomputes all nodes of this locked graph from new values of its inputs (in the order they
+    /// were spawned) and returns its outputs, which can then be differentiated as usual.  Unlike
+    /// replaying the computation on the locked graph, this does not require the code that was
+    /// traced.
+    pub fn evaluate(&self, inputs: &[f64]) -> Vec<f64ad> {
+        match self.computation_graph() {
+            ComputationGraph::ComputationGraphL(c) => { c.borrow().evaluate(inputs); }
+            _ => { panic!("only locked graphs can be evaluated.") }
+        }
+        self.outputs()
+    }
+    /// Evaluates this locked graph, and optionally the gradients of its outputs, at many input points
+    /// without changing the values on the graph.  `inputs` holds one row of input values per point,
+    /// in the order the inputs were spawned.  If `parallel` is true, the points are split across
+    /// threads.
+    pub fn evaluate_batch(&self, inputs: &[f64], gradients: bool, parallel: bool) -> BatchOutput {
+        match self.computation_graph() {
+            ComputationGraph::ComputationGraphL(c) => {
+                let outputs = c.borrow().outputs().clone();
+                evaluate_batch(self.computation_graph(), outputs, inputs, gradients, parallel)
+            }
+            _ => { panic!("only locked graphs can be evaluated.") }
         }
-
-        self.curr_idx += 1;
     }
-    pub fn reset(&mut self) {
-        if self.curr_len > 10_000_000 {
-            self.nodes = Vec::with_capacity(1_000_000);
-            self.curr_idx = 0;
-            self.curr_len = 0;
-        } else {
-            self.curr_idx = 0;
+    /// Evaluates this locked graph on a box of inputs, given as one interval per input (in the order
+    /// the inputs were spawned), and returns guaranteed enclosures of its outputs and, if `gradients`
+    /// is true, of their gradients over the whole box.  See `interval_mod` for the details.
+    pub fn evaluate_interval(&self, inputs: &[Interval], gradients: bool) -> IntervalOutput {
+        match self.computation_graph() {
+            ComputationGraph::ComputationGraphL(c) => {
+                let outputs = c.borrow().outputs().clone();
+                evaluate_interval(self.computation_graph(), outputs, inputs, gradients)
+            }
+            _ => { panic!("only locked graphs can be evaluated.") }
         }
     }
-    #[inline(always)]
-    pub fn curr_idx(&self) -> usize {
-        self.curr_idx
+    /// Returns this graph in a versioned format that can be serialized with serde.  Only supported
+    /// on tracer and locked graphs.
+    pub fn save(&self) -> SavedComputationGraph {
+        SavedComputationGraph::new(self.computation_graph())
+    }
+    /// Returns standalone Rust source for `pub fn {function_name}(x: &[f64]) -> Vec<f64>`, which
+    /// computes the marked outputs from the inputs (in the order they were spawned), and
+    /// `pub fn {function_name}_jacobian(x: &[f64]) -> Vec<Vec<f64>>`.  The generated code does not
+    /// depend on f64ad.  Only supported on tracer and locked graphs.
+    pub fn to_rust(&self, function_name: &str) -> String {
+        let computation_graph = self.computation_graph();
+        let output_node_idxs = match computation_graph {
+            ComputationGraph::ComputationGraphT(c) => { c.borrow().outputs() }
+            ComputationGraph::ComputationGraphL(c) => { c.borrow().outputs().clone() }
+            _ => { panic!("code can only be generated from tracer and locked graphs.") }
+        };
+        codegen::to_rust(computation_graph, function_name, &output_node_idxs)
     }
-    #[inline(always)]
-    pub fn item(&self, idx: usize) -> &T {
-        assert!(idx < self.curr_idx, "idx: {}, self_idx: {}", idx, self.curr_idx);
-        return &self.nodes[idx];
+    pub fn reset(&self) {
+        return unsafe { (*self.0).reset() };
+    }
+    pub fn num_nodes(&self) -> usize {
+        self.computation_graph().num_nodes()
+    }
+    /// Returns the current position on this graph's tape.  Nodes added before the mark can be kept
+    /// across iterations of, e.g., an optimization loop by calling `rewind_to` instead of `reset`.
+    pub fn mark(&self) -> TapeMark {
+        self.computation_graph().mark()
+    }
+    /// Removes all nodes that were added after `mark`.  Variables that referred to removed nodes
+    /// are invalidated and panic when used, while variables from before the mark remain valid.
+    /// Panics if the graph was reset, or rewound to an earlier position, since `mark` was made.
+    ///
+    /// Each rewind uses up one of `u32::MAX` tape generations.  When they run out, the whole graph
+    /// is reset instead (and `mark` becomes invalid).
+    pub fn rewind_to(&self, mark: &TapeMark) {
+        self.computation_graph().rewind_to(mark);
+    }
+    /// Number of bytes used by the nodes currently on this graph.
+    pub fn size_in_bytes(&self) -> usize {
+        self.computation_graph().size_in_bytes()
+    }
+    /// Node counts, memory use, depth, and the duration of the last derivative sweeps of this graph.
+    pub fn stats(&self) -> GraphStats {
+        GraphStats::new(self.computation_graph())
+    }
+    /// Sets how derivatives are computed at non-differentiable points (e.g., `abs` at 0, ties in
+    /// `max`/`min`, and rounding functions) for all subsequent derivative sweeps on this graph.
+    pub fn set_derivative_policy(&self, derivative_policy: DerivativePolicy) {
+        self.computation_graph().set_derivative_policy(derivative_policy);
+    }
+    pub fn derivative_policy(&self) -> DerivativePolicy {
+        self.computation_graph().derivative_policy()
+    }
+    /// Returns the inner level of this graph (created on first use).  Variables of this graph can
+    /// be used in computations on the inner level, where derivatives treat them as constants, and
+    /// results can be brought back to this graph with `f64ad::to_outer_level`.  This allows, e.g.,
+    /// differentiating through an inner gradient descent step.  The inner level is reset whenever
+    /// this graph is reset.  Only supported on `ComputationGraphF`.
+    pub fn nested(&self) -> GlobalComputationGraph {
+        GlobalComputationGraph(self.computation_graph().inner_level())
+    }
+    /// Moves `v` from this nested graph to its outer level by replaying the computation that
+    /// produced it on the outer graph.  Variables that were lifted from the outer level map back to
+    /// the original outer variables, while inputs spawned on this graph become constants.  If `v` is
+    /// already on an outer level, it is returned unchanged.
+    pub fn to_outer_level(&self, v: f64ad) -> f64ad {
+        to_outer_level(self.computation_graph(), v)
+    }
+    /// Returns a guard that pauses this graph until it is dropped.  While paused, operations on
+    /// variables of this graph produce plain `f64ad::f64` constants and nothing is added to the graph.
+    /// Guards can be nested.
+    pub fn no_grad(&self) -> NoGradGuard {
+        let computation_graph = self.computation_graph();
+        computation_graph.pause();
+        NoGradGuard { computation_graph }
+    }
+    /// When debug mode is on, the value and local partial derivatives of every node added to this
+    /// graph, as well as every step of its derivative sweeps, are checked for NaN or infinite values.
+    /// The first offending node can then be queried with `first_non_finite_node`.  Compile with the
+    /// `track_caller` feature to also record the call site that created the node.
+    pub fn set_debug_mode(&self, enabled: bool) {
+        self.computation_graph().set_debug_mode(enabled);
+    }
+    pub fn debug_mode(&self) -> bool {
+        self.computation_graph().debug_mode()
+    }
+    /// Returns the first node that produced a NaN or infinite value since the last reset, if any.
+    /// Only tracked while debug mode is on.
+    pub fn first_non_finite_node(&self) -> Option<NonFiniteRecord> {
+        self.computation_graph().first_non_finite_node()
+    }
+    /// Returns all nodes of this graph in Graphviz DOT format, labeled with their node idx, node
+    /// type class, and value.  If `adjoints` is given, nodes are also labeled with their adjoint
+    /// from that backwards mode sweep.
+    pub fn to_dot(&self, adjoints: Option<&BackwardsModeGradOutput>) -> String {
+        to_dot(self.computation_graph(), None, adjoints)
+    }
+    /// Same as `to_dot`, but only includes the nodes that `output` depends on.
+    pub fn to_dot_from(&self, output: &f64ad, adjoints: Option<&BackwardsModeGradOutput>) -> String {
+        let node_idx = resolve_node_idx(self.computation_graph(), output).expect("output is not on this computation graph.");
+        to_dot(self.computation_graph(), Some(node_idx), adjoints)
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
-static mut _GLOBAL_COMPUTATION_GRAPHS: OnceCell<Mutex<HashMap<(String, usize, ComputationGraphType), ComputationGraph>>> = OnceCell::new();
+// the graphs are boxed so that the pointers held by `GlobalComputationGraph`s stay valid when the
+// map grows.
+type GlobalComputationGraphMap = Mutex<HashMap<(String, usize, ComputationGraphType), Box<ComputationGraph>>>;
+static mut _GLOBAL_COMPUTATION_GRAPHS: OnceCell<GlobalComputationGraphMap> = OnceCell::new();
 
 pub struct GlobalComputationGraphs;
 impl GlobalComputationGraphs {
@@ -468,8 +856,49 @@ impl GlobalComputationGraphs {
     pub fn get(name: Option<&str>, idx: Option<usize>) -> GlobalComputationGraph {
         return Self::get_internal(name, idx, ComputationGraphType::ComputationGraphF);
     }
+    /// Same as `get`, but returns a `ComputationGraph1`.  This graph only supports first order
+    /// derivatives, but records the local partial derivatives of every node as it is added, making
+    /// derivative sweeps faster.
+    pub fn get_first_order(name: Option<&str>, idx: Option<usize>) -> GlobalComputationGraph {
+        Self::get_internal(name, idx, ComputationGraphType::ComputationGraph1)
+    }
+    /// Returns a tracer graph.  Computations traced on it can be locked with
+    /// `GlobalComputationGraph::lock` or saved with `GlobalComputationGraph::save`.
+    pub fn get_tracer(name: Option<&str>, idx: Option<usize>) -> GlobalComputationGraph {
+        Self::get_internal(name, idx, ComputationGraphType::ComputationGraphT)
+    }
+    /// Returns a locked graph that was created with `GlobalComputationGraph::lock` or `load_locked`.
+    pub fn get_locked(name: Option<&str>, idx: Option<usize>) -> GlobalComputationGraph {
+        let hashmap = Self::global_computation_graphs();
+        let key = (name.unwrap_or("").to_string(), idx.unwrap_or(0), ComputationGraphType::ComputationGraphL);
+
+        let binding = hashmap.lock().unwrap();
+        match binding.get(&key) {
+            None => { panic!("no locked graph with name {:?} and idx {}.", key.0, key.1) }
+            Some(computation_graph) => {
+                let r: *const ComputationGraph = &**computation_graph;
+                GlobalComputationGraph(r)
+            }
+        }
+    }
+    /// Loads a saved graph as a locked graph, which can then be accessed with `get_locked`.  Returns
+    /// an error, without inserting anything, if the saved graph is malformed or from another version.
+    pub fn load_locked(name: Option<&str>, idx: Option<usize>, saved_computation_graph: &SavedComputationGraph) -> Result<GlobalComputationGraph, LoadError> {
+        Self::insert_locked(name, idx, saved_computation_graph.to_locked()?);
+        Ok(Self::get_locked(name, idx))
+    }
+    fn insert_locked(name: Option<&str>, idx: Option<usize>, locked_computation_graph: ComputationGraphL) {
+        let hashmap = Self::global_computation_graphs();
+        let key = (name.unwrap_or("").to_string(), idx.unwrap_or(0), ComputationGraphType::ComputationGraphL);
+
+        let mut binding = hashmap.lock().unwrap();
+        binding.insert(key, Box::new(ComputationGraph::ComputationGraphL(RefCell::new(locked_computation_graph))));
+    }
+    fn global_computation_graphs() -> &'static GlobalComputationGraphMap {
+        unsafe { _GLOBAL_COMPUTATION_GRAPHS.get_or_init(|| Mutex::new(HashMap::new())) }
+    }
     fn get_internal(name: Option<&str>, idx: Option<usize>, computation_graph_type: ComputationGraphType) -> GlobalComputationGraph {
-        let hashmap = unsafe { _GLOBAL_COMPUTATION_GRAPHS.get_or_init(|| Mutex::new(HashMap::new())) };
+        let hashmap = Self::global_computation_graphs();
 
         let name = match name {
             None => { "".to_string() }
@@ -485,12 +914,12 @@ impl GlobalComputationGraphs {
         let res = binding.get(&(name.clone(), idx, computation_graph_type));
         return match res {
             None => {
-                binding.insert((name.clone(), idx, computation_graph_type), ComputationGraph::new(computation_graph_type));
+                binding.insert((name.clone(), idx, computation_graph_type), Box::new(ComputationGraph::new(computation_graph_type)));
                 drop(binding);
                 Self::get_internal(Some(&name), Some(idx), computation_graph_type)
             }
             Some(computation_graph) => {
-                let r: *const ComputationGraph = computation_graph;
+                let r: *const ComputationGraph = &**computation_graph;
                 return GlobalComputationGraph(r);
             }
         };
@@ -500,15 +929,47 @@ impl GlobalComputationGraphs {
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
 fn f64ad_universal_backwards_mode_grad(v: f64ad, add_to_computation_graph: bool) -> BackwardsModeGradOutput {
-    let l = v.computation_graph().num_nodes();
-    let mut derivs = vec![f64ad::f64(0.0); l];
-    derivs[v.node_idx()] = f64ad::f64(1.0);
+    let start = Instant::now();
+    let out = f64ad_universal_backwards_mode_grad_untimed(v, add_to_computation_graph);
+    v.computation_graph().with_sweep_timings(|x| x.backwards.set(Some(start.elapsed())));
+    out
+}
 
+fn f64ad_universal_backwards_mode_grad_untimed(v: f64ad, add_to_computation_graph: bool) -> BackwardsModeGradOutput {
     let computation_graph = v.computation_graph();
+    let generation = computation_graph.with_tape(|tape| tape.generation());
+    let derivative_policy = computation_graph.derivative_policy();
+    let debug_mode = computation_graph.debug_mode();
+
+    if !add_to_computation_graph && !debug_mode {
+        let derivs = match computation_graph {
+            ComputationGraph::ComputationGraph1(c) => { Some(c.borrow().backwards_mode_grad(v.node_idx())) }
+            ComputationGraph::ComputationGraphF(c) => { Some(c.borrow().backwards_mode_grad(v.node_idx())) }
+            ComputationGraph::ComputationGraphL(c) => { Some(c.borrow().backwards_mode_grad(v.node_idx())) }
+            ComputationGraph::ComputationGraphT(_) => { None }
+        };
+        if let Some(derivs) = derivs {
+            let derivs = derivs.iter().map(|x| f64ad::f64(*x)).collect();
+            return BackwardsModeGradOutput { derivs, computation_graph, generation };
+        }
+    }
+
+    let l = computation_graph.num_nodes();
+    let mut derivs = vec![f64ad::f64(0.0); l];
+    derivs[v.node_idx()] = f64ad::f64(1.0);
     'l: for node_idx in (0..l).rev() {
         let (parents, node_type_class, operands_mode) = computation_graph.get_node_bundle(node_idx);
         if node_type_class == NodeTypeClass::InputVariable { continue 'l; }
-        let derivatives = compute_derivatives(parents[0].unwrap(), parents[1], node_type_class, operands_mode, add_to_computation_graph);
+        if let NodeTypeClass::Composite { composite_idx, output_idx } = node_type_class {
+            // all outputs of a composite are handled at once, when its first output is reached.
+            if output_idx == 0 { composite_backwards_mode_step(computation_graph, composite_idx, &mut derivs, add_to_computation_graph); }
+            continue 'l;
+        }
+        let derivatives = compute_derivatives(parents[0].unwrap(), parents[1], node_type_class, operands_mode, add_to_computation_graph, &derivative_policy);
+        if debug_mode {
+            let seeds = vec![derivs[node_idx]; derivatives.len()];
+            check_sweep_step(computation_graph, node_idx, node_type_class, &parents, &derivatives, &seeds, NonFiniteStage::BackwardsSweep);
+        }
         match operands_mode {
             NodeOperandsMode::TwoParents => {
                 let parent0 = parents[0].unwrap();
@@ -531,19 +992,51 @@ fn f64ad_universal_backwards_mode_grad(v: f64ad, add_to_computation_graph: bool)
         }
     }
 
-    return BackwardsModeGradOutput { derivs };
+    return BackwardsModeGradOutput { derivs, computation_graph, generation };
 }
 
 fn f64ad_universal_forward_mode_grad(v: f64ad, add_to_computation_graph: bool) -> ForwardModeGradOutput {
-    let l = v.computation_graph().num_nodes();
-    let mut derivs = vec![f64ad::f64(0.0); l];
-    derivs[v.node_idx()] = f64ad::f64(1.0);
+    let start = Instant::now();
+    let out = f64ad_universal_forward_mode_grad_untimed(v, add_to_computation_graph);
+    v.computation_graph().with_sweep_timings(|x| x.forward.set(Some(start.elapsed())));
+    out
+}
 
+fn f64ad_universal_forward_mode_grad_untimed(v: f64ad, add_to_computation_graph: bool) -> ForwardModeGradOutput {
     let computation_graph = v.computation_graph();
+    let generation = computation_graph.with_tape(|tape| tape.generation());
+    let derivative_policy = computation_graph.derivative_policy();
+    let debug_mode = computation_graph.debug_mode();
+
+    if !add_to_computation_graph && !debug_mode {
+        let derivs = match computation_graph {
+            ComputationGraph::ComputationGraph1(c) => { Some(c.borrow().forward_mode_grad(v.node_idx())) }
+            ComputationGraph::ComputationGraphF(c) => { Some(c.borrow().forward_mode_grad(v.node_idx())) }
+            ComputationGraph::ComputationGraphL(c) => { Some(c.borrow().forward_mode_grad(v.node_idx())) }
+            ComputationGraph::ComputationGraphT(_) => { None }
+        };
+        if let Some(derivs) = derivs {
+            let derivs = derivs.iter().map(|x| f64ad::f64(*x)).collect();
+            return ForwardModeGradOutput { derivs, computation_graph, generation };
+        }
+    }
+
+    let l = computation_graph.num_nodes();
+    let mut derivs = vec![f64ad::f64(0.0); l];
+    derivs[v.node_idx()] = f64ad::f64(1.0);
     'l: for node_idx in 0..l {
         let (parents, node_type_class, operands_mode) = computation_graph.get_node_bundle(node_idx);
         if node_type_class == NodeTypeClass::InputVariable { continue 'l; }
-        let derivatives = compute_derivatives(parents[0].unwrap(), parents[1], node_type_class, operands_mode, add_to_computation_graph);
+        if let NodeTypeClass::Composite { composite_idx, output_idx } = node_type_class {
+            // all outputs of a composite are handled at once, when its first output is reached.
+            if output_idx == 0 { composite_forward_mode_step(computation_graph, composite_idx, &mut derivs, add_to_computation_graph); }
+            continue 'l;
+        }
+        let derivatives = compute_derivatives(parents[0].unwrap(), parents[1], node_type_class, operands_mode, add_to_computation_graph, &derivative_policy);
+        if debug_mode {
+            let seeds: Vec<f64ad> = parents.iter().flatten().filter(|x| x.map_to_type() != F64adType::F64).map(|x| derivs[x.node_idx()]).collect();
+            check_sweep_step(computation_graph, node_idx, node_type_class, &parents, &derivatives, &seeds, NonFiniteStage::ForwardSweep);
+        }
         match operands_mode {
             NodeOperandsMode::TwoParents => {
                 let parent0 = parents[0].unwrap();
@@ -567,36 +1060,72 @@ fn f64ad_universal_forward_mode_grad(v: f64ad, add_to_computation_graph: bool) -
         }
     }
 
-    return ForwardModeGradOutput { derivs };
+    return ForwardModeGradOutput { derivs, computation_graph, generation };
 }
 
 fn convert_to_f64_if_not_add_to_computation_graph(v: f64ad, add_to_computation_graph: bool) -> f64ad {
     return if !add_to_computation_graph { f64ad::f64(v.value()) } else { v };
 }
 
-#[derive(Clone, Debug)]
+/// Panics if `v` is not a node that existed when derivatives with `num_derivs` entries were computed
+/// on `computation_graph` in `generation`, e.g., because the graph was rewound since.
+fn assert_derivs_cover(computation_graph: &ComputationGraph, generation: u32, num_derivs: usize, v: &f64ad) {
+    v.assert_valid();
+    if v.map_to_type() == F64adType::F64 || v.computation_graph().computation_graph_id() != computation_graph.computation_graph_id() { return; }
+    assert!(v.generation() <= generation && v.node_idx() < num_derivs, "variable was created after these derivatives were computed.");
+}
+
+#[derive(Clone)]
 pub struct ForwardModeGradOutput {
     derivs: Vec<f64ad>,
+    computation_graph: &'static ComputationGraph,
+    /// Generation of the tape when the derivatives were computed.
+    generation: u32
 }
 impl ForwardModeGradOutput {
+    /// `output` may also be a variable of an outer level; its derivative is zero unless it was used
+    /// on this level.
     pub fn wrt(&self, output: &f64ad) -> f64ad {
-        return self.derivs[output.node_idx() as usize];
+        assert_derivs_cover(self.computation_graph, self.generation, self.derivs.len(), output);
+        match resolve_node_idx(self.computation_graph, output) {
+            None => { f64ad::f64(0.0) }
+            Some(node_idx) => { self.derivs[node_idx] }
+        }
+    }
+}
+impl Debug for ForwardModeGradOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ForwardModeGradOutput").field("derivs", &self.derivs).finish()
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct BackwardsModeGradOutput {
     derivs: Vec<f64ad>,
+    computation_graph: &'static ComputationGraph,
+    /// Generation of the tape when the derivatives were computed.
+    generation: u32
 }
 impl BackwardsModeGradOutput {
+    /// `input` may also be a variable of an outer level; its derivative is zero unless it was used
+    /// on this level.
     pub fn wrt(&self, input: &f64ad) -> f64ad {
-        return self.derivs[input.node_idx() as usize];
+        assert_derivs_cover(self.computation_graph, self.generation, self.derivs.len(), input);
+        match resolve_node_idx(self.computation_graph, input) {
+            None => { f64ad::f64(0.0) }
+            Some(node_idx) => { self.derivs[node_idx] }
+        }
+    }
+}
+impl Debug for BackwardsModeGradOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackwardsModeGradOutput").field("derivs", &self.derivs).finish()
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Clone, Debug, Copy, PartialEq)]
+#[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
 pub enum NodeTypeClass {
     InputVariable,
     Add,
@@ -627,13 +1156,17 @@ pub enum NodeTypeClass {
     Acosh,
     Atanh,
     Log,
+    /// Natural logarithm.  Only produced by graph optimization, from `Log` nodes with base `E`.
+    Ln,
     Sqrt,
     Exp,
     Powf,
-    Manual { value: f64, derivative: f64 }
+    Manual { value: f64, derivative: f64 },
+    /// Output `output_idx` of a multi-input, multi-output composite operation (e.g., a checkpoint region).
+    Composite { composite_idx: usize, output_idx: usize }
 }
 
-#[derive(Clone, Debug, Copy, PartialEq)]
+#[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
 pub enum NodeOperandsMode {
     TwoParents,
     OneParentLHS,
@@ -641,7 +1174,53 @@ pub enum NodeOperandsMode {
     NoParents,
 }
 
+/// Specifies which derivative is used at points where a function is not differentiable.  Only the
+/// derivatives are affected; values are always computed exactly.
+#[derive(Clone, Debug, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct DerivativePolicy {
+    /// Used for `Abs`, `Max`, and `Min`.
+    pub kinks: KinkPolicy,
+    /// Used for `Floor`, `Ceil`, `Round`, `Trunc`, and `Signum`.
+    pub rounding: RoundingPolicy
+}
+impl DerivativePolicy {
+    pub fn new(kinks: KinkPolicy, rounding: RoundingPolicy) -> Self {
+        let out = Self { kinks, rounding };
+        out.assert_valid();
+        out
+    }
+    fn assert_valid(&self) {
+        if let KinkPolicy::Smooth { temperature } = self.kinks {
+            assert!(temperature > 0.0, "smooth kink policy temperature must be positive, got {}", temperature);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum KinkPolicy {
+    /// `abs` has derivative +1 at 0 and ties in `max`/`min` send the whole derivative to the lhs.
+    #[default]
+    OneSided,
+    /// The midpoint of the subdifferential: `abs` has derivative 0 at 0 and ties in `max`/`min` are
+    /// split evenly between both operands.
+    Midpoint,
+    /// Derivatives of the softplus (log-sum-exp) smoothing with the given temperature are used
+    /// everywhere, i.e., `max(a, b) ~ b + t*softplus((a - b)/t)` and `abs(x) ~ t*ln(2*cosh(x/t))`.
+    /// Smaller temperatures are closer to the exact derivatives.
+    Smooth { temperature: f64 }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum RoundingPolicy {
+    /// The exact derivative almost everywhere, i.e., 0.
+    #[default]
+    Zero,
+    /// The straight-through estimator, i.e., rounding is treated as the identity in derivatives.
+    StraightThrough
+}
+
 #[inline(always)]
+#[cfg_attr(feature = "track_caller", track_caller)]
 pub (crate) fn f64ad_universal_function(lhs: f64ad, rhs: Option<f64ad>, node_type_class: NodeTypeClass) -> f64ad {
     let t = lhs.map_to_type();
     if t == F64adType::VarL {
@@ -655,6 +1234,7 @@ pub (crate) fn f64ad_universal_function(lhs: f64ad, rhs: Option<f64ad>, node_typ
 }
 
 #[inline(always)]
+#[cfg_attr(feature = "track_caller", track_caller)]
 fn f64ad_universal_function_2_operands(lhs: f64ad, rhs: f64ad, node_type_class: NodeTypeClass) -> f64ad {
     let t0 = lhs.map_to_type();
     let t1 = rhs.map_to_type();
@@ -670,7 +1250,10 @@ fn f64ad_universal_function_2_operands(lhs: f64ad, rhs: f64ad, node_type_class:
         NodeOperandsMode::OneParentLHS
     } else {
         assert_eq!(t0, t1);
-        assert_eq!(lhs.computation_graph().computation_graph_id(), rhs.computation_graph().computation_graph_id());
+        if lhs.computation_graph().computation_graph_id() != rhs.computation_graph().computation_graph_id() {
+            let (lhs, rhs) = lift_to_common_level(lhs, rhs);
+            return compute_value_f64ad(lhs, Some(rhs), node_type_class, NodeOperandsMode::TwoParents);
+        }
 
         NodeOperandsMode::TwoParents
     };
@@ -679,6 +1262,7 @@ fn f64ad_universal_function_2_operands(lhs: f64ad, rhs: f64ad, node_type_class:
 }
 
 #[inline(always)]
+#[cfg_attr(feature = "track_caller", track_caller)]
 fn f64ad_universal_function_1_operand(lhs: f64ad, node_type_class: NodeTypeClass) -> f64ad {
     let t0 = lhs.map_to_type();
 
@@ -737,14 +1321,17 @@ fn compute_value_f64(lhs: f64ad, rhs: Option<f64ad>, node_type_class: NodeTypeCl
         NodeTypeClass::Acosh => { lhs.value().acosh() }
         NodeTypeClass::Atanh => { lhs.value().atanh() }
         NodeTypeClass::Log => { lhs.value().log(rhs.unwrap().value()) }
+        NodeTypeClass::Ln => { lhs.value().ln() }
         NodeTypeClass::Sqrt => { lhs.value().sqrt() }
         NodeTypeClass::Exp => { lhs.value().exp() }
         NodeTypeClass::Powf => { lhs.value().powf(rhs.unwrap().value()) }
         NodeTypeClass::Manual { value, .. } => { value }
+        NodeTypeClass::Composite { .. } => { panic!("composite output cannot compute value.") }
     }
 }
 
 #[inline(always)]
+#[cfg_attr(feature = "track_caller", track_caller)]
 fn compute_value_f64ad(lhs: f64ad, rhs: Option<f64ad>, node_type_class: NodeTypeClass, operands_mode: NodeOperandsMode) -> f64ad {
     let value = compute_value_f64(lhs, rhs, node_type_class);
     return match operands_mode {
@@ -764,363 +1351,14 @@ fn compute_value_f64ad(lhs: f64ad, rhs: Option<f64ad>, node_type_class: NodeType
 }
 
 #[inline(always)]
-fn compute_derivatives(lhs: f64ad, rhs: Option<f64ad>, node_type_class: NodeTypeClass, operands_mode: NodeOperandsMode, add_to_computation_graph: bool) -> TinyVec<[f64ad; 2]> {
+fn compute_derivatives(lhs: f64ad, rhs: Option<f64ad>, node_type_class: NodeTypeClass, operands_mode: NodeOperandsMode, add_to_computation_graph: bool, derivative_policy: &DerivativePolicy) -> TinyVec<[f64ad; 2]> {
     let lhs = convert_to_f64_if_not_add_to_computation_graph(lhs, add_to_computation_graph);
     let rhs = match rhs {
         None => { None }
         Some(rhs) => { Some(convert_to_f64_if_not_add_to_computation_graph(rhs, add_to_computation_graph)) }
     };
 
-    match node_type_class {
-        NodeTypeClass::InputVariable => { tiny_vec!([f64ad; 2]) }
-        NodeTypeClass::Add => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { tiny_vec!([f64ad; 2] => f64ad::f64(1.0), f64ad::f64(1.0)) }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(1.0)) }
-                NodeOperandsMode::OneParentRHS => { tiny_vec!([f64ad; 2] => f64ad::f64(1.0)) }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Mul => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { tiny_vec!([f64ad; 2] => rhs.unwrap(), lhs) }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => rhs.unwrap()) }
-                NodeOperandsMode::OneParentRHS => { tiny_vec!([f64ad; 2] => lhs) }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Sub => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { tiny_vec!([f64ad; 2] => f64ad::f64(1.0), f64ad::f64(-1.0)) }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(1.0)) }
-                NodeOperandsMode::OneParentRHS => { tiny_vec!([f64ad; 2] => f64ad::f64(-1.0)) }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Div => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => 1.0/rhs, -lhs / (rhs*rhs))
-                }
-                NodeOperandsMode::OneParentLHS => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => 1.0/rhs)
-                }
-                NodeOperandsMode::OneParentRHS => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => -lhs / (rhs*rhs))
-                }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Neg => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(-1.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Abs => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => {
-                    let val = lhs.value();
-                    if val >= 0.0 { return tiny_vec!([f64ad; 2] => f64ad::f64(1.0)); } else { return tiny_vec!([f64ad; 2] => f64ad::f64(-1.0)); }
-                }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Signum => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(0.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Max => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => {
-                    if lhs >= rhs.unwrap() {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(1.0), f64ad::f64(0.0))
-                    } else {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(0.0), f64ad::f64(1.0))
-                    }
-                }
-                NodeOperandsMode::OneParentLHS => {
-                    if lhs >= rhs.unwrap() {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(1.0))
-                    } else {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(0.0))
-                    }
-                }
-                NodeOperandsMode::OneParentRHS => {
-                    if lhs >= rhs.unwrap() {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(0.0))
-                    } else {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(1.0))
-                    }
-                }
-                NodeOperandsMode::NoParents => {
-                    tiny_vec!([f64ad; 2])
-                }
-            }
-        }
-        NodeTypeClass::Min => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => {
-                    if lhs <= rhs.unwrap() {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(1.0), f64ad::f64(0.0))
-                    } else {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(0.0), f64ad::f64(1.0))
-                    }
-                }
-                NodeOperandsMode::OneParentLHS => {
-                    if lhs <= rhs.unwrap() {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(1.0))
-                    } else {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(0.0))
-                    }
-                }
-                NodeOperandsMode::OneParentRHS => {
-                    if lhs <= rhs.unwrap() {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(0.0))
-                    } else {
-                        tiny_vec!([f64ad; 2] => f64ad::f64(1.0))
-                    }
-                }
-                NodeOperandsMode::NoParents => {
-                    tiny_vec!([f64ad; 2])
-                }
-            }
-        }
-        NodeTypeClass::Atan2 => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => rhs/(lhs*lhs + rhs*rhs), -lhs/(lhs*lhs + rhs*rhs))
-                }
-                NodeOperandsMode::OneParentLHS => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => rhs/(lhs*lhs + rhs*rhs))
-                }
-                NodeOperandsMode::OneParentRHS => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => -lhs/(lhs*lhs + rhs*rhs))
-                }
-                NodeOperandsMode::NoParents => {
-                    tiny_vec!([f64ad; 2])
-                }
-            }
-        }
-        NodeTypeClass::Floor => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(0.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Ceil => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(0.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Round => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(0.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Trunc => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(0.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Fract => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => f64ad::f64(0.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Sin => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => lhs.cos()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Cos => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => -lhs.sin()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Tan => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => {
-                    let c = lhs.cos();
-                    tiny_vec!([f64ad; 2] => 1.0/(c*c))
-                }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Asin => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => 1.0 / (1.0 - lhs * lhs).sqrt()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Acos => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => -1.0/(1.0 - lhs * lhs).sqrt()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Atan => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => 1.0/(lhs*lhs + 1.0)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Sinh => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => lhs.cosh()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Cosh => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => lhs.sinh()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Tanh => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => {
-                    let c = lhs.cosh();
-                    tiny_vec!([f64ad; 2] => 1.0 / (c*c))
-                }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Asinh => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => 1.0/(lhs*lhs + 1.0).sqrt()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Acosh => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => 1.0/((lhs - 1.0).sqrt()*(lhs + 1.0).sqrt()) ) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Atanh => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => 1.0/(1.0 - lhs*lhs)) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Log => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => {
-                    let rhs = rhs.unwrap();
-                    let ln_rhs = rhs.ln();
-                    let ln_lhs = lhs.ln();
-                    tiny_vec!([f64ad; 2] => 1.0/(lhs * ln_rhs), -ln_lhs / (rhs * ln_rhs * ln_rhs))
-                }
-                NodeOperandsMode::OneParentLHS => {
-                    let rhs = rhs.unwrap();
-                    let ln_rhs = rhs.ln();
-                    tiny_vec!([f64ad; 2] => 1.0/(lhs * ln_rhs))
-                }
-                NodeOperandsMode::OneParentRHS => {
-                    let rhs = rhs.unwrap();
-                    let ln_rhs = rhs.ln();
-                    let ln_lhs = lhs.ln();
-                    tiny_vec!([f64ad; 2] => -ln_lhs / (rhs * ln_rhs * ln_rhs))
-                }
-                NodeOperandsMode::NoParents => {
-                    tiny_vec!([f64ad; 2])
-                }
-            }
-        }
-        NodeTypeClass::Sqrt => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => 1.0/(2.0*lhs.sqrt())) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Exp => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => { unreachable!() }
-                NodeOperandsMode::OneParentLHS => { tiny_vec!([f64ad; 2] => lhs.exp()) }
-                NodeOperandsMode::OneParentRHS => { unreachable!() }
-                NodeOperandsMode::NoParents => { tiny_vec!([f64ad; 2]) }
-            }
-        }
-        NodeTypeClass::Powf => {
-            match operands_mode {
-                NodeOperandsMode::TwoParents => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => rhs * lhs.powf(rhs - 1.0), lhs.powf(rhs) * lhs.ln())
-                }
-                NodeOperandsMode::OneParentLHS => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => rhs * lhs.powf(rhs - 1.0))
-                }
-                NodeOperandsMode::OneParentRHS => {
-                    let rhs = rhs.unwrap();
-                    tiny_vec!([f64ad; 2] => lhs.powf(rhs) * lhs.ln())
-                }
-                NodeOperandsMode::NoParents => {
-                    tiny_vec!([f64ad; 2])
-                }
-            }
-        }
-        NodeTypeClass::Manual { derivative, .. } => {
-            tiny_vec!([f64ad; 2] => f64ad::f64(derivative))
-        }
-    }
+    derivatives(lhs, rhs, node_type_class, operands_mode, derivative_policy)
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -1316,6 +1554,7 @@ pub fn manual_derivative_function<I: Debug, O: Debug, F0, F1, F2>(
 impl Add<f64ad> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn add(self, rhs: f64ad) -> Self::Output {
         f64ad_universal_function(self, Some(rhs), NodeTypeClass::Add)
     }
@@ -1323,6 +1562,7 @@ impl Add<f64ad> for f64ad {
 impl Add<f64> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn add(self, rhs: f64) -> Self::Output {
         return self + f64ad::f64(rhs);
     }
@@ -1330,21 +1570,25 @@ impl Add<f64> for f64ad {
 impl Add<f64ad> for f64 {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn add(self, rhs: f64ad) -> Self::Output {
         return f64ad::f64(self) + rhs;
     }
 }
 impl AddAssign for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn add_assign(&mut self, rhs: Self) {
         *self = *self + rhs;
     }
 }
 impl AddAssign<f64> for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn add_assign(&mut self, rhs: f64) {
         *self = *self + rhs;
     }
 }
 impl AddAssign<f64ad> for f64 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn add_assign(&mut self, rhs: f64ad) {
         *self += rhs.value();
     }
@@ -1353,6 +1597,7 @@ impl AddAssign<f64ad> for f64 {
 impl Mul<f64ad> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn mul(self, rhs: f64ad) -> Self::Output {
         f64ad_universal_function(self, Some(rhs), NodeTypeClass::Mul)
     }
@@ -1360,6 +1605,7 @@ impl Mul<f64ad> for f64ad {
 impl Mul<f64> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn mul(self, rhs: f64) -> Self::Output {
         return self * f64ad::f64(rhs);
     }
@@ -1367,24 +1613,28 @@ impl Mul<f64> for f64ad {
 impl Mul<f64ad> for f64 {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn mul(self, rhs: f64ad) -> Self::Output {
         return f64ad::f64(self) * rhs;
     }
 }
 impl MulAssign for f64ad {
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn mul_assign(&mut self, rhs: Self) {
         *self = *self * rhs;
     }
 }
 impl MulAssign<f64> for f64ad {
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn mul_assign(&mut self, rhs: f64) {
         *self = *self * rhs;
     }
 }
 impl MulAssign<f64ad> for f64 {
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn mul_assign(&mut self, rhs: f64ad) {
         *self *= rhs.value();
     }
@@ -1393,6 +1643,7 @@ impl MulAssign<f64ad> for f64 {
 impl Sub<f64ad> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sub(self, rhs: f64ad) -> Self::Output {
         f64ad_universal_function(self, Some(rhs), NodeTypeClass::Sub)
     }
@@ -1400,6 +1651,7 @@ impl Sub<f64ad> for f64ad {
 impl Sub<f64> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sub(self, rhs: f64) -> Self::Output {
         return self - f64ad::f64(rhs);
     }
@@ -1407,21 +1659,25 @@ impl Sub<f64> for f64ad {
 impl Sub<f64ad> for f64 {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sub(self, rhs: f64ad) -> Self::Output {
         return f64ad::f64(self) - rhs;
     }
 }
 impl SubAssign for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sub_assign(&mut self, rhs: Self) {
         *self = *self - rhs;
     }
 }
 impl SubAssign<f64> for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sub_assign(&mut self, rhs: f64) {
         *self = *self - rhs;
     }
 }
 impl SubAssign<f64ad> for f64 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sub_assign(&mut self, rhs: f64ad) {
         *self -= rhs.value();
     }
@@ -1430,6 +1686,7 @@ impl SubAssign<f64ad> for f64 {
 impl Div<f64ad> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn div(self, rhs: f64ad) -> Self::Output {
         f64ad_universal_function(self, Some(rhs), NodeTypeClass::Div)
     }
@@ -1437,6 +1694,7 @@ impl Div<f64ad> for f64ad {
 impl Div<f64> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn div(self, rhs: f64) -> Self::Output {
         return self / f64ad::f64(rhs);
     }
@@ -1444,23 +1702,27 @@ impl Div<f64> for f64ad {
 impl Div<f64ad> for f64 {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn div(self, rhs: f64ad) -> Self::Output {
         return f64ad::f64(self) / rhs;
     }
 }
 impl DivAssign for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn div_assign(&mut self, rhs: Self) {
         *self = *self / rhs;
     }
 }
 impl DivAssign<f64> for f64ad {
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn div_assign(&mut self, rhs: f64) {
         *self = *self / rhs;
     }
 }
 impl DivAssign<f64ad> for f64 {
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn div_assign(&mut self, rhs: f64ad) {
         *self /= rhs.value();
     }
@@ -1469,6 +1731,7 @@ impl DivAssign<f64ad> for f64 {
 impl Rem<f64ad> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn rem(self, rhs: f64ad) -> Self::Output {
         self - (self / rhs).floor() * rhs
     }
@@ -1476,6 +1739,7 @@ impl Rem<f64ad> for f64ad {
 impl Rem<f64> for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn rem(self, rhs: f64) -> Self::Output {
         self % f64ad::f64(rhs)
     }
@@ -1483,21 +1747,25 @@ impl Rem<f64> for f64ad {
 impl Rem<f64ad> for f64 {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn rem(self, rhs: f64ad) -> Self::Output {
         f64ad::f64(self) % rhs
     }
 }
 impl RemAssign for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn rem_assign(&mut self, rhs: Self) {
         *self = *self % rhs;
     }
 }
 impl RemAssign<f64> for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn rem_assign(&mut self, rhs: f64) {
         *self = *self % rhs;
     }
 }
 impl RemAssign<f64ad> for f64 {
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn rem_assign(&mut self, rhs: f64ad) {
         *self %= rhs.value();
     }
@@ -1506,6 +1774,7 @@ impl RemAssign<f64ad> for f64 {
 impl Neg for f64ad {
     type Output = f64ad;
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn neg(self) -> Self::Output {
         f64ad_universal_function(self, None, NodeTypeClass::Neg)
     }
@@ -0,0 +1,167 @@
+// Nested differentiation levels: a graph returned by `GlobalComputationGraph::nested` is an inner
+// level of the graph it was created from.  Variables of an outer level can be freely mixed with
+// variables of an inner level; they are lifted into the inner level as separate input nodes, so
+// derivatives taken on the inner level treat them as constants.  Results of the inner level can then
+// be moved back to the outer level with `GlobalComputationGraph::to_outer_level`, which replays the
+// inner computation (including any derivative nodes added with `add_to_computation_graph = true`)
+// on the outer graph.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use crate::f64ad::{ComputationGraph, f64ad, f64ad_universal_function, F64adType, NodeTypeClass};
+
+pub(crate) struct NestedLevel {
+    pub(crate) outer: &'static ComputationGraph,
+    /// Maps the node idx of an outer level variable to its lifted copy on this level.
+    lifted: RefCell<HashMap<usize, f64ad>>,
+    /// Maps the node idx of a node on this level to its counterpart on the outer level.
+    lowered: RefCell<HashMap<usize, f64ad>>
+}
+impl NestedLevel {
+    pub(crate) fn new(outer: &'static ComputationGraph) -> Self {
+        Self {
+            outer,
+            lifted: RefCell::new(HashMap::new()),
+            lowered: RefCell::new(HashMap::new())
+        }
+    }
+    pub(crate) fn clear(&self) {
+        self.lifted.borrow_mut().clear();
+        self.lowered.borrow_mut().clear();
+    }
+    /// Forgets the lifted and lowered copies of nodes on this level that were removed by a rewind.
+    pub(crate) fn truncate(&self, num_nodes: usize) {
+        self.lifted.borrow_mut().retain(|_, v| v.node_idx() < num_nodes);
+        self.lowered.borrow_mut().retain(|k, _| *k < num_nodes);
+    }
+}
+
+/// Lifts the operand that lives on the outer level so that both operands are on the same graph.
+pub(crate) fn lift_to_common_level(lhs: f64ad, rhs: f64ad) -> (f64ad, f64ad) {
+    let lhs_computation_graph = lhs.computation_graph();
+    let rhs_computation_graph = rhs.computation_graph();
+    if lhs_computation_graph.nesting_depth() >= rhs_computation_graph.nesting_depth() {
+        (lhs, lift(lhs_computation_graph, rhs))
+    } else {
+        (lift(rhs_computation_graph, lhs), rhs)
+    }
+}
+
+/// Returns the copy of `v` on `computation_graph`, where `v` is either already on
+/// `computation_graph` or on one of its outer levels.
+pub(crate) fn lift(computation_graph: &'static ComputationGraph, v: f64ad) -> f64ad {
+    if v.map_to_type() == F64adType::F64 { return v; }
+    if v.computation_graph().computation_graph_id() == computation_graph.computation_graph_id() { return v; }
+
+    let outer = match computation_graph.outer_level() {
+        None => { panic!("cannot mix variables from different computation graphs unless one graph is nested in the other.") }
+        Some(outer) => { outer }
+    };
+    let v = lift(outer, v);
+
+    let existing = computation_graph.with_nested_level(|x| x.lifted.borrow().get(&v.node_idx()).cloned());
+    if let Some(lifted) = existing { return lifted; }
+
+    let lifted = computation_graph.spawn_variable(v.value());
+    computation_graph.with_nested_level(|x| {
+        x.lifted.borrow_mut().insert(v.node_idx(), lifted);
+        x.lowered.borrow_mut().insert(lifted.node_idx(), v);
+    });
+    lifted
+}
+
+/// Returns the node idx on `computation_graph` that corresponds to `v`, or `None` if `v` is on an
+/// outer level and was never used on `computation_graph`.
+pub(crate) fn resolve_node_idx(computation_graph: &ComputationGraph, v: &f64ad) -> Option<usize> {
+    if v.computation_graph().computation_graph_id() == computation_graph.computation_graph_id() { return Some(v.node_idx()); }
+
+    let outer = match computation_graph.outer_level() {
+        None => { panic!("variable is not on this computation graph or any of its outer levels.") }
+        Some(outer) => { outer }
+    };
+    let outer_node_idx = resolve_node_idx(outer, v)?;
+    computation_graph.with_nested_level(|x| x.lifted.borrow().get(&outer_node_idx).map(|y| y.node_idx()))
+}
+
+pub(crate) fn to_outer_level(computation_graph: &'static ComputationGraph, v: f64ad) -> f64ad {
+    if v.map_to_type() == F64adType::F64 { return v; }
+    let outer = match computation_graph.outer_level() {
+        None => { panic!("to_outer_level can only be called on a nested computation graph.") }
+        Some(outer) => { outer }
+    };
+    if v.computation_graph().computation_graph_id() != computation_graph.computation_graph_id() {
+        assert!(is_outer_level(computation_graph, v.computation_graph()), "variable is not on this computation graph or any of its outer levels.");
+        return v;
+    }
+
+    // collect all nodes that v depends on that are not yet on the outer level.
+    let mut needed = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = vec![v.node_idx()];
+    while let Some(node_idx) = stack.pop() {
+        if !visited.insert(node_idx) { continue; }
+        if computation_graph.with_nested_level(|x| x.lowered.borrow().contains_key(&node_idx)) { continue; }
+        needed.push(node_idx);
+
+        let (parents, node_type_class, _) = computation_graph.get_node_bundle(node_idx);
+        match node_type_class {
+            NodeTypeClass::Composite { composite_idx, .. } => {
+                stack.extend(computation_graph.get_composite(composite_idx).input_node_idxs.iter().flatten());
+            }
+            _ => {
+                stack.extend(parents.iter().flatten().filter(|x| x.map_to_type() != F64adType::F64).map(|x| x.node_idx()));
+            }
+        }
+    }
+
+    // parents always have a smaller node idx than their children.
+    needed.sort_unstable();
+    for node_idx in needed {
+        lower_node(computation_graph, outer, node_idx);
+    }
+
+    computation_graph.with_nested_level(|x| x.lowered.borrow()[&v.node_idx()])
+}
+
+fn is_outer_level(computation_graph: &ComputationGraph, other: &ComputationGraph) -> bool {
+    match computation_graph.outer_level() {
+        None => { false }
+        Some(outer) => { outer.computation_graph_id() == other.computation_graph_id() || is_outer_level(outer, other) }
+    }
+}
+
+fn lower_node(computation_graph: &'static ComputationGraph, outer: &'static ComputationGraph, node_idx: usize) {
+    let lowered = |x: &usize| computation_graph.with_nested_level(|y| y.lowered.borrow()[x]);
+
+    let (parents, node_type_class, _) = computation_graph.get_node_bundle(node_idx);
+    match node_type_class {
+        NodeTypeClass::InputVariable => {
+            // inputs of an inner level that were not lifted from the outer level do not depend on it.
+            let out = f64ad::f64(computation_graph.get_node_value(node_idx));
+            computation_graph.with_nested_level(|x| x.lowered.borrow_mut().insert(node_idx, out));
+        }
+        NodeTypeClass::Composite { composite_idx, .. } => {
+            let record = computation_graph.get_composite(composite_idx);
+            if computation_graph.with_nested_level(|x| x.lowered.borrow().contains_key(&record.first_output_node_idx)) { return; }
+
+            let inputs: Vec<f64ad> = record.input_node_idxs.iter().zip(record.input_values.iter()).map(|(x, y)| {
+                match x {
+                    None => { f64ad::f64(*y) }
+                    Some(x) => { lowered(x) }
+                }
+            }).collect();
+            let outputs = outer.add_composite(&inputs, record.operation.clone());
+            computation_graph.with_nested_level(|x| {
+                let mut binding = x.lowered.borrow_mut();
+                for (i, output) in outputs.iter().enumerate() {
+                    binding.insert(record.first_output_node_idx + i, *output);
+                }
+            });
+        }
+        _ => {
+            let map_parent = |x: Option<f64ad>| x.map(|y| if y.map_to_type() == F64adType::F64 { y } else { lowered(&y.node_idx()) });
+            let out = f64ad_universal_function(map_parent(parents[0]).unwrap(), map_parent(parents[1]), node_type_class);
+            computation_graph.with_nested_level(|x| x.lowered.borrow_mut().insert(node_idx, out));
+        }
+    }
+}
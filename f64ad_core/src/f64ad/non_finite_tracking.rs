@@ -0,0 +1,159 @@
+// Debug mode for tracking down where NaN or infinite values first enter a computation graph.
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::panic::Location;
+use crate::f64ad::{compute_derivatives, ComputationGraph, f64ad, F64adType, NodeOperandsMode, NodeTypeClass};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFiniteStage {
+    /// The value of the node was NaN or infinite when it was added to the graph.
+    NodeValue,
+    /// The value of the node was finite, but one of its local partial derivatives was not.
+    LocalPartial,
+    /// A finite adjoint became NaN or infinite when propagated through the node.
+    BackwardsSweep,
+    /// A finite tangent became NaN or infinite when propagated through the node.
+    ForwardSweep
+}
+
+/// Information about the first node of a graph that produced a NaN or infinite value.
+#[derive(Clone, Debug)]
+pub struct NonFiniteRecord {
+    node_idx: usize,
+    node_type_class: NodeTypeClass,
+    stage: NonFiniteStage,
+    value: f64,
+    operand_values: [Option<f64>; 2],
+    local_partials: Vec<f64>,
+    location: Option<&'static Location<'static>>
+}
+impl NonFiniteRecord {
+    #[inline(always)]
+    pub fn node_idx(&self) -> usize {
+        self.node_idx
+    }
+    #[inline(always)]
+    pub fn node_type_class(&self) -> NodeTypeClass {
+        self.node_type_class
+    }
+    #[inline(always)]
+    pub fn stage(&self) -> NonFiniteStage {
+        self.stage
+    }
+    #[inline(always)]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+    /// Values of the lhs and rhs operands of the node, if present.
+    #[inline(always)]
+    pub fn operand_values(&self) -> [Option<f64>; 2] {
+        self.operand_values
+    }
+    #[inline(always)]
+    pub fn local_partials(&self) -> &Vec<f64> {
+        &self.local_partials
+    }
+    /// The call site that created the node.  Only available when the `track_caller` feature is on.
+    #[inline(always)]
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+impl Display for NonFiniteRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "non-finite {:?} at node {} ({:?}): value: {:?}, operands: {:?}, local partials: {:?}", self.stage, self.node_idx, self.node_type_class, self.value, self.operand_values, self.local_partials)?;
+        if let Some(location) = self.location {
+            write!(f, ", created at {}", location)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct NonFiniteTracker {
+    pub(crate) enabled: bool,
+    first: RefCell<Option<NonFiniteRecord>>
+}
+impl NonFiniteTracker {
+    /// Only the first record is kept, since every later non-finite value usually just propagates it.
+    pub(crate) fn record(&self, record: NonFiniteRecord) {
+        let mut binding = self.first.borrow_mut();
+        if binding.is_none() { *binding = Some(record); }
+    }
+    pub(crate) fn has_record(&self) -> bool {
+        self.first.borrow().is_some()
+    }
+    pub(crate) fn first(&self) -> Option<NonFiniteRecord> {
+        self.first.borrow().clone()
+    }
+    pub(crate) fn clear(&self) {
+        *self.first.borrow_mut() = None;
+    }
+    /// Forgets the record if its node was removed by a rewind.
+    pub(crate) fn truncate(&self, num_nodes: usize) {
+        let mut binding = self.first.borrow_mut();
+        if binding.as_ref().is_some_and(|x| x.node_idx >= num_nodes) { *binding = None; }
+    }
+}
+
+/// Checks the value and local partials of a node that was just added to the graph.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn check_new_node(computation_graph: &ComputationGraph, node: f64ad, value: f64, node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, parent_0: Option<f64ad>, parent_1: Option<f64ad>, location: Option<&'static Location<'static>>) {
+    if node.map_to_type() == F64adType::F64 || computation_graph.has_non_finite_record() { return; }
+
+    let local_partials: Vec<f64> = match (node_type_class, node_operands_mode) {
+        (NodeTypeClass::InputVariable, _) | (NodeTypeClass::Composite { .. }, _) | (_, NodeOperandsMode::NoParents) => { vec![] }
+        _ => {
+            compute_derivatives(parent_0.unwrap(), parent_1, node_type_class, node_operands_mode, false, &computation_graph.derivative_policy()).iter().map(|x| x.value()).collect()
+        }
+    };
+
+    let stage = if !value.is_finite() {
+        NonFiniteStage::NodeValue
+    } else if local_partials.iter().any(|x| !x.is_finite()) {
+        NonFiniteStage::LocalPartial
+    } else {
+        return;
+    };
+
+    computation_graph.record_non_finite(NonFiniteRecord {
+        node_idx: node.node_idx(),
+        node_type_class,
+        stage,
+        value,
+        operand_values: [parent_0.map(|x| x.value()), parent_1.map(|x| x.value())],
+        local_partials,
+        location
+    });
+}
+
+/// Checks one step of a derivative sweep.  `seeds` are the adjoints (backwards mode) or tangents
+/// (forward mode) that are multiplied by the local partials `derivatives` in this step.
+pub(crate) fn check_sweep_step(computation_graph: &ComputationGraph, node_idx: usize, node_type_class: NodeTypeClass, parents: &[Option<f64ad>; 2], derivatives: &[f64ad], seeds: &[f64ad], stage: NonFiniteStage) {
+    if computation_graph.has_non_finite_record() { return; }
+
+    let local_partials: Vec<f64> = derivatives.iter().map(|x| x.value()).collect();
+    let seeds_finite = seeds.iter().all(|x| x.value().is_finite());
+    let products_finite = local_partials.iter().zip(seeds.iter()).all(|(d, s)| (d * s.value()).is_finite());
+    if !seeds_finite || products_finite { return; }
+
+    computation_graph.record_non_finite(NonFiniteRecord {
+        node_idx,
+        node_type_class,
+        stage,
+        value: computation_graph.get_node_value(node_idx),
+        operand_values: [parents[0].map(|x| x.value()), parents[1].map(|x| x.value())],
+        local_partials,
+        location: None
+    });
+}
+
+#[inline(always)]
+#[cfg_attr(feature = "track_caller", track_caller)]
+pub(crate) fn caller_location() -> Option<&'static Location<'static>> {
+    #[cfg(feature = "track_caller")]
+    { Some(Location::caller()) }
+    #[cfg(not(feature = "track_caller"))]
+    { None }
+}
@@ -0,0 +1,363 @@
+// Optimization passes on tracer graphs.  A traced tape is rebuilt into a smaller tape with the same
+// values and derivatives for the marked outputs, which is then locked.  Because the optimized tape
+// no longer matches the traced code node for node, an optimized locked graph is meant to be used
+// with `evaluate` rather than by replaying the traced code on it.
+
+use std::collections::{HashMap, HashSet};
+use crate::f64ad::{f64ad, compute_value_f64, NodeTypeClass};
+use crate::f64ad::tape::{encode_node_type_class, Operand, Tape};
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct OptimizationPasses {
+    /// Replaces nodes whose operands are all constants with their value.
+    pub constant_folding: bool,
+    /// Reuses an earlier node with the same node type class and operands.
+    pub common_subexpression_elimination: bool,
+    /// Removes `x * 1`, `1 * x`, `x / 1`, `x + 0`, `0 + x`, `x - 0`, `x.powf(1)`, and `-(-x)`, and
+    /// turns `x.log(E)` into `x.ln()`.  Note that `-0.0 + 0.0` is `0.0`, so removing `x + 0` can
+    /// change the sign of a zero value.
+    pub algebraic_simplification: bool,
+    /// Removes nodes the outputs do not depend on.  Input variables are always kept.
+    pub dead_node_elimination: bool
+}
+impl Default for OptimizationPasses {
+    fn default() -> Self {
+        Self {
+            constant_folding: true,
+            common_subexpression_elimination: true,
+            algebraic_simplification: true,
+            dead_node_elimination: true
+        }
+    }
+}
+
+/// Returns the optimized tape and the node idxs of `outputs` on it.
+pub(crate) fn optimize(tape: &Tape, outputs: &[usize], passes: &OptimizationPasses) -> (Tape, Vec<usize>) {
+    let mut out = Tape::new();
+    // What each node of `tape` became on `out`: a node, or a constant if it was folded.
+    let mut mapping: Vec<Operand> = Vec::with_capacity(tape.len());
+    let mut subexpressions: HashMap<(u8, [Option<OperandKey>; 2]), usize> = HashMap::new();
+    let is_output: HashSet<usize> = outputs.iter().copied().collect();
+
+    for node_idx in 0..tape.len() {
+        let node_type_class = tape.node_type_class(node_idx);
+        if let NodeTypeClass::Composite { .. } = node_type_class { panic!("graphs with composite nodes cannot be optimized."); }
+        if node_type_class == NodeTypeClass::InputVariable {
+            let new_idx = out.push_operands(tape.value(node_idx), node_type_class, [None, None]);
+            mapping.push(Operand::Variable(new_idx));
+            continue;
+        }
+
+        let operands = tape.operands(node_idx).map(|x| {
+            x.map(|y| {
+                match y {
+                    Operand::Variable(parent) => { mapping[parent] }
+                    Operand::Constant(_) => { y }
+                }
+            })
+        });
+        let all_constant = operands.iter().flatten().all(|x| matches!(x, Operand::Constant(_)));
+
+        if passes.constant_folding && all_constant && !is_output.contains(&node_idx) {
+            let value = match node_type_class {
+                NodeTypeClass::Manual { value, .. } => { value }
+                _ => {
+                    let [lhs, rhs] = operands.map(|x| x.map(|y| f64ad::f64(constant_value(&y))));
+                    compute_value_f64(lhs.unwrap(), rhs, node_type_class)
+                }
+            };
+            mapping.push(Operand::Constant(value));
+            continue;
+        }
+
+        let (node_type_class, operands) = if passes.algebraic_simplification {
+            match simplify(&out, node_type_class, operands) {
+                Simplified::Operand(operand @ Operand::Variable(_)) => {
+                    mapping.push(operand);
+                    continue;
+                }
+                // Only reachable for outputs with constant operands, which are kept as nodes.
+                Simplified::Operand(Operand::Constant(_)) => { (node_type_class, operands) }
+                Simplified::Node(node_type_class, operands) => { (node_type_class, operands) }
+            }
+        } else {
+            (node_type_class, operands)
+        };
+
+        let key = match node_type_class {
+            NodeTypeClass::Manual { .. } => { None }
+            _ if passes.common_subexpression_elimination => {
+                let mut keys = operands.map(|x| x.map(|y| OperandKey::new(&y)));
+                if let NodeTypeClass::Add | NodeTypeClass::Mul = node_type_class {
+                    if keys[1] < keys[0] { keys.swap(0, 1); }
+                }
+                Some((encode_node_type_class(&node_type_class), keys))
+            }
+            _ => { None }
+        };
+        if let Some(existing) = key.as_ref().and_then(|x| subexpressions.get(x)) {
+            mapping.push(Operand::Variable(*existing));
+            continue;
+        }
+
+        let new_idx = out.push_operands(tape.value(node_idx), node_type_class, operands);
+        if let Some(key) = key { subexpressions.insert(key, new_idx); }
+        mapping.push(Operand::Variable(new_idx));
+    }
+
+    let outputs: Vec<usize> = outputs.iter().map(|x| {
+        match mapping[*x] {
+            Operand::Variable(new_idx) => { new_idx }
+            Operand::Constant(_) => { unreachable!() }
+        }
+    }).collect();
+
+    if passes.dead_node_elimination { eliminate_dead_nodes(&out, &outputs) } else { (out, outputs) }
+}
+
+fn eliminate_dead_nodes(tape: &Tape, outputs: &[usize]) -> (Tape, Vec<usize>) {
+    let mut live = vec![false; tape.len()];
+    for output in outputs { live[*output] = true; }
+    for node_idx in (0..tape.len()).rev() {
+        if tape.node_type_class(node_idx) == NodeTypeClass::InputVariable { live[node_idx] = true; }
+        if !live[node_idx] { continue; }
+        for parent in tape.variable_parents(node_idx).iter().flatten() { live[*parent] = true; }
+    }
+
+    let mut out = Tape::new();
+    let mut new_idxs = vec![usize::MAX; tape.len()];
+    for node_idx in (0..tape.len()).filter(|x| live[*x]) {
+        let operands = tape.operands(node_idx).map(|x| {
+            x.map(|y| {
+                match y {
+                    Operand::Variable(parent) => { Operand::Variable(new_idxs[parent]) }
+                    Operand::Constant(_) => { y }
+                }
+            })
+        });
+        new_idxs[node_idx] = out.push_operands(tape.value(node_idx), tape.node_type_class(node_idx), operands);
+    }
+
+    let outputs = outputs.iter().map(|x| new_idxs[*x]).collect();
+    (out, outputs)
+}
+
+enum Simplified {
+    Operand(Operand),
+    Node(NodeTypeClass, [Option<Operand>; 2])
+}
+
+/// Only rewrites that leave the value and derivatives of the node unchanged.
+fn simplify(tape: &Tape, node_type_class: NodeTypeClass, operands: [Option<Operand>; 2]) -> Simplified {
+    let is_constant = |x: &Option<Operand>, c: f64| matches!(x, Some(Operand::Constant(y)) if *y == c);
+    let [lhs, rhs] = operands;
+    match node_type_class {
+        NodeTypeClass::Mul if is_constant(&rhs, 1.0) => { Simplified::Operand(lhs.unwrap()) }
+        NodeTypeClass::Mul if is_constant(&lhs, 1.0) => { Simplified::Operand(rhs.unwrap()) }
+        NodeTypeClass::Div if is_constant(&rhs, 1.0) => { Simplified::Operand(lhs.unwrap()) }
+        NodeTypeClass::Powf if is_constant(&rhs, 1.0) => { Simplified::Operand(lhs.unwrap()) }
+        NodeTypeClass::Add if is_constant(&rhs, 0.0) => { Simplified::Operand(lhs.unwrap()) }
+        NodeTypeClass::Add if is_constant(&lhs, 0.0) => { Simplified::Operand(rhs.unwrap()) }
+        NodeTypeClass::Sub if is_constant(&rhs, 0.0) => { Simplified::Operand(lhs.unwrap()) }
+        NodeTypeClass::Neg => {
+            match lhs {
+                Some(Operand::Variable(parent)) if tape.node_type_class(parent) == NodeTypeClass::Neg => {
+                    Simplified::Operand(tape.operands(parent)[0].unwrap())
+                }
+                _ => { Simplified::Node(node_type_class, operands) }
+            }
+        }
+        NodeTypeClass::Log if is_constant(&rhs, std::f64::consts::E) => { Simplified::Node(NodeTypeClass::Ln, [lhs, None]) }
+        _ => { Simplified::Node(node_type_class, operands) }
+    }
+}
+
+fn constant_value(operand: &Operand) -> f64 {
+    match operand {
+        Operand::Variable(_) => { unreachable!() }
+        Operand::Constant(constant) => { *constant }
+    }
+}
+
+/// Operands compared by node idx or by the bits of the constant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum OperandKey {
+    Variable(usize),
+    Constant(u64)
+}
+impl OperandKey {
+    fn new(operand: &Operand) -> Self {
+        match operand {
+            Operand::Variable(parent) => { OperandKey::Variable(*parent) }
+            Operand::Constant(constant) => { OperandKey::Constant(constant.to_bits()) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::E;
+    use crate::f64ad::{ComputationGraph, DerivativePolicy, f64ad, GlobalComputationGraph, GlobalComputationGraphs, KinkPolicy, NodeTypeClass, RoundingPolicy};
+    use crate::f64ad::f64ad_var_l_mod::ComputationGraphL;
+    use crate::f64ad::tape::{Operand, Tape};
+    use super::{optimize, OptimizationPasses};
+
+    const NONE: OptimizationPasses = OptimizationPasses {
+        constant_folding: false,
+        common_subexpression_elimination: false,
+        algebraic_simplification: false,
+        dead_node_elimination: false
+    };
+    const POINTS: [[f64; 3]; 4] = [[0.5, 1.5, 0.3], [1.2, 0.7, -0.4], [0.3, 2.1, 0.3], [-0.2, 0.4, 0.9]];
+
+    /// Traces `model` at the first of `POINTS` and returns the traced tape and outputs.
+    fn trace(name: &str, model: fn(&[f64ad]) -> Vec<f64ad>) -> (Tape, Vec<usize>) {
+        let tracer = GlobalComputationGraphs::get_tracer(Some(name), None);
+        tracer.reset();
+        let inputs: Vec<f64ad> = POINTS[0].iter().map(|x| tracer.spawn_variable(*x)).collect();
+        tracer.set_outputs(&model(&inputs));
+        match tracer.computation_graph() {
+            ComputationGraph::ComputationGraphT(c) => {
+                let c = c.borrow();
+                let tape = c.tape().borrow().clone();
+                (tape, c.outputs())
+            }
+            _ => { unreachable!() }
+        }
+    }
+
+    /// The values of the outputs, followed by their derivatives with respect to each input from a
+    /// backwards mode sweep per output and from a forward mode sweep per input.
+    fn values_and_derivatives(locked: &GlobalComputationGraph, point: &[f64]) -> Vec<f64> {
+        let outputs = locked.evaluate(point);
+        let inputs = locked.inputs();
+        let mut out: Vec<f64> = outputs.iter().map(|x| x.value()).collect();
+        for output in &outputs {
+            let grad = output.backwards_mode_grad(false);
+            out.extend(inputs.iter().map(|x| grad.wrt(x).value()));
+        }
+        for input in &inputs {
+            let grad = input.forward_mode_grad(false);
+            out.extend(outputs.iter().map(|x| grad.wrt(x).value()));
+        }
+        out
+    }
+
+    /// Locks `tape` as is and optimized with `passes`, checks that both give the same values and
+    /// derivatives at each of `points`, and returns the optimized tape.
+    fn assert_matches_unoptimized(name: &str, tape: &Tape, outputs: &[usize], passes: &OptimizationPasses, points: &[[f64; 3]]) -> Tape {
+        let derivative_policy = DerivativePolicy::new(KinkPolicy::OneSided, RoundingPolicy::Zero);
+        let (optimized_tape, optimized_outputs) = optimize(tape, outputs, passes);
+        GlobalComputationGraphs::insert_locked(Some(name), Some(0), ComputationGraphL::new(derivative_policy, tape.clone(), outputs.to_vec()));
+        GlobalComputationGraphs::insert_locked(Some(name), Some(1), ComputationGraphL::new(derivative_policy, optimized_tape.clone(), optimized_outputs));
+        let unoptimized = GlobalComputationGraphs::get_locked(Some(name), Some(0));
+        let optimized = GlobalComputationGraphs::get_locked(Some(name), Some(1));
+
+        for point in points {
+            let expected = values_and_derivatives(&unoptimized, point);
+            let actual = values_and_derivatives(&optimized, point);
+            assert_eq!(expected.len(), actual.len());
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                assert!((e - a).abs() <= 1e-12 * (1.0 + e.abs()), "the optimized graph gives {} instead of {} at {:?}.", a, e, point);
+            }
+        }
+        optimized_tape
+    }
+
+    fn count(tape: &Tape, node_type_class: NodeTypeClass) -> usize {
+        (0..tape.len()).filter(|x| tape.node_type_class(*x) == node_type_class).count()
+    }
+
+    #[test]
+    fn constant_folding() {
+        // traced code never has nodes with only constant operands, so the tape is built directly.
+        let mut tape = Tape::new();
+        let x: Vec<usize> = POINTS[0].iter().map(|v| tape.push_operands(*v, NodeTypeClass::InputVariable, [None, None])).collect();
+        let c = tape.push_operands(5.0, NodeTypeClass::Add, [Some(Operand::Constant(2.0)), Some(Operand::Constant(3.0))]);
+        let d = tape.push_operands(5.0f64.sin(), NodeTypeClass::Sin, [Some(Operand::Variable(c)), None]);
+        let y = tape.push_operands(0.5 * 5.0, NodeTypeClass::Mul, [Some(Operand::Variable(x[0])), Some(Operand::Variable(c))]);
+        let z = tape.push_operands(2.5 + 5.0f64.sin(), NodeTypeClass::Add, [Some(Operand::Variable(y)), Some(Operand::Variable(d))]);
+        let w = tape.push_operands(5.0 * 0.3, NodeTypeClass::Mul, [Some(Operand::Variable(d)), Some(Operand::Variable(x[2]))]);
+        // an output with only constant operands is kept as a node.
+        let o = tape.push_operands(5.0f64.sqrt(), NodeTypeClass::Sqrt, [Some(Operand::Variable(c)), None]);
+
+        let passes = OptimizationPasses { constant_folding: true, ..NONE };
+        let optimized = assert_matches_unoptimized("optimization_tests_constant_folding", &tape, &[z, w, o], &passes, &POINTS);
+        assert_eq!(optimized.len(), 7);
+        assert_eq!(count(&optimized, NodeTypeClass::Sin), 0);
+        assert_eq!(count(&optimized, NodeTypeClass::Sqrt), 1);
+    }
+
+    #[test]
+    fn common_subexpression_elimination() {
+        let (tape, outputs) = trace("optimization_tests_cse", |x| {
+            vec![x[0].cos() * x[1].cos() + x[0].cos(), x[0] * x[1] + x[1] * x[0], x[2].max(x[0]) - x[2].max(x[0])]
+        });
+        let passes = OptimizationPasses { common_subexpression_elimination: true, ..NONE };
+        let optimized = assert_matches_unoptimized("optimization_tests_cse", &tape, &outputs, &passes, &POINTS);
+        assert_eq!(count(&optimized, NodeTypeClass::Cos), 2);
+        assert_eq!(count(&optimized, NodeTypeClass::Mul), 2);
+        assert_eq!(count(&optimized, NodeTypeClass::Max), 1);
+    }
+
+    #[test]
+    fn algebraic_simplification() {
+        let (tape, outputs) = trace("optimization_tests_algebraic_simplification", |x| {
+            vec![
+                (x[1] * 1.0 + 0.0).ln() - (-(-x[2])).powf(f64ad::f64(1.0)) / 1.0,
+                (1.0 * x[0]) * (0.0 + x[1]) + (x[0] - 0.0),
+                x[1].log(f64ad::f64(E)) * x[2]
+            ]
+        });
+        let passes = OptimizationPasses { algebraic_simplification: true, ..NONE };
+        let optimized = assert_matches_unoptimized("optimization_tests_algebraic_simplification", &tape, &outputs, &passes, &POINTS);
+        // the inner negation is left for dead node elimination.
+        assert_eq!(count(&optimized, NodeTypeClass::Neg), 1);
+        assert_eq!(count(&optimized, NodeTypeClass::Powf), 0);
+        assert_eq!(count(&optimized, NodeTypeClass::Div), 0);
+        assert_eq!(count(&optimized, NodeTypeClass::Sub), 1);
+        assert_eq!(count(&optimized, NodeTypeClass::Log), 0);
+        assert_eq!(count(&optimized, NodeTypeClass::Ln), 2);
+    }
+
+    #[test]
+    fn algebraic_simplification_changes_the_sign_of_zero() {
+        // `-0.0 + 0.0` is `0.0`, while the simplified graph returns `x` itself.
+        let (tape, outputs) = trace("optimization_tests_signed_zero", |x| vec![x[0] + 0.0]);
+        let passes = OptimizationPasses { algebraic_simplification: true, ..NONE };
+        assert_matches_unoptimized("optimization_tests_signed_zero", &tape, &outputs, &passes, &[[-0.0, 0.0, 0.0]]);
+
+        let unoptimized = GlobalComputationGraphs::get_locked(Some("optimization_tests_signed_zero"), Some(0));
+        let optimized = GlobalComputationGraphs::get_locked(Some("optimization_tests_signed_zero"), Some(1));
+        assert!(unoptimized.evaluate(&[-0.0, 0.0, 0.0])[0].value().is_sign_positive());
+        assert!(optimized.evaluate(&[-0.0, 0.0, 0.0])[0].value().is_sign_negative());
+    }
+
+    #[test]
+    fn dead_node_elimination() {
+        let (tape, outputs) = trace("optimization_tests_dce", |x| {
+            let _dead = x[0].sin().exp() * x[2];
+            vec![x[0] * x[1]]
+        });
+        let passes = OptimizationPasses { dead_node_elimination: true, ..NONE };
+        let optimized = assert_matches_unoptimized("optimization_tests_dce", &tape, &outputs, &passes, &POINTS);
+        // input variables are kept even if the outputs do not depend on them.
+        assert_eq!(optimized.len(), 4);
+        assert_eq!(count(&optimized, NodeTypeClass::InputVariable), 3);
+    }
+
+    #[test]
+    fn all_passes() {
+        let (tape, outputs) = trace("optimization_tests_all", |x| {
+            let a = x[0].cos() * x[1].cos() + x[0].cos();
+            let b = (x[1] * 1.0 + 0.0).ln() - (-(-x[2])).powf(f64ad::f64(1.0)) / 1.0;
+            let _dead = x[0].sin().exp() * x[2];
+            let c = (1.0 * x[0]) * (x[1] + x[2]).abs() + x[2].cos() * x[0].cos() + (x[0] - 0.0).max(x[2]) + x[1].log(f64ad::f64(E));
+            vec![a + b, c, x[0].cos(), b]
+        });
+        let optimized = assert_matches_unoptimized("optimization_tests_all", &tape, &outputs, &OptimizationPasses::default(), &POINTS);
+        assert!(optimized.len() < tape.len());
+        assert_eq!(count(&optimized, NodeTypeClass::Sin), 0);
+        assert_eq!(count(&optimized, NodeTypeClass::Log), 0);
+    }
+}
@@ -0,0 +1,275 @@
+// Reductions over many values.  Summing `n` values through `Add` records `n - 1` binary nodes (and
+// a dot product twice that), which dominates the tape of vector-heavy cost functions.  The
+// functions here record each reduction as a single composite operation with one input per value,
+// so the reverse and forward sweeps handle the whole reduction in one step.
+//
+// As with the other composite operations, the recorded reductions only support first order
+// derivatives, so they are only recorded as composites on `ComputationGraph1` graphs.  On all other
+// graphs (`ComputationGraphF`, where higher order derivatives are computed, and `ComputationGraphT`
+// and `ComputationGraphL`, which do not support composite operations) the same reductions are
+// recorded with ordinary binary operations instead, so these functions can be used with any graph.
+
+use std::rc::Rc;
+use crate::f64ad::f64ad;
+use crate::f64ad::composite_operations::{CompositeOperation, record_composite, supports_composites};
+
+/// `xs[0] + xs[1] + ...`.  Zero if `xs` is empty.
+pub fn f64ad_sum(xs: &[f64ad]) -> f64ad {
+    if xs.is_empty() { return f64ad::f64(0.0); }
+    if !supports_composites(xs) { return xs.iter().copied().sum(); }
+    record_composite(xs, Rc::new(Sum))[0]
+}
+
+/// `xs[0] * xs[1] * ...`.  One if `xs` is empty.
+pub fn f64ad_product(xs: &[f64ad]) -> f64ad {
+    if !supports_composites(xs) { return xs.iter().copied().product(); }
+    record_composite(xs, Rc::new(Product))[0]
+}
+
+/// `a[0] * b[0] + a[1] * b[1] + ...`.
+pub fn f64ad_dot(a: &[f64ad], b: &[f64ad]) -> f64ad {
+    assert_eq!(a.len(), b.len(), "cannot take the dot product of slices of different lengths.");
+    if !supports_composites(a) || !supports_composites(b) { return a.iter().zip(b.iter()).map(|(x, y)| *x * *y).sum(); }
+    let inputs: Vec<f64ad> = a.iter().chain(b.iter()).copied().collect();
+    record_composite(&inputs, Rc::new(Dot { n: a.len() }))[0]
+}
+
+/// `xs[0]² + xs[1]² + ...`.
+pub fn f64ad_norm_squared(xs: &[f64ad]) -> f64ad {
+    if !supports_composites(xs) { return xs.iter().map(|x| *x * *x).sum(); }
+    record_composite(xs, Rc::new(NormSquared))[0]
+}
+
+/// `sqrt(xs[0]² + xs[1]² + ...)`.  Like `sqrt`, its derivative is infinite when all of `xs` are zero.
+pub fn f64ad_norm(xs: &[f64ad]) -> f64ad {
+    f64ad_norm_squared(xs).sqrt()
+}
+
+/// `ln(exp(xs[0]) + exp(xs[1]) + ...)`, computed with the largest value factored out so that it
+/// does not overflow.  Panics if `xs` is empty.
+pub fn f64ad_logsumexp(xs: &[f64ad]) -> f64ad {
+    assert!(!xs.is_empty(), "cannot take the logsumexp of an empty slice.");
+    if !supports_composites(xs) {
+        let max = max_value(xs);
+        return xs.iter().map(|x| (*x - max).exp()).sum::<f64ad>().ln() + max;
+    }
+    record_composite(xs, Rc::new(LogSumExp))[0]
+}
+
+/// `exp(xs[i]) / (exp(xs[0]) + exp(xs[1]) + ...)` for each `i`, computed with the largest value
+/// factored out so that it does not overflow.  All outputs are recorded as one composite operation.
+pub fn f64ad_softmax(xs: &[f64ad]) -> Vec<f64ad> {
+    if xs.is_empty() { return vec![]; }
+    if !supports_composites(xs) {
+        let max = max_value(xs);
+        let exps: Vec<f64ad> = xs.iter().map(|x| (*x - max).exp()).collect();
+        let total: f64ad = exps.iter().copied().sum();
+        return exps.iter().map(|x| *x / total).collect();
+    }
+    record_composite(xs, Rc::new(Softmax))
+}
+
+fn max_value(xs: &[f64ad]) -> f64 {
+    xs.iter().map(|x| x.value()).fold(f64::NEG_INFINITY, f64::max)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+struct Sum;
+impl CompositeOperation for Sum {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        vec![input_values.iter().sum()]
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        vec![output_adjoints[0]; input_values.len()]
+    }
+
+    fn forward_mode(&self, _input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        vec![input_tangents.iter().sum()]
+    }
+}
+
+struct Product;
+impl Product {
+    /// `∏_{j != i} x_j` for each `i`, from prefix and suffix products so that zeros in `x` are
+    /// handled without dividing.
+    fn products_of_others(x: &[f64]) -> Vec<f64> {
+        let mut out = vec![1.0; x.len()];
+        let mut prefix = 1.0;
+        for (o, x) in out.iter_mut().zip(x.iter()) {
+            *o = prefix;
+            prefix *= x;
+        }
+        let mut suffix = 1.0;
+        for (o, x) in out.iter_mut().zip(x.iter()).rev() {
+            *o *= suffix;
+            suffix *= x;
+        }
+        out
+    }
+}
+impl CompositeOperation for Product {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        vec![input_values.iter().product()]
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        Self::products_of_others(input_values).iter().map(|p| output_adjoints[0] * p).collect()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        vec![Self::products_of_others(input_values).iter().zip(input_tangents.iter()).map(|(p, t)| p * t).sum()]
+    }
+}
+
+struct Dot {
+    n: usize
+}
+impl CompositeOperation for Dot {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        let (a, b) = input_values.split_at(self.n);
+        vec![a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()]
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        let (a, b) = input_values.split_at(self.n);
+        b.iter().chain(a.iter()).map(|x| output_adjoints[0] * x).collect()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        let (a, b) = input_values.split_at(self.n);
+        let (a_dot, b_dot) = input_tangents.split_at(self.n);
+        vec![(0..self.n).map(|i| a_dot[i] * b[i] + a[i] * b_dot[i]).sum()]
+    }
+}
+
+struct NormSquared;
+impl CompositeOperation for NormSquared {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        vec![input_values.iter().map(|x| x * x).sum()]
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        input_values.iter().map(|x| 2.0 * x * output_adjoints[0]).collect()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        vec![input_values.iter().zip(input_tangents.iter()).map(|(x, t)| 2.0 * x * t).sum()]
+    }
+}
+
+/// The softmax of `x`, which is also the gradient of its logsumexp.
+fn softmax(x: &[f64]) -> Vec<f64> {
+    let max = x.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = x.iter().map(|x| (x - max).exp()).collect();
+    let total: f64 = exps.iter().sum();
+    exps.iter().map(|x| x / total).collect()
+}
+
+struct LogSumExp;
+impl CompositeOperation for LogSumExp {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        let max = input_values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        vec![input_values.iter().map(|x| (x - max).exp()).sum::<f64>().ln() + max]
+    }
+
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        softmax(input_values).iter().map(|s| s * output_adjoints[0]).collect()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        vec![softmax(input_values).iter().zip(input_tangents.iter()).map(|(s, t)| s * t).sum()]
+    }
+}
+
+struct Softmax;
+impl CompositeOperation for Softmax {
+    fn compute_outputs(&self, input_values: &[f64]) -> Vec<f64> {
+        softmax(input_values)
+    }
+
+    // The Jacobian is `diag(s) - s sᵀ`, which is symmetric, so both modes apply it the same way.
+    fn backwards_mode(&self, input_values: &[f64], output_adjoints: &[f64]) -> Vec<f64> {
+        let s = softmax(input_values);
+        let weighted: f64 = s.iter().zip(output_adjoints.iter()).map(|(s, a)| s * a).sum();
+        s.iter().zip(output_adjoints.iter()).map(|(s, a)| s * (a - weighted)).collect()
+    }
+
+    fn forward_mode(&self, input_values: &[f64], input_tangents: &[f64]) -> Vec<f64> {
+        self.backwards_mode(input_values, input_tangents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::f64ad::{f64ad, GlobalComputationGraphs};
+    use super::*;
+
+    /// First and second derivatives of `f(x, y)` with respect to `x` at `(0.3, 0.8)`, from nested
+    /// backwards mode sweeps on a `ComputationGraphF`.
+    fn derivatives_wrt_x(f: fn(f64ad, f64ad) -> f64ad) -> (f64, f64) {
+        let computation_graph = GlobalComputationGraphs::get(Some("reductions_tests"), None);
+        computation_graph.reset();
+        let x = computation_graph.spawn_variable(0.3);
+        let y = computation_graph.spawn_variable(0.8);
+        let d = f(x, y).backwards_mode_grad(true).wrt(&x);
+        let d2 = d.backwards_mode_grad(true).wrt(&x);
+        (d.value(), d2.value())
+    }
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!((actual.0 - expected.0).abs() < 1e-12 && (actual.1 - expected.1).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn second_order_derivatives_on_computation_graph_f() {
+        let (x, y) = (0.3f64, 0.8f64);
+        let s = x.exp() / (x.exp() + y.exp());
+        assert_close(derivatives_wrt_x(|x, y| f64ad_sum(&[x * x, x * y, y])), (2.0 * x + y, 2.0));
+        assert_close(derivatives_wrt_x(|x, y| f64ad_product(&[x, x, y])), (2.0 * x * y, 2.0 * y));
+        assert_close(derivatives_wrt_x(|x, y| f64ad_dot(&[x, y], &[x, y])), (2.0 * x, 2.0));
+        assert_close(derivatives_wrt_x(|x, y| f64ad_norm_squared(&[x, y])), (2.0 * x, 2.0));
+        assert_close(derivatives_wrt_x(|x, y| f64ad_logsumexp(&[x, y])), (s, s * (1.0 - s)));
+        assert_close(derivatives_wrt_x(|x, y| f64ad_softmax(&[x, y])[0]), (s * (1.0 - s), s * (1.0 - s) * (1.0 - 2.0 * s)));
+    }
+
+    /// Checks the Jacobian of `f` at `x` on a first order graph, from backwards and forward mode
+    /// sweeps, against central differences, and that `f` was recorded as a single composite.
+    fn assert_composite_matches_finite_differences(name: &str, f: fn(&[f64ad]) -> Vec<f64ad>, x: &[f64]) {
+        let computation_graph = GlobalComputationGraphs::get_first_order(Some("reductions_tests_first_order"), None);
+        computation_graph.reset();
+        let inputs: Vec<f64ad> = x.iter().map(|x| computation_graph.spawn_variable(*x)).collect();
+        let outputs = f(&inputs);
+        assert_eq!(computation_graph.num_nodes(), inputs.len() + outputs.len(), "{} was not recorded as a single composite.", name);
+
+        let values = |x: &[f64]| -> Vec<f64> { f(&x.iter().map(|x| f64ad::f64(*x)).collect::<Vec<f64ad>>()).iter().map(|x| x.value()).collect() };
+        let h = 1e-6;
+        for (j, input) in inputs.iter().enumerate() {
+            let (mut plus, mut minus) = (x.to_vec(), x.to_vec());
+            plus[j] += h;
+            minus[j] -= h;
+            let (plus, minus) = (values(&plus), values(&minus));
+            let forward = input.forward_mode_grad(false);
+            for (i, output) in outputs.iter().enumerate() {
+                let expected = (plus[i] - minus[i]) / (2.0 * h);
+                for (mode, actual) in [("backwards", output.backwards_mode_grad(false).wrt(input).value()), ("forward", forward.wrt(output).value())] {
+                    assert!((actual - expected).abs() <= 1e-8 * (1.0 + expected.abs()), "{} mode d output {} / d input {} of {} at {:?} is {}, but finite differences give {}.", mode, i, j, name, x, actual, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn composites_on_first_order_graphs() {
+        let x = [0.7, -1.3, 2.1, 0.4];
+        assert_composite_matches_finite_differences("f64ad_sum", |x| vec![f64ad_sum(x)], &x);
+        assert_composite_matches_finite_differences("f64ad_product", |x| vec![f64ad_product(x)], &x);
+        assert_composite_matches_finite_differences("f64ad_product", |x| vec![f64ad_product(x)], &[0.7, 0.0, 2.1, 0.4]);
+        assert_composite_matches_finite_differences("f64ad_product", |x| vec![f64ad_product(x)], &[0.0, -1.3, 0.0, 0.4]);
+        assert_composite_matches_finite_differences("f64ad_dot", |x| vec![f64ad_dot(&x[..2], &x[2..])], &x);
+        assert_composite_matches_finite_differences("f64ad_norm_squared", |x| vec![f64ad_norm_squared(x)], &x);
+        assert_composite_matches_finite_differences("f64ad_logsumexp", |x| vec![f64ad_logsumexp(x)], &x);
+        assert_composite_matches_finite_differences("f64ad_softmax", f64ad_softmax, &x);
+    }
+}
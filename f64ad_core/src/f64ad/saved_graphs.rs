@@ -0,0 +1,153 @@
+// Saving and loading of tracer and locked graphs.  A graph is traced once, e.g., in a build step,
+// saved with any serde format, and loaded at runtime as a locked graph that can be evaluated on new
+// inputs and differentiated without re-running the traced code.
+
+use std::fmt::{Display, Formatter};
+use serde::{Serialize, Deserialize};
+use crate::f64ad::{ComputationGraph, DerivativePolicy, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::f64ad_var_l_mod::ComputationGraphL;
+use crate::f64ad::tape::{Operand, Tape};
+
+/// Incremented whenever the layout of `SavedComputationGraph` changes.
+pub const SAVED_COMPUTATION_GRAPH_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedNode {
+    pub node_type_class: NodeTypeClass,
+    pub node_operands_mode: NodeOperandsMode,
+    /// Variable operands refer to earlier nodes by their node idx.
+    pub operands: [Option<Operand>; 2],
+    /// Value of the node when the graph was saved.
+    pub value: f64
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedComputationGraph {
+    version: u32,
+    derivative_policy: DerivativePolicy,
+    nodes: Vec<SavedNode>,
+    /// Node idxs of the input variables, in the order they were spawned.
+    inputs: Vec<usize>,
+    /// Node idxs of the marked outputs.
+    outputs: Vec<usize>
+}
+impl SavedComputationGraph {
+    pub(crate) fn new(computation_graph: &ComputationGraph) -> Self {
+        let (derivative_policy, outputs) = match computation_graph {
+            ComputationGraph::ComputationGraphT(c) => { (c.borrow().derivative_policy(), c.borrow().outputs()) }
+            ComputationGraph::ComputationGraphL(c) => { (c.borrow().derivative_policy(), c.borrow().outputs().clone()) }
+            _ => { panic!("only tracer and locked graphs can be saved.") }
+        };
+
+        computation_graph.with_tape(|tape| {
+            let nodes: Vec<SavedNode> = (0..tape.len()).map(|node_idx| {
+                SavedNode {
+                    node_type_class: tape.node_type_class(node_idx),
+                    node_operands_mode: tape.node_operands_mode(node_idx),
+                    operands: tape.operands(node_idx),
+                    value: tape.value(node_idx)
+                }
+            }).collect();
+            let inputs = (0..tape.len()).filter(|x| nodes[*x].node_type_class == NodeTypeClass::InputVariable).collect();
+
+            Self {
+                version: SAVED_COMPUTATION_GRAPH_VERSION,
+                derivative_policy,
+                nodes,
+                inputs,
+                outputs
+            }
+        })
+    }
+    /// Checks the saved graph and rebuilds it as a locked graph.  Saved graphs are usually read
+    /// from files at runtime, so a graph that does not check out is reported as an error rather
+    /// than a panic.
+    pub(crate) fn to_locked(&self) -> Result<ComputationGraphL, LoadError> {
+        if self.version != SAVED_COMPUTATION_GRAPH_VERSION { return Err(LoadError::UnsupportedVersion { version: self.version }); }
+
+        let mut tape = Tape::new();
+        for (node_idx, node) in self.nodes.iter().enumerate() {
+            if let NodeTypeClass::Manual { .. } | NodeTypeClass::Composite { .. } = node.node_type_class { return Err(LoadError::UnsupportedNode { node_idx }); }
+            let num_operands = node.operands.iter().take_while(|x| x.is_some()).count();
+            if num_operands != expected_num_operands(node.node_type_class) || node.operands[num_operands..].iter().any(|x| x.is_some()) {
+                return Err(LoadError::WrongNumberOfOperands { node_idx });
+            }
+            for operand in node.operands.iter().flatten() {
+                if let Operand::Variable(parent) = operand {
+                    if *parent >= node_idx { return Err(LoadError::ParentNotBefore { node_idx, parent: *parent }); }
+                }
+            }
+            tape.push_operands(node.value, node.node_type_class, node.operands);
+            if tape.node_operands_mode(node_idx) != node.node_operands_mode { return Err(LoadError::OperandsModeMismatch { node_idx }); }
+        }
+
+        let inputs: Vec<usize> = (0..tape.len()).filter(|x| self.nodes[*x].node_type_class == NodeTypeClass::InputVariable).collect();
+        if inputs != self.inputs { return Err(LoadError::InputsMismatch); }
+        if let Some(output) = self.outputs.iter().find(|x| **x >= tape.len()) { return Err(LoadError::OutputOutOfRange { output: *output }); }
+
+        Ok(ComputationGraphL::new(self.derivative_policy, tape, self.outputs.clone()))
+    }
+    #[inline(always)]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+    #[inline(always)]
+    pub fn derivative_policy(&self) -> DerivativePolicy {
+        self.derivative_policy
+    }
+    #[inline(always)]
+    pub fn nodes(&self) -> &Vec<SavedNode> {
+        &self.nodes
+    }
+    #[inline(always)]
+    pub fn inputs(&self) -> &Vec<usize> {
+        &self.inputs
+    }
+    #[inline(always)]
+    pub fn outputs(&self) -> &Vec<usize> {
+        &self.outputs
+    }
+}
+
+/// Number of operands of a node of class `node_type_class`, which always fill the operand slots
+/// from the lhs.
+fn expected_num_operands(node_type_class: NodeTypeClass) -> usize {
+    match node_type_class {
+        NodeTypeClass::InputVariable | NodeTypeClass::Composite { .. } => { 0 }
+        NodeTypeClass::Add | NodeTypeClass::Mul | NodeTypeClass::Sub | NodeTypeClass::Div | NodeTypeClass::Max | NodeTypeClass::Min | NodeTypeClass::Atan2 | NodeTypeClass::Log | NodeTypeClass::Powf => { 2 }
+        _ => { 1 }
+    }
+}
+
+/// Why a saved graph could not be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// The graph was saved with a different `SAVED_COMPUTATION_GRAPH_VERSION`.
+    UnsupportedVersion { version: u32 },
+    /// Manual and composite nodes (e.g., checkpoint regions) cannot be evaluated on locked graphs.
+    UnsupportedNode { node_idx: usize },
+    /// The node has a different number of operands than its node type class takes.
+    WrongNumberOfOperands { node_idx: usize },
+    /// The node refers to a parent that does not come before it.
+    ParentNotBefore { node_idx: usize, parent: usize },
+    /// The saved operands mode of the node does not match its operands.
+    OperandsModeMismatch { node_idx: usize },
+    /// The saved input list does not match the input variable nodes.
+    InputsMismatch,
+    /// A marked output is not one of the nodes.
+    OutputOutOfRange { output: usize }
+}
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::UnsupportedVersion { version } => { write!(f, "saved computation graph has version {}, but only version {} is supported.", version, SAVED_COMPUTATION_GRAPH_VERSION) }
+            LoadError::UnsupportedNode { node_idx } => { write!(f, "node {} of saved computation graph is a manual or composite node, which cannot be evaluated on a locked graph.", node_idx) }
+            LoadError::WrongNumberOfOperands { node_idx } => { write!(f, "node {} of saved computation graph has the wrong number of operands for its node type class.", node_idx) }
+            LoadError::ParentNotBefore { node_idx, parent } => { write!(f, "node {} of saved computation graph refers to node {}, which does not come before it.", node_idx, parent) }
+            LoadError::OperandsModeMismatch { node_idx } => { write!(f, "operands mode of node {} of saved computation graph does not match its operands.", node_idx) }
+            LoadError::InputsMismatch => { write!(f, "inputs of saved computation graph do not match its input variable nodes.") }
+            LoadError::OutputOutOfRange { output } => { write!(f, "output {} of saved computation graph is not one of its nodes.", output) }
+        }
+    }
+}
+impl std::error::Error for LoadError { }
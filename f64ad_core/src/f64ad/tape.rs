@@ -0,0 +1,545 @@
+// Compact struct-of-arrays storage for the nodes of a computation graph.  Instead of storing a
+// `NodeTypeClass` and two `Option<f64ad>` parents per node, each node only takes up its value, a
+// two byte op code, and two `u32` operand slots.
+
+use serde::{Serialize, Deserialize};
+use crate::f64ad::{ComputationGraph, DerivativePolicy, f64ad, F64adType, NodeOperandsMode, NodeTypeClass};
+use crate::f64ad::composite_operations::{composite_backwards_mode_step_f64, composite_forward_mode_step_f64, CompositeRecord};
+use crate::f64ad::derivative_rules::derivatives;
+
+/// What a node stores in one of its two operand slots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperandKind {
+    /// The slot is unused.
+    None,
+    /// The slot holds the node idx of a variable parent.
+    Variable,
+    /// The slot holds an index into the tape's constant pool.
+    Constant
+}
+
+/// An operand of a node: either a variable parent, given by its node idx, or a constant.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Operand {
+    Variable(usize),
+    Constant(f64)
+}
+
+/// A node type class (without its payload) together with the kinds of both operand slots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpCode {
+    node_type_class: u8,
+    operand_kinds: u8
+}
+impl OpCode {
+    fn new(node_type_class: &NodeTypeClass, operand_kinds: [OperandKind; 2]) -> Self {
+        let encode_kind = |x: OperandKind| -> u8 {
+            match x {
+                OperandKind::None => { 0 }
+                OperandKind::Variable => { 1 }
+                OperandKind::Constant => { 2 }
+            }
+        };
+        Self {
+            node_type_class: encode_node_type_class(node_type_class),
+            operand_kinds: encode_kind(operand_kinds[0]) | (encode_kind(operand_kinds[1]) << 2)
+        }
+    }
+    #[inline(always)]
+    pub fn operand_kind(&self, slot: usize) -> OperandKind {
+        match (self.operand_kinds >> (2 * slot)) & 0b11 {
+            0 => { OperandKind::None }
+            1 => { OperandKind::Variable }
+            _ => { OperandKind::Constant }
+        }
+    }
+    #[inline(always)]
+    pub fn node_operands_mode(&self) -> NodeOperandsMode {
+        match (self.operand_kind(0), self.operand_kind(1)) {
+            (OperandKind::Variable, OperandKind::Variable) => { NodeOperandsMode::TwoParents }
+            (OperandKind::Variable, _) => { NodeOperandsMode::OneParentLHS }
+            (_, OperandKind::Variable) => { NodeOperandsMode::OneParentRHS }
+            _ => { NodeOperandsMode::NoParents }
+        }
+    }
+    #[inline(always)]
+    fn is_input_variable(&self) -> bool {
+        self.node_type_class == INPUT_VARIABLE_CODE
+    }
+    #[inline(always)]
+    fn is_composite(&self) -> bool {
+        self.node_type_class == COMPOSITE_CODE
+    }
+}
+
+const INPUT_VARIABLE_CODE: u8 = 0;
+const MANUAL_CODE: u8 = 32;
+const COMPOSITE_CODE: u8 = 33;
+const LN_CODE: u8 = 34;
+
+pub(crate) fn encode_node_type_class(node_type_class: &NodeTypeClass) -> u8 {
+    match node_type_class {
+        NodeTypeClass::InputVariable => { INPUT_VARIABLE_CODE }
+        NodeTypeClass::Add => { 1 }
+        NodeTypeClass::Mul => { 2 }
+        NodeTypeClass::Sub => { 3 }
+        NodeTypeClass::Div => { 4 }
+        NodeTypeClass::Neg => { 5 }
+        NodeTypeClass::Abs => { 6 }
+        NodeTypeClass::Signum => { 7 }
+        NodeTypeClass::Max => { 8 }
+        NodeTypeClass::Min => { 9 }
+        NodeTypeClass::Atan2 => { 10 }
+        NodeTypeClass::Floor => { 11 }
+        NodeTypeClass::Ceil => { 12 }
+        NodeTypeClass::Round => { 13 }
+        NodeTypeClass::Trunc => { 14 }
+        NodeTypeClass::Fract => { 15 }
+        NodeTypeClass::Sin => { 16 }
+        NodeTypeClass::Cos => { 17 }
+        NodeTypeClass::Tan => { 18 }
+        NodeTypeClass::Asin => { 19 }
+        NodeTypeClass::Acos => { 20 }
+        NodeTypeClass::Atan => { 21 }
+        NodeTypeClass::Sinh => { 22 }
+        NodeTypeClass::Cosh => { 23 }
+        NodeTypeClass::Tanh => { 24 }
+        NodeTypeClass::Asinh => { 25 }
+        NodeTypeClass::Acosh => { 26 }
+        NodeTypeClass::Atanh => { 27 }
+        NodeTypeClass::Log => { 28 }
+        NodeTypeClass::Ln => { LN_CODE }
+        NodeTypeClass::Sqrt => { 29 }
+        NodeTypeClass::Exp => { 30 }
+        NodeTypeClass::Powf => { 31 }
+        NodeTypeClass::Manual { .. } => { MANUAL_CODE }
+        NodeTypeClass::Composite { .. } => { COMPOSITE_CODE }
+    }
+}
+
+/// `payload` is only used for `Manual` (the index of its value in the constant pool, followed by its
+/// derivative) and `Composite` (composite idx and output idx).
+fn decode_node_type_class(code: u8, payload: [u32; 2], constants: &[f64]) -> NodeTypeClass {
+    match code {
+        INPUT_VARIABLE_CODE => { NodeTypeClass::InputVariable }
+        1 => { NodeTypeClass::Add }
+        2 => { NodeTypeClass::Mul }
+        3 => { NodeTypeClass::Sub }
+        4 => { NodeTypeClass::Div }
+        5 => { NodeTypeClass::Neg }
+        6 => { NodeTypeClass::Abs }
+        7 => { NodeTypeClass::Signum }
+        8 => { NodeTypeClass::Max }
+        9 => { NodeTypeClass::Min }
+        10 => { NodeTypeClass::Atan2 }
+        11 => { NodeTypeClass::Floor }
+        12 => { NodeTypeClass::Ceil }
+        13 => { NodeTypeClass::Round }
+        14 => { NodeTypeClass::Trunc }
+        15 => { NodeTypeClass::Fract }
+        16 => { NodeTypeClass::Sin }
+        17 => { NodeTypeClass::Cos }
+        18 => { NodeTypeClass::Tan }
+        19 => { NodeTypeClass::Asin }
+        20 => { NodeTypeClass::Acos }
+        21 => { NodeTypeClass::Atan }
+        22 => { NodeTypeClass::Sinh }
+        23 => { NodeTypeClass::Cosh }
+        24 => { NodeTypeClass::Tanh }
+        25 => { NodeTypeClass::Asinh }
+        26 => { NodeTypeClass::Acosh }
+        27 => { NodeTypeClass::Atanh }
+        28 => { NodeTypeClass::Log }
+        LN_CODE => { NodeTypeClass::Ln }
+        29 => { NodeTypeClass::Sqrt }
+        30 => { NodeTypeClass::Exp }
+        31 => { NodeTypeClass::Powf }
+        MANUAL_CODE => {
+            let idx = payload[1] as usize;
+            NodeTypeClass::Manual { value: constants[idx], derivative: constants[idx + 1] }
+        }
+        COMPOSITE_CODE => { NodeTypeClass::Composite { composite_idx: payload[0] as usize, output_idx: payload[1] as usize } }
+        _ => { unreachable!("invalid node type class code: {}", code) }
+    }
+}
+
+/// A position on the tape of a graph, returned by `GlobalComputationGraph::mark`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TapeMark {
+    pub (crate) computation_graph_id: usize,
+    pub (crate) generation: u32,
+    pub (crate) num_nodes: usize,
+    pub (crate) num_constants: usize,
+    pub (crate) num_composites: usize
+}
+impl TapeMark {
+    #[inline(always)]
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+}
+
+#[derive(Clone)]
+pub struct Tape {
+    values: Vec<f64>,
+    op_codes: Vec<OpCode>,
+    operands: Vec<[u32; 2]>,
+    constants: Vec<f64>,
+    /// Bumped on every rewind and clear.  Variables remember the generation they were created in.
+    generation: u32,
+    /// Variables created before this generation were invalidated by a clear.
+    first_valid_generation: u32,
+    /// Runs of generations with the same floor, as `(first generation of the run, floor)`, sorted
+    /// by generation and starting at `first_valid_generation`.  The floor of generation `g` is the
+    /// smallest length the tape has been rewound to since `g`, i.e., variables of generation `g` at
+    /// or above it no longer exist.  Floors never decrease with the generation, and rewinding lowers
+    /// a suffix of them to a single value, so there is one run per distinct floor (e.g., repeatedly
+    /// rewinding to the same mark keeps two runs).
+    floors: Vec<(u32, u32)>
+}
+impl Tape {
+    pub fn new() -> Self {
+        Self {
+            values: Vec::with_capacity(1_000_000),
+            op_codes: Vec::with_capacity(1_000_000),
+            operands: Vec::with_capacity(1_000_000),
+            constants: Vec::new(),
+            generation: 0,
+            first_valid_generation: 0,
+            floors: vec![(0, u32::MAX)]
+        }
+    }
+    /// Adds a node to the end of the tape and returns its node idx.
+    #[inline(always)]
+    pub(crate) fn push(&mut self, value: f64, node_type_class: NodeTypeClass, parent_0: Option<f64ad>, parent_1: Option<f64ad>) -> usize {
+        self.push_operands(value, node_type_class, [to_operand(&parent_0), to_operand(&parent_1)])
+    }
+    /// Same as `push`, with the operands given directly.  Variable operands must refer to nodes that
+    /// are already on the tape.
+    #[inline(always)]
+    pub(crate) fn push_operands(&mut self, value: f64, node_type_class: NodeTypeClass, operands: [Option<Operand>; 2]) -> usize {
+        let node_idx = self.values.len();
+        assert!(node_idx < u32::MAX as usize, "computation graph is limited to {} nodes.", u32::MAX);
+
+        let (op_code, operands) = self.encode(node_type_class, operands);
+        self.values.push(value);
+        self.op_codes.push(op_code);
+        self.operands.push(operands);
+
+        node_idx
+    }
+    /// Overwrites the value and operands of an existing node.  Returns false, without changing
+    /// anything, if the node has a different node type class or operand kinds.
+    pub(crate) fn overwrite(&mut self, node_idx: usize, value: f64, node_type_class: NodeTypeClass, parent_0: Option<f64ad>, parent_1: Option<f64ad>) -> bool {
+        if node_idx >= self.len() { return false; }
+        let operands = [to_operand(&parent_0), to_operand(&parent_1)];
+        let op_code = self.op_codes[node_idx];
+        if op_code != OpCode::new(&node_type_class, operands.map(|x| operand_kind(&x))) { return false; }
+        if let NodeTypeClass::Manual { .. } | NodeTypeClass::Composite { .. } = node_type_class { return false; }
+
+        self.values[node_idx] = value;
+        for (slot, operand) in operands.iter().enumerate() {
+            match operand {
+                None => { }
+                Some(Operand::Variable(parent)) => { self.operands[node_idx][slot] = *parent as u32; }
+                Some(Operand::Constant(constant)) => { self.constants[self.operands[node_idx][slot] as usize] = *constant; }
+            }
+        }
+
+        true
+    }
+    /// Sets the value of an existing node without changing its operands.
+    #[inline(always)]
+    pub(crate) fn set_value(&mut self, node_idx: usize, value: f64) {
+        self.values[node_idx] = value;
+    }
+    fn encode(&mut self, node_type_class: NodeTypeClass, operands: [Option<Operand>; 2]) -> (OpCode, [u32; 2]) {
+        let kinds = operands.map(|x| operand_kind(&x));
+        let mut slots = [0u32; 2];
+        for (slot, operand) in operands.iter().enumerate() {
+            match operand {
+                None => { }
+                Some(Operand::Variable(parent)) => {
+                    assert!(*parent < self.len(), "operand refers to node {}, which is not on the tape.", parent);
+                    slots[slot] = *parent as u32;
+                }
+                Some(Operand::Constant(constant)) => { slots[slot] = self.push_constant(*constant); }
+            }
+        }
+
+        match node_type_class {
+            NodeTypeClass::Manual { value, derivative } => {
+                assert_eq!(kinds[1], OperandKind::None, "manual nodes cannot have a rhs operand.");
+                slots[1] = self.push_constant(value);
+                self.push_constant(derivative);
+            }
+            NodeTypeClass::Composite { composite_idx, output_idx } => {
+                slots = [composite_idx as u32, output_idx as u32];
+            }
+            _ => { }
+        }
+
+        (OpCode::new(&node_type_class, kinds), slots)
+    }
+    fn push_constant(&mut self, value: f64) -> u32 {
+        self.constants.push(value);
+        (self.constants.len() - 1) as u32
+    }
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    #[inline(always)]
+    pub fn value(&self, node_idx: usize) -> f64 {
+        self.values[node_idx]
+    }
+    #[inline(always)]
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+    #[inline(always)]
+    pub fn op_code(&self, node_idx: usize) -> OpCode {
+        self.op_codes[node_idx]
+    }
+    #[inline(always)]
+    pub fn node_type_class(&self, node_idx: usize) -> NodeTypeClass {
+        decode_node_type_class(self.op_codes[node_idx].node_type_class, self.operands[node_idx], &self.constants)
+    }
+    #[inline(always)]
+    pub fn node_operands_mode(&self, node_idx: usize) -> NodeOperandsMode {
+        self.op_codes[node_idx].node_operands_mode()
+    }
+    /// Node idxs of the variable parents of a node.
+    #[inline(always)]
+    pub fn variable_parents(&self, node_idx: usize) -> [Option<usize>; 2] {
+        let op_code = self.op_codes[node_idx];
+        let operands = self.operands[node_idx];
+        [0, 1].map(|slot| if op_code.operand_kind(slot) == OperandKind::Variable { Some(operands[slot] as usize) } else { None })
+    }
+    /// Values of both operands of a node (constant or variable), if present.
+    #[inline(always)]
+    pub fn operand_values(&self, node_idx: usize) -> [Option<f64>; 2] {
+        let op_code = self.op_codes[node_idx];
+        let operands = self.operands[node_idx];
+        [0, 1].map(|slot| {
+            match op_code.operand_kind(slot) {
+                OperandKind::None => { None }
+                OperandKind::Variable => { Some(self.values[operands[slot] as usize]) }
+                OperandKind::Constant => { Some(self.constants[operands[slot] as usize]) }
+            }
+        })
+    }
+    /// Both operands of a node, if present.  Composite nodes have no operands on the tape.
+    #[inline(always)]
+    pub fn operands(&self, node_idx: usize) -> [Option<Operand>; 2] {
+        let op_code = self.op_codes[node_idx];
+        let operands = self.operands[node_idx];
+        if op_code.is_composite() { return [None, None]; }
+        [0, 1].map(|slot| {
+            match op_code.operand_kind(slot) {
+                OperandKind::None => { None }
+                OperandKind::Variable => { Some(Operand::Variable(operands[slot] as usize)) }
+                OperandKind::Constant => { Some(Operand::Constant(self.constants[operands[slot] as usize])) }
+            }
+        })
+    }
+    /// Rebuilds the parents of a node as `f64ad`s, with variable parents on `computation_graph`.
+    pub(crate) fn parents(&self, node_idx: usize, computation_graph: &'static ComputationGraph) -> [Option<f64ad>; 2] {
+        self.operands(node_idx).map(|x| {
+            x.map(|y| {
+                match y {
+                    Operand::Variable(parent) => { computation_graph.variable(parent) }
+                    Operand::Constant(constant) => { f64ad::f64(constant) }
+                }
+            })
+        })
+    }
+    /// Local partial derivatives of a node with respect to its operand slots (zero for slots that
+    /// do not hold a variable).
+    pub(crate) fn local_partials(&self, node_idx: usize, derivative_policy: &DerivativePolicy) -> [f64; 2] {
+        let op_code = self.op_codes[node_idx];
+        if op_code.is_input_variable() || op_code.is_composite() { return [0.0; 2]; }
+
+        let [lhs, rhs] = self.operand_values(node_idx);
+        local_partials_from_values(self.node_type_class(node_idx), op_code.node_operands_mode(), lhs.unwrap(), rhs, derivative_policy)
+    }
+    /// First order backwards mode sweep.  `local_partials` returns the (slot aligned) local partials
+    /// of a node, e.g., precomputed ones or `Tape::local_partials`.
+    pub(crate) fn backwards_mode_grad<F: Fn(usize) -> [f64; 2]>(&self, node_idx: usize, composites: &[CompositeRecord], local_partials: F) -> Vec<f64> {
+        let mut derivs = vec![0.0; self.len()];
+        derivs[node_idx] = 1.0;
+
+        for idx in (0..self.len()).rev() {
+            let op_code = self.op_codes[idx];
+            if op_code.is_input_variable() { continue; }
+            if op_code.is_composite() {
+                // all outputs of a composite are handled at once, when its first output is reached.
+                let [composite_idx, output_idx] = self.operands[idx];
+                if output_idx == 0 { composite_backwards_mode_step_f64(&composites[composite_idx as usize], &mut derivs); }
+                continue;
+            }
+
+            let partials = local_partials(idx);
+            let operands = self.operands[idx];
+            let curr_deriv = derivs[idx];
+            for slot in 0..2 {
+                if op_code.operand_kind(slot) == OperandKind::Variable {
+                    derivs[operands[slot] as usize] += curr_deriv * partials[slot];
+                }
+            }
+        }
+
+        derivs
+    }
+    /// First order forward mode sweep.  See `backwards_mode_grad`.
+    pub(crate) fn forward_mode_grad<F: Fn(usize) -> [f64; 2]>(&self, node_idx: usize, composites: &[CompositeRecord], local_partials: F) -> Vec<f64> {
+        let mut derivs = vec![0.0; self.len()];
+        derivs[node_idx] = 1.0;
+
+        for idx in 0..self.len() {
+            let op_code = self.op_codes[idx];
+            if op_code.is_input_variable() { continue; }
+            if op_code.is_composite() {
+                let [composite_idx, output_idx] = self.operands[idx];
+                if output_idx == 0 { composite_forward_mode_step_f64(&composites[composite_idx as usize], &mut derivs); }
+                continue;
+            }
+
+            let partials = local_partials(idx);
+            let operands = self.operands[idx];
+            for slot in 0..2 {
+                if op_code.operand_kind(slot) == OperandKind::Variable {
+                    derivs[idx] += derivs[operands[slot] as usize] * partials[slot];
+                }
+            }
+        }
+
+        derivs
+    }
+    /// Removes all nodes and invalidates all existing variables.
+    pub fn clear(&mut self) {
+        if self.values.capacity() > 10_000_000 {
+            self.release();
+        } else {
+            self.values.clear();
+            self.op_codes.clear();
+            self.operands.clear();
+            self.constants.clear();
+            self.invalidate_all();
+        }
+    }
+    /// Same as `clear`, but also frees the memory used by the tape.
+    pub fn release(&mut self) {
+        let generation = self.generation;
+        *self = Self::new();
+        self.generation = generation;
+        self.invalidate_all();
+    }
+    fn invalidate_all(&mut self) {
+        // after the last generation, generations start over.  This can only make variables from
+        // more than `u32::MAX` clears ago look valid again.
+        self.generation = self.generation.checked_add(1).unwrap_or(0);
+        self.first_valid_generation = self.generation;
+        self.floors = vec![(self.generation, u32::MAX)];
+    }
+    #[inline(always)]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+    pub(crate) fn mark(&self, computation_graph_id: usize, num_composites: usize) -> TapeMark {
+        TapeMark {
+            computation_graph_id,
+            generation: self.generation,
+            num_nodes: self.len(),
+            num_constants: self.constants.len(),
+            num_composites
+        }
+    }
+    /// Returns true if nodes up to `num_nodes` from `generation` are all still on the tape.
+    pub(crate) fn is_prefix_valid(&self, num_nodes: usize, generation: u32) -> bool {
+        generation >= self.first_valid_generation && generation <= self.generation &&
+            num_nodes <= self.floor(generation) as usize && num_nodes <= self.len()
+    }
+    fn floor(&self, generation: u32) -> u32 {
+        let run_idx = self.floors.partition_point(|(first_generation, _)| *first_generation <= generation);
+        self.floors[run_idx - 1].1
+    }
+    /// Returns false if the generation cannot be bumped by another rewind.  The graph then has to be
+    /// cleared instead.
+    pub(crate) fn can_rewind(&self) -> bool {
+        self.generation < u32::MAX
+    }
+    /// Returns true if the variable at `node_idx` that was created in `generation` still exists.
+    #[inline(always)]
+    pub(crate) fn is_valid(&self, node_idx: usize, generation: u32) -> bool {
+        self.is_prefix_valid(node_idx + 1, generation)
+    }
+    /// Removes all nodes added after `mark`, and invalidates the variables that referred to them.
+    pub(crate) fn rewind_to(&mut self, mark: &TapeMark, computation_graph_id: usize) {
+        assert_eq!(mark.computation_graph_id, computation_graph_id, "mark is from a different computation graph, or the graph was reset since it was made.");
+        assert!(self.is_prefix_valid(mark.num_nodes, mark.generation), "mark is no longer valid, the graph was rewound to an earlier mark since it was made.");
+
+        self.values.truncate(mark.num_nodes);
+        self.op_codes.truncate(mark.num_nodes);
+        self.operands.truncate(mark.num_nodes);
+        self.constants.truncate(mark.num_constants);
+
+        // floors never decrease with the generation, so only the runs of a suffix have to be
+        // lowered, and they merge into a single run.
+        let num_nodes = mark.num_nodes as u32;
+        let mut first_lowered_generation = None;
+        while let Some((first_generation, floor)) = self.floors.last().copied() {
+            if floor <= num_nodes { break; }
+            first_lowered_generation = Some(first_generation);
+            self.floors.pop();
+        }
+        if let Some(first_lowered_generation) = first_lowered_generation {
+            if self.floors.last().map(|x| x.1) != Some(num_nodes) { self.floors.push((first_lowered_generation, num_nodes)); }
+        }
+        self.generation = self.generation.checked_add(1).expect("tape generation overflowed, the graph should have been cleared instead.");
+        self.floors.push((self.generation, u32::MAX));
+    }
+    /// Number of bytes used by the nodes currently on the tape.
+    pub fn size_in_bytes(&self) -> usize {
+        self.len() * (std::mem::size_of::<f64>() + std::mem::size_of::<OpCode>() + std::mem::size_of::<[u32; 2]>()) + self.constants.len() * std::mem::size_of::<f64>()
+    }
+}
+impl Default for Tape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline(always)]
+fn to_operand(parent: &Option<f64ad>) -> Option<Operand> {
+    parent.map(|x| if x.map_to_type() == F64adType::F64 { Operand::Constant(x.value()) } else { Operand::Variable(x.node_idx()) })
+}
+
+#[inline(always)]
+fn operand_kind(operand: &Option<Operand>) -> OperandKind {
+    match operand {
+        None => { OperandKind::None }
+        Some(Operand::Variable(_)) => { OperandKind::Variable }
+        Some(Operand::Constant(_)) => { OperandKind::Constant }
+    }
+}
+
+/// Slot aligned local partial derivatives of a (non input, non composite) node from its operand
+/// values.
+pub(crate) fn local_partials_from_values(node_type_class: NodeTypeClass, node_operands_mode: NodeOperandsMode, lhs: f64, rhs: Option<f64>, derivative_policy: &DerivativePolicy) -> [f64; 2] {
+    let derivatives = match node_operands_mode {
+        NodeOperandsMode::NoParents => { return [0.0; 2]; }
+        _ => { derivatives(lhs, rhs, node_type_class, node_operands_mode, derivative_policy) }
+    };
+
+    match node_operands_mode {
+        NodeOperandsMode::TwoParents => { [derivatives[0], derivatives[1]] }
+        NodeOperandsMode::OneParentLHS => { [derivatives[0], 0.0] }
+        NodeOperandsMode::OneParentRHS => { [0.0, derivatives[0]] }
+        NodeOperandsMode::NoParents => { unreachable!() }
+    }
+}
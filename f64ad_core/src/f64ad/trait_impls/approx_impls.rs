@@ -1,14 +1,16 @@
 use approx::{AbsDiffEq, RelativeEq, UlpsEq};
-use num_traits::Signed;
 use crate::f64ad::f64ad;
 
+// All comparisons are made on the underlying `f64` values (using `f64`'s own `approx` impls), so
+// comparing two `f64ad` variables never adds nodes to a computation graph.
+
 impl UlpsEq for f64ad {
     fn default_max_ulps() -> u32 {
-        unimplemented!("take the time to figure this out.")
+        f64::default_max_ulps()
     }
 
-    fn ulps_eq(&self, _other: &Self, _epsilon: Self::Epsilon, _max_ulps: u32) -> bool {
-        unimplemented!("take the time to figure this out.")
+    fn ulps_eq(&self, other: &Self, epsilon: Self::Epsilon, max_ulps: u32) -> bool {
+        self.value().ulps_eq(&other.value(), epsilon.value(), max_ulps)
     }
 }
 
@@ -20,12 +22,7 @@ impl AbsDiffEq for f64ad {
     }
 
     fn abs_diff_eq(&self, other: &Self, epsilon: Self::Epsilon) -> bool {
-        let diff = *self - *other;
-        if diff.abs() < epsilon {
-            true
-        } else {
-            false
-        }
+        self.value().abs_diff_eq(&other.value(), epsilon.value())
     }
 }
 
@@ -34,12 +31,7 @@ impl RelativeEq for f64ad {
         f64ad::f64(0.000000001)
     }
 
-    fn relative_eq(&self, other: &Self, epsilon: Self::Epsilon, _max_relative: Self::Epsilon) -> bool {
-        let diff = *self - *other;
-        if diff.abs() < epsilon {
-            true
-        } else {
-            false
-        }
+    fn relative_eq(&self, other: &Self, epsilon: Self::Epsilon, max_relative: Self::Epsilon) -> bool {
+        self.value().relative_eq(&other.value(), epsilon.value(), max_relative.value())
     }
-}
\ No newline at end of file
+}
@@ -7,7 +7,7 @@ impl SimdValue for f64ad {
     type SimdBool = bool;
 
     fn lanes() -> usize {
-        4
+        1
     }
 
     fn splat(val: Self::Element) -> Self {
@@ -1,4 +1,7 @@
-use num_traits::{Bounded, FromPrimitive, Num, One, Signed, Zero};
+use std::iter::{Product, Sum};
+use std::num::FpCategory;
+use num_traits::{Bounded, Float, FloatConst, FromPrimitive, Num, NumCast, One, Signed, ToPrimitive, Zero};
+use simba::scalar::{ComplexField, RealField};
 use crate::f64ad::{f64ad, f64ad_universal_function_1_operand, NodeTypeClass};
 
 impl Zero for f64ad {
@@ -35,10 +38,12 @@ impl Num for f64ad {
 impl Signed for f64ad {
     #[cfg_attr(feature = "inline_on", inline)]
     #[cfg_attr(feature = "inline_always_on", inline(always))]
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn abs(&self) -> Self {
         f64ad_universal_function_1_operand(*self, NodeTypeClass::Abs)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn abs_sub(&self, other: &Self) -> Self {
         return if *self <= *other {
             f64ad::f64(0.0)
@@ -47,6 +52,7 @@ impl Signed for f64ad {
         };
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn signum(&self) -> Self {
         f64ad_universal_function_1_operand(*self, NodeTypeClass::Signum)
     }
@@ -78,4 +84,469 @@ impl Bounded for f64ad {
     fn max_value() -> Self {
         Self::f64(f64::MAX)
     }
-}
\ No newline at end of file
+}
+impl ToPrimitive for f64ad {
+    fn to_i64(&self) -> Option<i64> {
+        self.value().to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.value().to_u64()
+    }
+
+    fn to_f32(&self) -> Option<f32> {
+        self.value().to_f32()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(self.value())
+    }
+}
+
+impl NumCast for f64ad {
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        n.to_f64().map(f64ad::f64)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// `Float` shares most of its method names with `ComplexField` and `RealField`, and all three are
+// re-exported from the crate root.  These inherent methods take precedence over all of the trait
+// methods, so calls like `x.sin()` stay unambiguous under a glob import and every trait routes
+// through the same node types.
+impl f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn floor(self) -> f64ad { ComplexField::floor(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn ceil(self) -> f64ad { ComplexField::ceil(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn round(self) -> f64ad { ComplexField::round(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn trunc(self) -> f64ad { ComplexField::trunc(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn fract(self) -> f64ad { ComplexField::fract(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn abs(self) -> f64ad { ComplexField::abs(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn signum(self) -> f64ad { ComplexField::signum(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn mul_add(self, a: f64ad, b: f64ad) -> f64ad { ComplexField::mul_add(self, a, b) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn recip(self) -> f64ad { ComplexField::recip(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn powi(self, n: i32) -> f64ad { ComplexField::powi(self, n) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn powf(self, n: f64ad) -> f64ad { ComplexField::powf(self, n) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn sqrt(self) -> f64ad { ComplexField::sqrt(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn cbrt(self) -> f64ad { ComplexField::cbrt(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn hypot(self, other: f64ad) -> f64ad { ComplexField::hypot(self, other) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn exp(self) -> f64ad { ComplexField::exp(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn exp2(self) -> f64ad { ComplexField::exp2(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn exp_m1(self) -> f64ad { ComplexField::exp_m1(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn ln(self) -> f64ad { ComplexField::ln(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn ln_1p(self) -> f64ad { ComplexField::ln_1p(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn log(self, base: f64ad) -> f64ad { ComplexField::log(self, base) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn log2(self) -> f64ad { ComplexField::log2(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn log10(self) -> f64ad { ComplexField::log10(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn sin(self) -> f64ad { ComplexField::sin(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn cos(self) -> f64ad { ComplexField::cos(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn tan(self) -> f64ad { ComplexField::tan(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn sin_cos(self) -> (f64ad, f64ad) { ComplexField::sin_cos(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn asin(self) -> f64ad { ComplexField::asin(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn acos(self) -> f64ad { ComplexField::acos(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn atan(self) -> f64ad { ComplexField::atan(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn atan2(self, other: f64ad) -> f64ad { RealField::atan2(self, other) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn sinh(self) -> f64ad { ComplexField::sinh(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn cosh(self) -> f64ad { ComplexField::cosh(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn tanh(self) -> f64ad { ComplexField::tanh(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn asinh(self) -> f64ad { ComplexField::asinh(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn acosh(self) -> f64ad { ComplexField::acosh(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn atanh(self) -> f64ad { ComplexField::atanh(self) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn max(self, other: f64ad) -> f64ad { RealField::max(self, other) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn min(self, other: f64ad) -> f64ad { RealField::min(self, other) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn clamp(self, min: f64ad, max: f64ad) -> f64ad { RealField::clamp(self, min, max) }
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    pub fn copysign(self, sign: f64ad) -> f64ad { RealField::copysign(self, sign) }
+}
+
+impl Float for f64ad {
+    fn nan() -> Self {
+        f64ad::f64(f64::NAN)
+    }
+
+    fn infinity() -> Self {
+        f64ad::f64(f64::INFINITY)
+    }
+
+    fn neg_infinity() -> Self {
+        f64ad::f64(f64::NEG_INFINITY)
+    }
+
+    fn neg_zero() -> Self {
+        f64ad::f64(-0.0)
+    }
+
+    fn min_value() -> Self {
+        f64ad::f64(f64::MIN)
+    }
+
+    fn min_positive_value() -> Self {
+        f64ad::f64(f64::MIN_POSITIVE)
+    }
+
+    fn epsilon() -> Self {
+        f64ad::f64(f64::EPSILON)
+    }
+
+    fn max_value() -> Self {
+        f64ad::f64(f64::MAX)
+    }
+
+    fn is_nan(self) -> bool {
+        self.value().is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.value().is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.value().is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.value().is_normal()
+    }
+
+    fn is_subnormal(self) -> bool {
+        self.value().is_subnormal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.value().classify()
+    }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn floor(self) -> Self { self.floor() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn ceil(self) -> Self { self.ceil() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn round(self) -> Self { self.round() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn trunc(self) -> Self { self.trunc() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn fract(self) -> Self { self.fract() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn abs(self) -> Self { self.abs() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn signum(self) -> Self { self.signum() }
+
+    fn is_sign_positive(self) -> bool {
+        self.value().is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.value().is_sign_negative()
+    }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn mul_add(self, a: Self, b: Self) -> Self { self.mul_add(a, b) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn recip(self) -> Self { self.recip() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn powi(self, n: i32) -> Self { self.powi(n) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn powf(self, n: Self) -> Self { self.powf(n) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sqrt(self) -> Self { self.sqrt() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn exp(self) -> Self { self.exp() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn exp2(self) -> Self { self.exp2() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn ln(self) -> Self { self.ln() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn log(self, base: Self) -> Self { self.log(base) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn log2(self) -> Self { self.log2() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn log10(self) -> Self { self.log10() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn to_degrees(self) -> Self { self * (180.0 / std::f64::consts::PI) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn to_radians(self) -> Self { self * (std::f64::consts::PI / 180.0) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn max(self, other: Self) -> Self { self.max(other) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn min(self, other: Self) -> Self { self.min(other) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn clamp(self, min: Self, max: Self) -> Self { self.clamp(min, max) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn abs_sub(self, other: Self) -> Self { <Self as Signed>::abs_sub(&self, &other) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn cbrt(self) -> Self { self.cbrt() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn hypot(self, other: Self) -> Self { self.hypot(other) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sin(self) -> Self { self.sin() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn cos(self) -> Self { self.cos() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn tan(self) -> Self { self.tan() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn asin(self) -> Self { self.asin() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn acos(self) -> Self { self.acos() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn atan(self) -> Self { self.atan() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn atan2(self, other: Self) -> Self { self.atan2(other) }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sin_cos(self) -> (Self, Self) { self.sin_cos() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn exp_m1(self) -> Self { self.exp_m1() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn ln_1p(self) -> Self { self.ln_1p() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sinh(self) -> Self { self.sinh() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn cosh(self) -> Self { self.cosh() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn tanh(self) -> Self { self.tanh() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn asinh(self) -> Self { self.asinh() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn acosh(self) -> Self { self.acosh() }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn atanh(self) -> Self { self.atanh() }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        Float::integer_decode(self.value())
+    }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn copysign(self, sign: Self) -> Self { self.copysign(sign) }
+}
+
+impl FloatConst for f64ad {
+    fn E() -> Self { f64ad::f64(std::f64::consts::E) }
+    fn FRAC_1_PI() -> Self { f64ad::f64(std::f64::consts::FRAC_1_PI) }
+    fn FRAC_1_SQRT_2() -> Self { f64ad::f64(std::f64::consts::FRAC_1_SQRT_2) }
+    fn FRAC_2_PI() -> Self { f64ad::f64(std::f64::consts::FRAC_2_PI) }
+    fn FRAC_2_SQRT_PI() -> Self { f64ad::f64(std::f64::consts::FRAC_2_SQRT_PI) }
+    fn FRAC_PI_2() -> Self { f64ad::f64(std::f64::consts::FRAC_PI_2) }
+    fn FRAC_PI_3() -> Self { f64ad::f64(std::f64::consts::FRAC_PI_3) }
+    fn FRAC_PI_4() -> Self { f64ad::f64(std::f64::consts::FRAC_PI_4) }
+    fn FRAC_PI_6() -> Self { f64ad::f64(std::f64::consts::FRAC_PI_6) }
+    fn FRAC_PI_8() -> Self { f64ad::f64(std::f64::consts::FRAC_PI_8) }
+    fn LN_10() -> Self { f64ad::f64(std::f64::consts::LN_10) }
+    fn LN_2() -> Self { f64ad::f64(std::f64::consts::LN_2) }
+    fn LOG10_E() -> Self { f64ad::f64(std::f64::consts::LOG10_E) }
+    fn LOG2_E() -> Self { f64ad::f64(std::f64::consts::LOG2_E) }
+    fn PI() -> Self { f64ad::f64(std::f64::consts::PI) }
+    fn SQRT_2() -> Self { f64ad::f64(std::f64::consts::SQRT_2) }
+    fn TAU() -> Self { f64ad::f64(std::f64::consts::TAU) }
+    fn LOG10_2() -> Self { f64ad::f64(std::f64::consts::LOG10_2) }
+    fn LOG2_10() -> Self { f64ad::f64(std::f64::consts::LOG2_10) }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Sums and products fold from the first element rather than from a `0.0` or `1.0` constant, so
+// no redundant node is added to the computation graph.  Empty iterators give a plain `f64`.
+impl Sum for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sum<I: Iterator<Item=f64ad>>(mut iter: I) -> Self {
+        match iter.next() {
+            None => f64ad::f64(0.0),
+            Some(first) => iter.fold(first, |acc, x| acc + x)
+        }
+    }
+}
+
+impl<'a> Sum<&'a f64ad> for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn sum<I: Iterator<Item=&'a f64ad>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl Product for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn product<I: Iterator<Item=f64ad>>(mut iter: I) -> Self {
+        match iter.next() {
+            None => f64ad::f64(1.0),
+            Some(first) => iter.fold(first, |acc, x| acc * x)
+        }
+    }
+}
+
+impl<'a> Product<&'a f64ad> for f64ad {
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn product<I: Iterator<Item=&'a f64ad>>(iter: I) -> Self {
+        iter.copied().product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::{Product, Sum};
+    use num_traits::Float;
+    use crate::f64ad::{f64ad, GlobalComputationGraphs};
+
+    /// Checks the value of `f` on `f64ad` against `g`, the same function on `f64`, and its derivatives at `(x, y)` from
+    /// backwards and forward mode sweeps against central differences of `g`.
+    fn check(name: &str, f: fn(f64ad, f64ad) -> f64ad, g: fn(f64, f64) -> f64, x: f64, y: f64) {
+        let computation_graph = GlobalComputationGraphs::get(Some("num_trait_impls_tests"), None);
+        computation_graph.reset();
+        let (xv, yv) = (computation_graph.spawn_variable(x), computation_graph.spawn_variable(y));
+        let out = f(xv, yv);
+        assert!((out.value() - g(x, y)).abs() <= 1e-12 * (1.0 + g(x, y).abs()), "value of {} is {}, but {} on f64.", name, out.value(), g(x, y));
+
+        let h = 1e-6;
+        let expected = [(g(x + h, y) - g(x - h, y)) / (2.0 * h), (g(x, y + h) - g(x, y - h)) / (2.0 * h)];
+        let backwards = out.backwards_mode_grad(false);
+        for (input, expected) in [xv, yv].iter().zip(expected.iter()) {
+            let forward = input.forward_mode_grad(false).wrt(&out).value();
+            for actual in [backwards.wrt(input).value(), forward] {
+                assert!((actual - expected).abs() <= 1e-6 * (1.0 + expected.abs()), "derivative of {} is {}, but finite differences give {}.", name, actual, expected);
+            }
+        }
+    }
+
+    /// Runs `check` on a function written once for any `Float`, so the `f64ad` side goes through
+    /// the `Float` impl.
+    macro_rules! check {
+        ($name:expr, |$x:ident, $y:ident| $body:expr) => { check!($name, |$x, $y| $body, 0.3, 0.8) };
+        ($name:expr, |$x:ident, $y:ident| $body:expr, $x0:expr, $y0:expr) => {{
+            #[allow(unused_variables)]
+            fn f<T: Float + Sum + Product>($x: T, $y: T) -> T { $body }
+            check($name, f::<f64ad>, f::<f64>, $x0, $y0);
+        }};
+    }
+
+    #[test]
+    fn float_methods() {
+        check!("floor", |x, y| x.floor() + y, 1.3, 0.8);
+        check!("ceil", |x, y| x.ceil() * y, 1.3, 0.8);
+        check!("round", |x, y| x.round() * y, 1.3, 0.8);
+        check!("trunc", |x, y| x.trunc() * y, -1.3, 0.8);
+        check!("fract", |x, y| x.fract() * y, 1.3, 0.8);
+        check!("abs", |x, y| x.abs() * y, -0.7, 0.8);
+        check!("signum", |x, y| x.signum() * y, -0.7, 0.8);
+        check!("mul_add", |x, y| x.mul_add(y, x));
+        check!("recip", |x, y| x.recip() + y);
+        check!("powi", |x, y| x.powi(3) * y);
+        check!("powf", |x, y| x.powf(y));
+        check!("sqrt", |x, y| x.sqrt() * y);
+        check!("cbrt", |x, y| x.cbrt() * y);
+        check!("exp", |x, y| x.exp() * y);
+        check!("exp2", |x, y| x.exp2() * y);
+        check!("exp_m1", |x, y| x.exp_m1() * y);
+        check!("ln", |x, y| x.ln() * y);
+        check!("ln_1p", |x, y| x.ln_1p() * y);
+        check!("log", |x, y| x.log(y + T::one()));
+        check!("log2", |x, y| x.log2() * y);
+        check!("log10", |x, y| x.log10() * y);
+        check!("to_degrees", |x, y| x.to_degrees() * y);
+        check!("to_radians", |x, y| x.to_radians() * y);
+        check!("max", |x, y| x.max(y));
+        check!("min", |x, y| x.min(y));
+        check!("clamp", |x, y| x.clamp(T::zero(), y), 0.3, 0.8);
+        check!("clamp above", |x, y| x.clamp(T::zero(), y), 0.9, 0.8);
+        check!("abs_sub", |x, y| y.abs_sub(x));
+        check!("hypot", |x, y| x.hypot(y));
+        check!("sin", |x, y| x.sin() * y);
+        check!("cos", |x, y| x.cos() * y);
+        check!("tan", |x, y| x.tan() * y);
+        check!("sin_cos", |x, y| { let (s, c) = x.sin_cos(); s * y + c });
+        check!("asin", |x, y| x.asin() * y);
+        check!("acos", |x, y| x.acos() * y);
+        check!("atan", |x, y| x.atan() * y);
+        check!("atan2", |x, y| x.atan2(y));
+        check!("sinh", |x, y| x.sinh() * y);
+        check!("cosh", |x, y| x.cosh() * y);
+        check!("tanh", |x, y| x.tanh() * y);
+        check!("asinh", |x, y| x.asinh() * y);
+        check!("acosh", |x, y| (x + T::one()).acosh() * y);
+        check!("atanh", |x, y| x.atanh() * y);
+        check!("copysign", |x, y| x.copysign(y - T::one()) * y);
+        check!("sum", |x, y| [x, y, x * y].iter().copied().sum::<T>());
+        check!("product", |x, y| [x, y, x + y].iter().copied().product::<T>());
+    }
+
+    #[test]
+    fn empty_sum_and_product() {
+        assert_eq!(Vec::<f64ad>::new().into_iter().sum::<f64ad>(), f64ad::f64(0.0));
+        assert_eq!(Vec::<f64ad>::new().into_iter().product::<f64ad>(), f64ad::f64(1.0));
+    }
+}
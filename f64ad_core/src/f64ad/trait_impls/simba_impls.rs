@@ -12,6 +12,7 @@ impl RealField for f64ad {
         return self.is_negative();
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn copysign(self, sign: Self) -> Self {
         return if sign.is_positive() {
             self.abs()
@@ -20,19 +21,23 @@ impl RealField for f64ad {
         };
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn max(self, other: Self) -> Self {
         f64ad_universal_function_2_operands(self, other, NodeTypeClass::Max)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn min(self, other: Self) -> Self {
         f64ad_universal_function_2_operands(self, other, NodeTypeClass::Min)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn clamp(self, min: Self, max: Self) -> Self {
         assert!(min <= max);
         return self.max(min).min(max);
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn atan2(self, other: Self) -> Self {
         f64ad_universal_function_2_operands(self, other, NodeTypeClass::Atan2)
     }
@@ -115,124 +120,172 @@ impl ComplexField for f64ad {
 
     fn imaginary(self) -> Self::RealField { Self::zero() }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn modulus(self) -> Self::RealField { return self.abs(); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn modulus_squared(self) -> Self::RealField { self * self }
 
-    fn argument(self) -> Self::RealField { unimplemented!(); }
+    /// `0` for positive values and `pi` for negative ones, as `atan2(0, self)`.
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn argument(self) -> Self::RealField { f64ad::f64(0.0).atan2(self) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn norm1(self) -> Self::RealField { return self.abs(); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn scale(self, factor: Self::RealField) -> Self { return self * factor; }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn unscale(self, factor: Self::RealField) -> Self { return self / factor; }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn floor(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Floor)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn ceil(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Ceil)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn round(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Round)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn trunc(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Trunc)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn fract(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Fract)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn mul_add(self, a: Self, b: Self) -> Self { return (self * a) + b; }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn abs(self) -> Self::RealField {
         <Self as Signed>::abs(&self)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
+    fn signum(self) -> Self {
+        <Self as Signed>::signum(&self)
+    }
+
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn hypot(self, other: Self) -> Self::RealField {
         return (self.powi(2) + other.powi(2)).sqrt();
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn recip(self) -> Self { return 1.0 / self; }
 
     fn conjugate(self) -> Self { return self; }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sin(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Sin)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn cos(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Cos)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sin_cos(self) -> (Self, Self) {
         return (self.sin(), self.cos());
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn tan(self) -> Self { f64ad_universal_function_1_operand(self, NodeTypeClass::Tan) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn asin(self) -> Self { f64ad_universal_function_1_operand(self, NodeTypeClass::Asin) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn acos(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Acos)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn atan(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Atan)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sinh(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Sinh)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn cosh(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Cosh)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn tanh(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Tanh)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn asinh(self) -> Self { f64ad_universal_function_1_operand(self, NodeTypeClass::Asinh) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn acosh(self) -> Self {
         f64ad_universal_function_1_operand(self, NodeTypeClass::Acosh)
     }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn atanh(self) -> Self { f64ad_universal_function_1_operand(self, NodeTypeClass::Atanh) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn log(self, base: Self::RealField) -> Self { f64ad_universal_function_2_operands(self, base, NodeTypeClass::Log) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn log2(self) -> Self { return self.log(f64ad::f64(2.0)); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn log10(self) -> Self { return self.log(f64ad::f64(10.0)); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn ln(self) -> Self { return self.log(f64ad::f64(std::f64::consts::E)); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn ln_1p(self) -> Self { (1.0 + self).ln() }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn sqrt(self) -> Self { f64ad_universal_function_1_operand(self, NodeTypeClass::Sqrt) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn exp(self) -> Self { f64ad_universal_function_1_operand(self, NodeTypeClass::Exp) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn exp2(self) -> Self { f64ad::f64(2.0).powf(self) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn exp_m1(self) -> Self { return self.exp() - 1.0; }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn powi(self, n: i32) -> Self { return self.powf(f64ad::f64(n as f64)); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn powf(self, n: Self::RealField) -> Self { f64ad_universal_function_2_operands(self, n, NodeTypeClass::Powf) }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn powc(self, n: Self) -> Self { return self.powf(n); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn cbrt(self) -> Self { return self.powf(f64ad::f64(1.0 / 3.0)); }
 
     fn is_finite(&self) -> bool { return self.value().is_finite(); }
 
+    #[cfg_attr(feature = "track_caller", track_caller)]
     fn try_sqrt(self) -> Option<Self> {
         Some(self.sqrt())
     }
@@ -23,6 +23,14 @@
 //! meaning it can be used in any `nalgebra` or `ndarray` computations.
 //! - Certain functions can be pre-computed and locked to boost performance at run-time.
 //!
+//! ## Composite operations
+//! The reductions in `f64ad::reductions` (e.g., `f64ad_sum`, `f64ad_dot`, `f64ad_softmax`) and the
+//! matrix operations in `f64ad::linear_algebra` record a whole operation as a single composite node,
+//! which keeps tapes small.  Composite nodes only have first order derivatives, so they are only
+//! recorded on `ComputationGraph1` graphs.  On `ComputationGraphF`, `ComputationGraphT`, and
+//! `ComputationGraphL` graphs, the same functions record ordinary nodes instead (or panic where that
+//! is not possible, see each module), so they do not save any tape there.
+//!
 //! ## Crate structure
 //! This crate is a cargo workspace with two member crates: (1) `f64ad_core`; and (2) `f64ad_core_derive`.
 //! All core implementations for f64ad_ can be found in `f64ad_core`.  The `f64ad_core_derive` is